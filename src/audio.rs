@@ -0,0 +1,72 @@
+use bevy::audio::Volume;
+use bevy::prelude::*;
+use crate::config;
+use crate::settings::Settings;
+use crate::sim::SimEvent;
+use crate::state;
+
+#[derive(Resource)]
+pub struct SoundEffects {
+    pub hit: Handle<AudioSource>,
+    pub pole: Handle<AudioSource>,
+    pub wall: Handle<AudioSource>,
+    pub point: Handle<AudioSource>,
+    pub win: Handle<AudioSource>,
+}
+
+pub fn load_sound_effects(mut commands: Commands, asset_server: Res<AssetServer>) {
+    commands.insert_resource(SoundEffects {
+        hit: asset_server.load(config::ASSET_SOUND_HIT),
+        pole: asset_server.load(config::ASSET_SOUND_POLE),
+        wall: asset_server.load(config::ASSET_SOUND_WALL),
+        point: asset_server.load(config::ASSET_SOUND_POINT),
+        win: asset_server.load(config::ASSET_SOUND_WIN),
+    });
+}
+
+pub fn play_sound_effects(
+    mut commands: Commands,
+    mut events: MessageReader<state::GameEvent>,
+    sounds: Res<SoundEffects>,
+    settings: Res<Settings>,
+) {
+    let base_volume = settings.master_volume * settings.sfx_volume;
+    if base_volume <= 0.0 {
+        events.clear();
+        return;
+    }
+
+    let events: Vec<SimEvent> = events.read().map(|event| event.0).collect();
+    // The winning point plays the win jingle on its own rather than under the point chime.
+    let match_won = events.iter().any(|event| matches!(event, SimEvent::MatchWon { .. }));
+    for event in events {
+        let (sound, intensity) = match event {
+            SimEvent::PointScored { .. } if match_won => continue,
+            SimEvent::PlayerHit { speed, .. } => (&sounds.hit, Some(speed)),
+            SimEvent::PoleHit { speed } => (&sounds.pole, Some(speed)),
+            SimEvent::WallBounce { speed } => (&sounds.wall, Some(speed)),
+            SimEvent::Fault { .. } | SimEvent::SetWon { .. } => continue,
+            SimEvent::PointScored { .. } | SimEvent::Dropped { .. } => (&sounds.point, None),
+            SimEvent::MatchWon { .. } => (&sounds.win, None),
+            SimEvent::Served { .. } | SimEvent::Launched => (&sounds.hit, None),
+            SimEvent::Returned { .. } => (&sounds.point, None),
+            SimEvent::Missed => continue,
+        };
+
+        // Harder impacts are louder and slightly higher pitched.
+        let (volume, pitch) = match intensity {
+            Some(speed) => {
+                let t = (speed / config::BALL_MAX_SPEED).clamp(0.1, 1.2);
+                (0.35 + 0.55 * t, 0.85 + 0.3 * t)
+            }
+            None => (1.0, 1.0),
+        };
+
+        commands.spawn((
+            AudioPlayer::new(sound.clone()),
+            PlaybackSettings::DESPAWN
+                .with_volume(Volume::Linear(volume * base_volume))
+                .with_speed(pitch),
+        ));
+    }
+}
@@ -0,0 +1,85 @@
+//! Headless match server: simulates the match itself and streams snapshots to
+//! two players and any number of spectators.
+
+use std::time::{Duration, Instant};
+use dino_ball::config::{self, SpeedLevel};
+use dino_ball::server::Server;
+use dino_ball::sim::{Arena, MatchRules, MatchSim, SimEvent};
+
+const USAGE: &str = "usage: dino-ball-server [--port N] [--speed 1-3] [--score N] [--sets N] [--win-by-two] [--touch-rules]";
+
+struct Options {
+    port: u16,
+    speed: SpeedLevel,
+    rules: MatchRules,
+}
+
+impl Options {
+    fn parse(mut args: impl Iterator<Item = String>) -> Result<Self, String> {
+        let mut options = Options { port: config::NET_DEFAULT_PORT, speed: SpeedLevel::default(), rules: MatchRules::default() };
+        while let Some(arg) = args.next() {
+            let mut value = |name: &str| -> Result<u32, String> {
+                let text = args.next().ok_or_else(|| format!("{} needs a value", name))?;
+                text.parse().map_err(|_| format!("{} is not a number: {}", name, text))
+            };
+            match arg.as_str() {
+                "--port" => options.port = u16::try_from(value("--port")?).map_err(|_| "port out of range".to_string())?,
+                "--speed" => options.speed = SpeedLevel::from_u32(value("--speed")?).ok_or("speed must be 1, 2 or 3")?,
+                "--score" => options.rules.winning_score = value("--score")?.clamp(1, config::MAX_WINNING_SCORE),
+                "--sets" => options.rules.sets = value("--sets")?.clamp(1, config::MAX_SETS) | 1,
+                "--win-by-two" => options.rules.win_by_two = true,
+                "--touch-rules" => options.rules.touch_rules = true,
+                "--help" | "-h" => return Err(USAGE.to_string()),
+                _ => return Err(format!("unknown argument: {}", arg)),
+            }
+        }
+        Ok(options)
+    }
+}
+
+fn main() {
+    let options = match Options::parse(std::env::args().skip(1)) {
+        Ok(options) => options,
+        Err(e) => {
+            eprintln!("{}\n{}", e, USAGE);
+            std::process::exit(2);
+        }
+    };
+    let mut server = match Server::bind(options.port, 2) {
+        Ok(server) => server,
+        Err(e) => {
+            eprintln!("Failed to listen on port {}: {}", options.port, e);
+            std::process::exit(1);
+        }
+    };
+    let mut sim = MatchSim::new(Arena::default(), options.speed, options.rules);
+    println!("Serving on UDP port {}", options.port);
+
+    let dt = 1.0 / config::SIMULATION_HZ;
+    let mut next_tick = Instant::now();
+    loop {
+        server.poll(&sim);
+        // A finished match stays on screen until both players have gone, then the court is reset.
+        if sim.state.game_over && server.players_connected() == 0 {
+            sim.reset();
+            println!("Match reset");
+        }
+        let stepped = server.is_full();
+        if stepped {
+            sim.step(server.inputs(), dt as f32);
+            if sim.state.game_over && sim.events.iter().any(|event| matches!(event, SimEvent::MatchWon { .. })) {
+                println!("Player {} won {}-{}", sim.state.winner.unwrap_or(1), sim.state.player1_score, sim.state.player2_score);
+            }
+        }
+        server.end_tick(&sim, stepped);
+
+        next_tick += Duration::from_secs_f64(dt);
+        let now = Instant::now();
+        match next_tick.checked_duration_since(now) {
+            Some(wait) => std::thread::sleep(wait),
+            // Fell far behind (suspended?); don't try to catch up in a burst.
+            None if now - next_tick > Duration::from_secs(1) => next_tick = now,
+            None => {}
+        }
+    }
+}
@@ -0,0 +1,214 @@
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Action {
+    MoveLeft,
+    MoveRight,
+    Jump,
+    Serve,
+    Pause,
+    Replay,
+}
+
+impl Action {
+    pub const ALL: [Action; 6] = [
+        Action::MoveLeft,
+        Action::MoveRight,
+        Action::Jump,
+        Action::Serve,
+        Action::Pause,
+        Action::Replay,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Action::MoveLeft => "MOVE LEFT",
+            Action::MoveRight => "MOVE RIGHT",
+            Action::Jump => "JUMP",
+            Action::Serve => "SERVE",
+            Action::Pause => "PAUSE",
+            Action::Replay => "REPLAY",
+        }
+    }
+
+    /// Actions that affect the whole game rather than one player, so players may share a key.
+    pub fn is_shared(self) -> bool {
+        matches!(self, Action::Pause | Action::Replay)
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct PlayerBindings {
+    pub move_left: KeyCode,
+    pub move_right: KeyCode,
+    pub jump: KeyCode,
+    pub serve: KeyCode,
+    pub pause: KeyCode,
+    #[serde(default = "default_replay_key")]
+    pub replay: KeyCode,
+}
+
+fn default_replay_key() -> KeyCode {
+    KeyCode::KeyR
+}
+
+impl PlayerBindings {
+    /// Default keys for a player slot.
+    pub fn for_slot(slot: usize) -> Self {
+        match slot % 4 {
+            0 => Self::player1(),
+            1 => Self::player2(),
+            2 => Self::player3(),
+            _ => Self::player4(),
+        }
+    }
+
+    pub fn player1() -> Self {
+        Self {
+            move_left: KeyCode::KeyA,
+            move_right: KeyCode::KeyD,
+            jump: KeyCode::KeyW,
+            serve: KeyCode::Space,
+            pause: KeyCode::Escape,
+            replay: default_replay_key(),
+        }
+    }
+
+    pub fn player2() -> Self {
+        Self {
+            move_left: KeyCode::ArrowLeft,
+            move_right: KeyCode::ArrowRight,
+            jump: KeyCode::ArrowUp,
+            serve: KeyCode::Enter,
+            pause: KeyCode::Escape,
+            replay: default_replay_key(),
+        }
+    }
+
+    /// Second keyboard's worth of keys for doubles: the right-hand letters.
+    pub fn player3() -> Self {
+        Self {
+            move_left: KeyCode::KeyJ,
+            move_right: KeyCode::KeyL,
+            jump: KeyCode::KeyI,
+            serve: KeyCode::KeyK,
+            pause: KeyCode::Escape,
+            replay: default_replay_key(),
+        }
+    }
+
+    pub fn player4() -> Self {
+        Self {
+            move_left: KeyCode::Numpad4,
+            move_right: KeyCode::Numpad6,
+            jump: KeyCode::Numpad8,
+            serve: KeyCode::Numpad5,
+            pause: KeyCode::Escape,
+            replay: default_replay_key(),
+        }
+    }
+
+    pub fn key(&self, action: Action) -> KeyCode {
+        match action {
+            Action::MoveLeft => self.move_left,
+            Action::MoveRight => self.move_right,
+            Action::Jump => self.jump,
+            Action::Serve => self.serve,
+            Action::Pause => self.pause,
+            Action::Replay => self.replay,
+        }
+    }
+
+    pub fn set(&mut self, action: Action, key: KeyCode) {
+        match action {
+            Action::MoveLeft => self.move_left = key,
+            Action::MoveRight => self.move_right = key,
+            Action::Jump => self.jump = key,
+            Action::Serve => self.serve = key,
+            Action::Pause => self.pause = key,
+            Action::Replay => self.replay = key,
+        }
+    }
+}
+
+/// Two bindings that share a key. Players may share keys for shared actions like pause since they do the same thing for everyone.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Conflict {
+    pub key: KeyCode,
+    pub first: (usize, Action),
+    pub second: (usize, Action),
+}
+
+/// Keyboard bindings for every player slot, in slot order.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(default)]
+pub struct Bindings {
+    pub players: Vec<PlayerBindings>,
+}
+
+impl Default for Bindings {
+    fn default() -> Self {
+        Self {
+            players: vec![PlayerBindings::player1(), PlayerBindings::player2(), PlayerBindings::player3(), PlayerBindings::player4()],
+        }
+    }
+}
+
+impl Bindings {
+    /// Adds default keys for slots missing from older settings files.
+    pub fn fill_missing(&mut self) {
+        let defaults = Self::default().players;
+        if self.players.len() < defaults.len() {
+            let start = self.players.len();
+            self.players.extend_from_slice(&defaults[start..]);
+        }
+    }
+
+    pub fn pressed(&self, keyboard: &ButtonInput<KeyCode>, player: usize, action: Action) -> bool {
+        self.players.get(player).is_some_and(|b| keyboard.pressed(b.key(action)))
+    }
+
+    pub fn just_pressed(&self, keyboard: &ButtonInput<KeyCode>, player: usize, action: Action) -> bool {
+        self.players.get(player).is_some_and(|b| keyboard.just_pressed(b.key(action)))
+    }
+
+    pub fn any_just_pressed(&self, keyboard: &ButtonInput<KeyCode>, action: Action) -> bool {
+        (0..self.players.len()).any(|player| self.just_pressed(keyboard, player, action))
+    }
+
+    pub fn conflicts(&self) -> Vec<Conflict> {
+        let all: Vec<(usize, Action, KeyCode)> = self.players.iter().enumerate()
+            .flat_map(|(player, b)| Action::ALL.iter().map(move |&action| (player, action, b.key(action))))
+            .collect();
+
+        let mut conflicts = Vec::new();
+        for (i, &(p1, a1, k1)) in all.iter().enumerate() {
+            for &(p2, a2, k2) in &all[i + 1..] {
+                if k1 == k2 && !(a1 == a2 && a1.is_shared()) {
+                    conflicts.push(Conflict { key: k1, first: (p1, a1), second: (p2, a2) });
+                }
+            }
+        }
+        conflicts
+    }
+
+    /// Whether giving `player` these keys would have them share one with another binding.
+    pub fn clashes(&self, player: usize, keys: &PlayerBindings) -> bool {
+        let mut bindings = self.clone();
+        let Some(slot) = bindings.players.get_mut(player) else { return false };
+        *slot = keys.clone();
+        bindings.conflicts().iter().any(|c| c.first.0 == player || c.second.0 == player)
+    }
+
+    pub fn is_conflicting(&self, player: usize, action: Action) -> bool {
+        self.conflicts().iter().any(|c| c.first == (player, action) || c.second == (player, action))
+    }
+}
+
+/// Short display name for a key, e.g. "A" for `KeyCode::KeyA`.
+pub fn key_label(key: KeyCode) -> String {
+    let name = format!("{:?}", key);
+    let name = name.strip_prefix("Key").or_else(|| name.strip_prefix("Digit")).unwrap_or(&name);
+    name.to_uppercase()
+}
@@ -1,4 +1,6 @@
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
 pub enum SpeedLevel {
     Slow,
     #[default]
@@ -14,15 +16,25 @@ impl SpeedLevel {
             SpeedLevel::Fast => 1.2,
         }
     }
-    pub fn to_u32(&self) -> u32 {
+    pub fn to_u32(self) -> u32 {
         match self {
             SpeedLevel::Slow => 1,
             SpeedLevel::Normal => 2,
             SpeedLevel::Fast => 3,
         }
     }
+    pub fn from_u32(value: u32) -> Option<Self> {
+        match value {
+            1 => Some(SpeedLevel::Slow),
+            2 => Some(SpeedLevel::Normal),
+            3 => Some(SpeedLevel::Fast),
+            _ => None,
+        }
+    }
 }
 
+pub const SIMULATION_HZ: f64 = 120.0;
+
 pub const SPEED_RATIO: f32 = 0.35;
 pub const GRAVITY: f32 = 2310.0;
 pub const JUMP_HEIGHT_RATIO_OF_POLE: f32 = 0.8;
@@ -30,6 +42,8 @@ pub const NET_HEIGHT_RATIO: f32 = 0.45;
 pub const NET_WIDTH_RATIO: f32 = 0.03;
 pub const NET_COLLISION_WIDTH: f32 = 5.0;
 pub const GROUND_OFFSET_RATIO: f32 = 0.12;
+// Height of the training launcher above the top of the pole.
+pub const LAUNCHER_HEIGHT_RATIO: f32 = 0.14;
 
 pub const PLAYER_HEIGHT_RATIO: f32 = 0.2;
 pub const PLAYER_ASPECT_RATIO: f32 = 0.8;
@@ -38,11 +52,72 @@ pub const BALL_SIZE_RATIO: f32 = 0.1;
 pub const BALL_BOUNCE: f32 = 1.25;
 pub const BALL_GRAVITY: f32 = 1050.0;
 pub const BALL_MAX_SPEED: f32 = 840.0;
-pub const BALL_HORIZONTAL_FRICTION: f32 = 0.99;
+// Fraction of velocity kept after one second of ball time.
+pub const BALL_HORIZONTAL_DAMPING: f32 = 0.547;
+pub const BALL_ANGULAR_DAMPING: f32 = 0.298;
+// Longest distance the ball may travel in one sub-step, relative to its radius.
+pub const BALL_MAX_STEP_RATIO: f32 = 0.25;
 pub const BALL_ROTATION_FACTOR: f32 = 0.05;
 pub const BALL_MAX_SPIN: f32 = 5.0;
 
+pub const GAMEPAD_STICK_DEADZONE: f32 = 0.35;
+pub const GAMEPAD_STICK_JUMP: f32 = 0.6;
+
 pub const WINNING_SCORE: u32 = 5;
+pub const TARGET_SCORES: [u32; 4] = [5, 11, 15, 21];
+pub const SET_COUNTS: [u32; 3] = [1, 3, 5];
+// Widest rules a dedicated server can be started with; peers sending anything else are ignored.
+pub const MAX_WINNING_SCORE: u32 = 99;
+pub const MAX_SETS: u32 = 9;
+// Seconds of play kept for the instant replay, and how much of it before the serve to show.
+pub const INSTANT_REPLAY_SECONDS: f64 = 8.0;
+pub const INSTANT_REPLAY_LEAD_IN: f64 = 0.5;
+pub const INSTANT_REPLAY_SPEED: f32 = 0.35;
+pub const REPLAY_SPEEDS: [f32; 5] = [0.25, 0.5, 1.0, 2.0, 4.0];
+pub const REPLAY_SEEK_SECONDS: f64 = 5.0;
+// Frames a peer may run ahead of the last confirmed remote input before it waits.
+pub const NET_MAX_PREDICTION: u32 = 12;
+pub const NET_MAX_INPUTS_PER_PACKET: usize = 64;
+pub const NET_TIMEOUT_SECONDS: f64 = 10.0;
+pub const NET_DEFAULT_PORT: u16 = 7878;
+// Servers send state every few ticks; clients draw that far behind the newest one and blend in between.
+pub const SNAPSHOT_INTERVAL_TICKS: u32 = 4;
+pub const SNAPSHOT_INTERPOLATION_DELAY: f64 = 0.1;
+pub const NET_KEEPALIVE_SECONDS: f64 = 1.0;
+// Spectators only watch, so they can afford a longer buffer for smoother playback.
+pub const SPECTATOR_DELAY: f64 = 0.3;
+pub const SPECTATOR_DEFAULT_PORT: u16 = 7880;
+pub const MAX_SPECTATORS: usize = 16;
+// Hosts waiting for an opponent announce themselves on this port; listed games vanish once they go quiet.
+pub const LAN_DISCOVERY_PORT: u16 = 7879;
+pub const LAN_ANNOUNCE_INTERVAL: f64 = 1.0;
+pub const LAN_HOST_TIMEOUT: f64 = 3.5;
+pub const LAN_MAX_LISTED: usize = 4;
+pub const MAX_TOUCHES: u32 = 3;
+// Contacts by one player closer together than this count as a single touch.
+pub const TOUCH_MERGE_TIME: f32 = 0.12;
+// Closest two teammates can stand, as a fraction of a player's width.
+pub const TEAMMATE_SPACING_RATIO: f32 = 0.7;
+// Most dinos on court at once (2 vs 2), and the hue shift each one is drawn with.
+pub const MAX_PLAYERS: usize = 4;
+pub const PLAYER_HUE_SHIFTS: [f32; MAX_PLAYERS] = [0.0, 0.45, 0.2, 0.7];
+// Hue of the dino art before any shift, and the colours profiles can pick as shifts from it.
+pub const DINO_BASE_HUE: f32 = 0.59;
+pub const DINO_COLORS: [(&str, f32); 8] = [
+    ("BLUE", 0.0),
+    ("PURPLE", 0.2),
+    ("PINK", 0.33),
+    ("RED", 0.45),
+    ("ORANGE", 0.5),
+    ("YELLOW", 0.57),
+    ("GREEN", 0.7),
+    ("TEAL", 0.88),
+];
+// Two dinos whose hues are closer than this (about 15°) are too hard to tell apart.
+pub const MIN_HUE_DISTANCE: f32 = 0.04;
+// Step of the hue buttons on the dino screen.
+pub const HUE_STEP: f32 = 10.0 / 360.0;
+pub const MAX_PROFILE_NAME: usize = 12;
 
 pub const ASSET_BACK: &str = "back.png";
 pub const ASSET_BALL: &str = "ball.png";
@@ -52,3 +127,9 @@ pub const ASSET_PLAYER_STATIONARY: &str = "player/stationary.png";
 pub const ASSET_PLAYER_JUMP: &str = "player/jump.png";
 pub const ASSET_PLAYER_RUN1: &str = "player/run1.png";
 pub const ASSET_PLAYER_RUN2: &str = "player/run2.png";
+
+pub const ASSET_SOUND_HIT: &str = "sounds/hit.wav";
+pub const ASSET_SOUND_POLE: &str = "sounds/pole.wav";
+pub const ASSET_SOUND_WALL: &str = "sounds/wall.wav";
+pub const ASSET_SOUND_POINT: &str = "sounds/point.wav";
+pub const ASSET_SOUND_WIN: &str = "sounds/win.wav";
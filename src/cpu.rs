@@ -0,0 +1,176 @@
+use crate::config;
+use crate::sim::{MatchSim, PlayerInput, Rng, Side};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Difficulty {
+    Easy,
+    #[default]
+    Normal,
+    Hard,
+}
+
+impl Difficulty {
+    pub fn label(self) -> &'static str {
+        match self {
+            Difficulty::Easy => "EASY",
+            Difficulty::Normal => "NORMAL",
+            Difficulty::Hard => "HARD",
+        }
+    }
+    /// Seconds between two looks at the ball's trajectory.
+    pub fn reaction_delay(self) -> f32 {
+        match self {
+            Difficulty::Easy => 0.35,
+            Difficulty::Normal => 0.18,
+            Difficulty::Hard => 0.06,
+        }
+    }
+    /// Largest positioning mistake, as a fraction of the player's width.
+    pub fn position_error(self) -> f32 {
+        match self {
+            Difficulty::Easy => 0.8,
+            Difficulty::Normal => 0.5,
+            Difficulty::Hard => 0.1,
+        }
+    }
+    /// Chance of jumping into a dropping ball instead of letting it bounce off the head.
+    pub fn spike_chance(self) -> f32 {
+        match self {
+            Difficulty::Easy => 0.05,
+            Difficulty::Normal => 0.35,
+            Difficulty::Hard => 0.8,
+        }
+    }
+}
+
+/// Drives one player from the match state, producing the same inputs a human would.
+#[derive(Clone, Debug)]
+pub struct CpuController {
+    pub difficulty: Difficulty,
+    rng: Rng,
+    think_timer: f32,
+    serve_timer: f32,
+    target_x: Option<f32>,
+    wants_spike: bool,
+}
+
+impl CpuController {
+    pub fn new(difficulty: Difficulty, seed: u32) -> Self {
+        Self {
+            difficulty,
+            rng: Rng::new(seed),
+            think_timer: 0.0,
+            serve_timer: 0.0,
+            target_x: None,
+            wants_spike: false,
+        }
+    }
+
+    pub fn update(&mut self, sim: &MatchSim, slot: usize, dt: f32) -> PlayerInput {
+        let Some(me) = sim.players.get(slot) else { return PlayerInput::default() };
+        if sim.state.game_over {
+            return PlayerInput::default();
+        }
+
+        let arena = sim.arena;
+        let player_size = arena.player_size();
+        let (min_x, max_x) = arena.side_bounds(me.side);
+        let home_x = (min_x + max_x) / 2.0;
+
+        if !sim.state.is_ball_active {
+            self.target_x = None;
+            if sim.state.serving_player.side() != me.side {
+                self.serve_timer = 0.0;
+                return self.walk_to(me.pos.x, home_x, player_size.x);
+            }
+            // The ball hangs above the server, so serving is just a jump after a short wait.
+            self.serve_timer += dt;
+            let jump = self.serve_timer > 0.4 + self.difficulty.reaction_delay() * 2.0;
+            if jump {
+                self.serve_timer = 0.0;
+            }
+            return PlayerInput { jump, ..PlayerInput::default() };
+        }
+        self.serve_timer = 0.0;
+
+        self.think_timer -= dt;
+        if self.think_timer <= 0.0 {
+            self.think_timer = self.difficulty.reaction_delay();
+            self.plan(sim, slot);
+        }
+
+        let target_x = self.target_x.unwrap_or(home_x);
+        let mut input = self.walk_to(me.pos.x, target_x, player_size.x);
+
+        let ball = &sim.ball;
+        let dx = (ball.pos.x - me.pos.x).abs();
+        let height_above = ball.pos.y - me.pos.y;
+        let reach = arena.net_height() * config::JUMP_HEIGHT_RATIO_OF_POLE + player_size.y;
+        if self.wants_spike && ball.vel.y < 0.0 && dx < player_size.x * 0.6 && height_above > player_size.y * 0.5 && height_above < reach {
+            input.jump = true;
+        }
+        input
+    }
+
+    fn plan(&mut self, sim: &MatchSim, slot: usize) {
+        let me = &sim.players[slot];
+        let arena = sim.arena;
+        let player_width = arena.player_size().x;
+        let contact_y = me.pos.y + arena.player_size().y / 2.0;
+
+        self.target_x = predict_landing(sim, contact_y)
+            .filter(|x| match me.side {
+                Side::Left => *x < 0.0,
+                Side::Right => *x > 0.0,
+            })
+            .map(|x| {
+                // Meet the ball slightly from behind so it bounces towards the net.
+                let away_from_net = match me.side {
+                    Side::Left => -1.0,
+                    Side::Right => 1.0,
+                };
+                let max_error = self.difficulty.position_error() * player_width;
+                x + away_from_net * player_width * 0.25 + self.rng.range(-max_error, max_error)
+            });
+        self.wants_spike = self.rng.next_f32() < self.difficulty.spike_chance();
+    }
+
+    fn walk_to(&self, x: f32, target_x: f32, player_width: f32) -> PlayerInput {
+        let dead_zone = player_width * 0.05;
+        PlayerInput {
+            left: target_x < x - dead_zone,
+            right: target_x > x + dead_zone,
+            ..PlayerInput::default()
+        }
+    }
+}
+
+/// Follows the ball forward with the simulation's gravity, damping and wall bounce
+/// and returns the x where it first drops below `y`.
+pub fn predict_landing(sim: &MatchSim, y: f32) -> Option<f32> {
+    let arena = sim.arena;
+    let ball_radius = arena.ball_radius();
+    let half_width = arena.half_width();
+    let dt = (1.0 / config::SIMULATION_HZ) as f32 * sim.speed.factor();
+
+    let mut pos = sim.ball.pos;
+    let mut vel = sim.ball.vel;
+    for _ in 0..(config::SIMULATION_HZ as usize * 4) {
+        vel.y -= config::BALL_GRAVITY * dt;
+        vel.x *= config::BALL_HORIZONTAL_DAMPING.powf(dt);
+        pos += vel * dt;
+
+        if pos.x - ball_radius < -half_width {
+            pos.x = -half_width + ball_radius;
+            vel.x *= -config::BALL_BOUNCE;
+        }
+        if pos.x + ball_radius > half_width {
+            pos.x = half_width - ball_radius;
+            vel.x *= -config::BALL_BOUNCE;
+        }
+        if vel.y < 0.0 && pos.y < y {
+            return Some(pos.x);
+        }
+    }
+    None
+}
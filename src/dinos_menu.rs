@@ -0,0 +1,261 @@
+use bevy::prelude::*;
+use crate::config;
+use crate::profiles::Profiles;
+use crate::settings::Settings;
+use crate::skins::{hue_label, is_distinct, swatch_color, DinoSkins};
+use crate::state;
+use crate::types::*;
+
+const PREVIEW_HEIGHT: f32 = 90.0;
+/// Same pace as the run cycle in a match.
+const RUN_FRAME_SECS: f32 = 0.1;
+
+pub fn setup_dinos_menu(mut commands: Commands) {
+    let label_color = Color::srgb(0.7, 0.7, 0.7);
+
+    commands.spawn((
+        Node {
+            width: Val::Percent(100.0),
+            height: Val::Percent(100.0),
+            align_items: AlignItems::Center,
+            justify_content: JustifyContent::Center,
+            flex_direction: FlexDirection::Column,
+            ..default()
+        },
+        BackgroundColor(Color::srgba(0.0, 0.0, 0.0, 0.85)),
+        DinosUI,
+    )).with_children(|parent| {
+        parent.spawn((
+            text("DINOS", 48.0, Color::WHITE),
+            Node {
+                margin: UiRect::bottom(Val::Px(16.0)),
+                ..default()
+            },
+        ));
+
+        parent.spawn(Node {
+            flex_direction: FlexDirection::Row,
+            ..default()
+        }).with_children(|row| {
+            for slot in 0..config::MAX_PLAYERS {
+                row.spawn(Node {
+                    flex_direction: FlexDirection::Column,
+                    align_items: AlignItems::Center,
+                    width: Val::Px(280.0),
+                    ..default()
+                }).with_children(|column| {
+                    column.spawn(text(&format!("PLAYER {}", slot + 1), 18.0, label_color));
+                    column.spawn((text("", 24.0, Color::WHITE), DinosValue::Name(slot)));
+
+                    column.spawn(Node {
+                        flex_direction: FlexDirection::Row,
+                        align_items: AlignItems::FlexEnd,
+                        margin: UiRect::vertical(Val::Px(10.0)),
+                        ..default()
+                    }).with_children(|frames| {
+                        for frame in [PreviewFrame::Stationary, PreviewFrame::Run, PreviewFrame::Jump] {
+                            frames.spawn((
+                                ImageNode::default(),
+                                Node {
+                                    width: Val::Px(PREVIEW_HEIGHT * config::PLAYER_ASPECT_RATIO),
+                                    height: Val::Px(PREVIEW_HEIGHT),
+                                    margin: UiRect::horizontal(Val::Px(4.0)),
+                                    ..default()
+                                },
+                                DinoPreview { slot, frame },
+                            ));
+                        }
+                    });
+
+                    column.spawn(Node {
+                        flex_direction: FlexDirection::Row,
+                        align_items: AlignItems::Center,
+                        ..default()
+                    }).with_children(|picker| {
+                        spawn_button(picker, 40.0, DinosButton::HueDown(slot), |btn| {
+                            btn.spawn(text("-", 24.0, Color::WHITE));
+                        });
+                        spawn_button(picker, 170.0, DinosButton::Preset(slot), |btn| {
+                            btn.spawn((
+                                Node {
+                                    width: Val::Px(16.0),
+                                    height: Val::Px(16.0),
+                                    margin: UiRect::right(Val::Px(8.0)),
+                                    ..default()
+                                },
+                                BackgroundColor(Color::NONE),
+                                DinoSwatch(slot),
+                            ));
+                            btn.spawn((text("", 20.0, Color::WHITE), DinosValue::Color(slot)));
+                        });
+                        spawn_button(picker, 40.0, DinosButton::HueUp(slot), |btn| {
+                            btn.spawn(text("+", 24.0, Color::WHITE));
+                        });
+                    });
+                });
+            }
+        });
+
+        parent.spawn((
+            text("- AND + NUDGE THE HUE, THE MIDDLE BUTTON CYCLES PRESETS. COLOURS TOO CLOSE TO ANOTHER PLAYER'S ARE SKIPPED.", 16.0, label_color),
+            Node {
+                margin: UiRect::vertical(Val::Px(16.0)),
+                ..default()
+            },
+        ));
+
+        parent.spawn((
+            Button,
+            Node {
+                width: Val::Px(200.0),
+                height: Val::Px(60.0),
+                margin: UiRect::all(Val::Px(10.0)),
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
+                ..default()
+            },
+            BackgroundColor(Color::srgb(0.2, 0.2, 0.2)),
+            DinosButton::Back,
+        )).with_children(|btn| {
+            btn.spawn(text("BACK", 28.0, Color::WHITE));
+        });
+    });
+}
+
+fn spawn_button(parent: &mut ChildSpawnerCommands, width: f32, action: DinosButton, children: impl FnOnce(&mut ChildSpawnerCommands)) {
+    parent.spawn((
+        Button,
+        Node {
+            width: Val::Px(width),
+            height: Val::Px(36.0),
+            margin: UiRect::all(Val::Px(4.0)),
+            justify_content: JustifyContent::Center,
+            align_items: AlignItems::Center,
+            ..default()
+        },
+        BackgroundColor(Color::srgb(0.2, 0.2, 0.2)),
+        action,
+    )).with_children(children);
+}
+
+fn text(value: &str, size: f32, color: Color) -> (Text, TextFont, TextColor) {
+    (
+        Text::new(value),
+        TextFont {
+            font_size: size,
+            ..default()
+        },
+        TextColor(color),
+    )
+}
+
+pub fn cleanup_dinos_menu(mut commands: Commands, dinos_ui: Query<Entity, With<DinosUI>>) {
+    for entity in dinos_ui.iter() {
+        commands.entity(entity).despawn();
+    }
+}
+
+/// Colours every other slot is using, which `slot` has to stay clear of.
+fn other_hues(profiles: &Profiles, settings: &Settings, slot: usize) -> Vec<f32> {
+    (0..config::MAX_PLAYERS)
+        .filter(|other| *other != slot)
+        .map(|other| profiles.slot_hue(other, settings))
+        .collect()
+}
+
+pub fn dinos_button_system(
+    interaction_query: Query<(&Interaction, &DinosButton), Changed<Interaction>>,
+    mut profiles: ResMut<Profiles>,
+    mut settings: ResMut<Settings>,
+    mut app_state: ResMut<NextState<state::AppMode>>,
+    menu_ui: Query<Entity, With<MenuUI>>,
+    mut commands: Commands,
+) {
+    for (interaction, action) in interaction_query.iter() {
+        if *interaction != Interaction::Pressed {
+            continue;
+        }
+        let (slot, candidates): (usize, Vec<f32>) = match *action {
+            DinosButton::HueDown(slot) | DinosButton::HueUp(slot) => {
+                let step = if matches!(action, DinosButton::HueUp(_)) { config::HUE_STEP } else { -config::HUE_STEP };
+                let current = profiles.slot_hue(slot, &settings);
+                // Whole degrees, so repeated steps don't drift.
+                let candidates = (1..=36)
+                    .map(|n| ((current + step * n as f32).rem_euclid(1.0) * 360.0).round() / 360.0)
+                    .collect();
+                (slot, candidates)
+            }
+            DinosButton::Preset(slot) => {
+                let current = profiles.slot_hue(slot, &settings);
+                let count = config::DINO_COLORS.len();
+                let start = config::DINO_COLORS.iter()
+                    .position(|(_, hue)| *hue == current)
+                    .map_or(0, |i| i + 1);
+                let candidates = (0..count).map(|n| config::DINO_COLORS[(start + n) % count].1).collect();
+                (slot, candidates)
+            }
+            DinosButton::Back => {
+                app_state.set(state::AppMode::Menu);
+                for entity in menu_ui.iter() {
+                    commands.entity(entity).insert(Visibility::Visible);
+                }
+                continue;
+            }
+        };
+        let others = other_hues(&profiles, &settings, slot);
+        if let Some(hue) = candidates.into_iter().find(|hue| is_distinct(*hue, others.iter().copied())) {
+            profiles.set_slot_hue(slot, hue, &mut settings);
+        }
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn dinos_display_system(
+    profiles: Res<Profiles>,
+    settings: Res<Settings>,
+    time: Res<Time>,
+    asset_server: Res<AssetServer>,
+    mut skins: ResMut<DinoSkins>,
+    mut texts: Query<(&DinosValue, &mut Text)>,
+    mut previews: Query<(&DinoPreview, &mut ImageNode)>,
+    mut swatches: Query<(&DinoSwatch, &mut BackgroundColor), Without<DinosButton>>,
+    mut buttons: Query<(&Interaction, &mut BackgroundColor), With<DinosButton>>,
+) {
+    for (value, mut text) in texts.iter_mut() {
+        let label = match *value {
+            DinosValue::Name(slot) => profiles.selected(slot)
+                .filter(|profile| !profile.name.trim().is_empty())
+                .map_or(format!("PLAYER {}", slot + 1), |profile| profile.name.clone()),
+            DinosValue::Color(slot) => hue_label(profiles.slot_hue(slot, &settings)),
+        };
+        if text.0 != label {
+            text.0 = label;
+        }
+    }
+
+    let running = ((time.elapsed_secs() / RUN_FRAME_SECS) as u32).is_multiple_of(2);
+    for (preview, mut image) in previews.iter_mut() {
+        let anims = skins.get(profiles.slot_hue(preview.slot, &settings), &asset_server);
+        let wanted = match preview.frame {
+            PreviewFrame::Stationary => anims.stationary,
+            PreviewFrame::Run if running => anims.run1,
+            PreviewFrame::Run => anims.run2,
+            PreviewFrame::Jump => anims.jump,
+        };
+        if image.image != wanted {
+            image.image = wanted;
+        }
+    }
+
+    for (swatch, mut color) in swatches.iter_mut() {
+        color.0 = swatch_color(profiles.slot_hue(swatch.0, &settings));
+    }
+
+    for (interaction, mut color) in buttons.iter_mut() {
+        *color = match interaction {
+            Interaction::Pressed => BackgroundColor(Color::srgb(0.5, 0.5, 0.5)),
+            Interaction::Hovered => BackgroundColor(Color::srgb(0.4, 0.4, 0.4)),
+            Interaction::None => BackgroundColor(Color::srgb(0.2, 0.2, 0.2)),
+        };
+    }
+}
@@ -0,0 +1,310 @@
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use crate::config::SpeedLevel;
+use crate::netplay::NetSession;
+use crate::remote::RemoteSession;
+use crate::profiles::Players;
+use crate::sim::{MatchRules, MatchStats, Side};
+use crate::state;
+use crate::util;
+
+const HISTORY_FILE: &str = "history.toml";
+const EXPORT_CSV: &str = "match-history.csv";
+const EXPORT_JSON: &str = "match-history.json";
+
+/// One finished match as it was saved to the history.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct MatchRecord {
+    /// Seconds since the Unix epoch when the match ended.
+    pub finished_at: u64,
+    /// Who played each slot; even slots were on the left.
+    pub players: Vec<String>,
+    /// Profile id behind each slot; 0 for guests, the CPU and online opponents.
+    #[serde(default)]
+    pub profiles: Vec<u32>,
+    pub rules: MatchRules,
+    pub speed: SpeedLevel,
+    /// Final points of every set, as (left, right).
+    pub set_scores: Vec<(u32, u32)>,
+    /// 1 for the left side, 2 for the right.
+    pub winner: u32,
+    pub online: bool,
+    pub stats: MatchStats,
+}
+
+impl MatchRecord {
+    /// Names on `side`, joined for display.
+    pub fn side_names(&self, side: Side) -> String {
+        self.players.iter()
+            .enumerate()
+            .filter(|(slot, _)| slot_side(*slot) == side)
+            .map(|(_, name)| name.as_str())
+            .collect::<Vec<_>>()
+            .join(" & ")
+    }
+
+    pub fn sets_won(&self) -> (u32, u32) {
+        let left = self.set_scores.iter().filter(|(left, right)| left > right).count() as u32;
+        (left, self.set_scores.len() as u32 - left)
+    }
+
+    pub fn score_line(&self) -> String {
+        self.set_scores.iter()
+            .map(|(left, right)| format!("{left}-{right}"))
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+
+    fn won(&self, slot: usize) -> bool {
+        (slot_side(slot) == Side::Left) == (self.winner == 1)
+    }
+}
+
+/// Matches alternate sides by slot, like `MatchSim::new` places them.
+fn slot_side(slot: usize) -> Side {
+    if slot.is_multiple_of(2) { Side::Left } else { Side::Right }
+}
+
+/// Every completed match, oldest first, kept between sessions.
+#[derive(Resource, Serialize, Deserialize, Default, Debug, Clone)]
+#[serde(default)]
+pub struct MatchHistory {
+    pub matches: Vec<MatchRecord>,
+}
+
+/// Sums over the whole history for the records screen.
+#[derive(Default, Debug)]
+pub struct Totals {
+    pub matches: u32,
+    pub points: u32,
+    pub hits: u32,
+    pub aces: u32,
+    pub longest_rally: u32,
+    pub rallies: u32,
+    pub rally_hits: u32,
+    pub pole_hits: u32,
+    pub wall_bounces: u32,
+}
+
+impl Totals {
+    pub fn average_rally(&self) -> f32 {
+        if self.rallies == 0 { 0.0 } else { self.rally_hits as f32 / self.rallies as f32 }
+    }
+}
+
+impl MatchHistory {
+    pub fn load() -> Self {
+        util::load_data(HISTORY_FILE)
+    }
+
+    fn save(&self) {
+        util::save_data(HISTORY_FILE, self);
+    }
+
+    pub fn totals(&self) -> Totals {
+        let mut totals = Totals::default();
+        for record in &self.matches {
+            let stats = &record.stats;
+            totals.matches += 1;
+            totals.points += record.set_scores.iter().map(|(left, right)| left + right).sum::<u32>();
+            totals.hits += stats.hits.iter().sum::<u32>();
+            totals.aces += stats.aces.iter().sum::<u32>();
+            totals.longest_rally = totals.longest_rally.max(stats.longest_rally);
+            totals.rallies += stats.rallies;
+            totals.rally_hits += stats.rally_hits;
+            totals.pole_hits += stats.pole_hits;
+            totals.wall_bounces += stats.wall_bounces;
+        }
+        totals
+    }
+
+    /// Wins and losses per profile under its latest name, most matches first.
+    /// Guests, the CPU and online opponents aren't anyone in particular, so they're left out.
+    pub fn win_loss(&self) -> Vec<(String, u32, u32)> {
+        let mut table: BTreeMap<u32, (&str, u32, u32)> = BTreeMap::new();
+        for record in &self.matches {
+            for (slot, (name, profile)) in record.players.iter().zip(&record.profiles).enumerate() {
+                if *profile == 0 {
+                    continue;
+                }
+                let entry = table.entry(*profile).or_default();
+                entry.0 = name;
+                if record.won(slot) {
+                    entry.1 += 1;
+                } else {
+                    entry.2 += 1;
+                }
+            }
+        }
+        let mut rows: Vec<_> = table.into_values().map(|(name, won, lost)| (name.to_string(), won, lost)).collect();
+        rows.sort_by_key(|(_, won, lost)| std::cmp::Reverse(won + lost));
+        rows
+    }
+
+    /// Writes the history to the data directory as one CSV row per match.
+    pub fn export_csv(&self) -> std::io::Result<PathBuf> {
+        let mut csv = String::from(
+            "date,left,right,winner,sets_left,sets_right,set_scores,speed,winning_score,best_of,win_by_two,touch_rules,team_size,online,\
+             hits_left,hits_right,aces_left,aces_right,serve_points_left,serve_points_right,longest_rally,average_rally,pole_hits,wall_bounces\n",
+        );
+        for record in &self.matches {
+            let stats = &record.stats;
+            let side_hits = |side: Side| -> u32 {
+                stats.hits.iter().enumerate().filter(|(slot, _)| slot_side(*slot) == side).map(|(_, hits)| hits).sum()
+            };
+            let (sets_left, sets_right) = record.sets_won();
+            let fields = [
+                format_date(record.finished_at),
+                csv_field(&record.side_names(Side::Left)),
+                csv_field(&record.side_names(Side::Right)),
+                csv_field(&record.side_names(if record.winner == 1 { Side::Left } else { Side::Right })),
+                sets_left.to_string(),
+                sets_right.to_string(),
+                record.score_line(),
+                format!("{:?}", record.speed),
+                record.rules.winning_score.to_string(),
+                record.rules.sets.to_string(),
+                record.rules.win_by_two.to_string(),
+                record.rules.touch_rules.to_string(),
+                record.rules.team_size.to_string(),
+                record.online.to_string(),
+                side_hits(Side::Left).to_string(),
+                side_hits(Side::Right).to_string(),
+                stats.aces[0].to_string(),
+                stats.aces[1].to_string(),
+                stats.serve_points[0].to_string(),
+                stats.serve_points[1].to_string(),
+                stats.longest_rally.to_string(),
+                format!("{:.2}", stats.average_rally()),
+                stats.pole_hits.to_string(),
+                stats.wall_bounces.to_string(),
+            ];
+            csv.push_str(&fields.join(","));
+            csv.push('\n');
+        }
+        write_export(EXPORT_CSV, csv)
+    }
+
+    /// Writes the full history, stats included, to the data directory as JSON.
+    pub fn export_json(&self) -> std::io::Result<PathBuf> {
+        let json = serde_json::to_string_pretty(&self.matches).map_err(std::io::Error::other)?;
+        write_export(EXPORT_JSON, json)
+    }
+}
+
+fn write_export(file: &str, contents: String) -> std::io::Result<PathBuf> {
+    let path = util::data_path(file).ok_or_else(|| std::io::Error::other("no data directory"))?;
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+    std::fs::write(&path, contents)?;
+    Ok(path)
+}
+
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+/// `YYYY-MM-DD HH:MM` in UTC.
+pub fn format_date(secs: u64) -> String {
+    let days = (secs / 86_400) as i64;
+    let minutes = secs % 86_400 / 60;
+    // Civil-from-days, counting from 0000-03-01 so leap days fall at the end of the year.
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + (month <= 2) as i64;
+    format!("{:04}-{:02}-{:02} {:02}:{:02}", year, month, day, minutes / 60, minutes % 60)
+}
+
+/// Saves the match to the history once it's over. Spectated matches aren't ours to keep.
+pub fn record_match(
+    game: Res<state::Match>,
+    players: Res<Players>,
+    net: Option<Res<NetSession>>,
+    remote: Option<Res<RemoteSession>>,
+    mut history: ResMut<MatchHistory>,
+    mut recorded: Local<bool>,
+) {
+    if !game.state.game_over {
+        *recorded = false;
+        return;
+    }
+    // Rollback can re-simulate the final frames, so only the first finish counts.
+    if *recorded || remote.as_ref().is_some_and(|session| session.slot.is_none()) {
+        return;
+    }
+    *recorded = true;
+    let finished_at = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs());
+    history.matches.push(MatchRecord {
+        finished_at,
+        players: players.names(),
+        profiles: players.0.iter().map(|player| player.profile.unwrap_or(0)).collect(),
+        rules: game.rules,
+        speed: game.speed,
+        set_scores: game.state.set_scores.clone(),
+        winner: game.state.winner.unwrap_or(1),
+        online: net.is_some() || remote.is_some(),
+        stats: game.stats.clone(),
+    });
+    history.save();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(players: &[(&str, u32)], winner: u32) -> MatchRecord {
+        MatchRecord {
+            finished_at: 0,
+            players: players.iter().map(|(name, _)| name.to_string()).collect(),
+            profiles: players.iter().map(|(_, profile)| *profile).collect(),
+            rules: MatchRules::default(),
+            speed: SpeedLevel::default(),
+            set_scores: vec![if winner == 1 { (5, 3) } else { (3, 5) }],
+            winner,
+            online: false,
+            stats: MatchStats::default(),
+        }
+    }
+
+    #[test]
+    fn win_loss_counts_profiles_not_names() {
+        let history = MatchHistory {
+            matches: vec![
+                record(&[("REX", 1), ("CPU", 0)], 1),
+                record(&[("PLAYER 1", 0), ("CPU", 0)], 2),
+                record(&[("REX", 1), ("OPPONENT", 0)], 2),
+                // Renamed since, and a guest who happens to have the old name.
+                record(&[("T-REX", 1), ("REX", 0)], 1),
+                record(&[("SPIKE", 2), ("T-REX", 1)], 1),
+            ],
+        };
+        assert_eq!(history.win_loss(), vec![
+            ("T-REX".to_string(), 2, 2),
+            ("SPIKE".to_string(), 1, 0),
+        ]);
+    }
+
+    #[test]
+    fn records_from_before_profile_ids_still_load() {
+        let mut saved = toml::to_string(&MatchHistory { matches: vec![record(&[("REX", 1), ("CPU", 0)], 1)] }).unwrap();
+        saved = saved.lines().filter(|line| !line.starts_with("profiles")).collect::<Vec<_>>().join("\n");
+        let history: MatchHistory = toml::from_str(&saved).unwrap();
+        assert_eq!(history.matches[0].players, vec!["REX", "CPU"]);
+        assert!(history.win_loss().is_empty());
+    }
+}
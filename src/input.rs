@@ -0,0 +1,75 @@
+use bevy::prelude::*;
+use crate::bindings::Action;
+use crate::config;
+use crate::settings::Settings;
+use crate::sim::PlayerInput;
+use crate::state;
+
+pub fn keyboard_input_system(
+    keyboard: Res<ButtonInput<KeyCode>>,
+    settings: Res<Settings>,
+    mut inputs: ResMut<state::PlayerInputs>,
+) {
+    let bindings = &settings.controls;
+    inputs.0 = (0..bindings.players.len())
+        .map(|player| PlayerInput {
+            left: bindings.pressed(&keyboard, player, Action::MoveLeft),
+            right: bindings.pressed(&keyboard, player, Action::MoveRight),
+            jump: bindings.pressed(&keyboard, player, Action::Jump),
+            serve: bindings.pressed(&keyboard, player, Action::Serve),
+        })
+        .collect();
+}
+
+/// Gives newly connected pads the first free player slot and frees the slots of disconnected ones.
+pub fn assign_gamepads(
+    gamepads: Query<Entity, With<Gamepad>>,
+    mut slots: ResMut<state::GamepadSlots>,
+) {
+    for slot in slots.0.iter_mut() {
+        if slot.is_some_and(|entity| !gamepads.contains(entity)) {
+            *slot = None;
+        }
+    }
+
+    for entity in gamepads.iter() {
+        if slots.0.contains(&Some(entity)) {
+            continue;
+        }
+        if let Some(free) = slots.0.iter_mut().find(|slot| slot.is_none()) {
+            *free = Some(entity);
+        }
+    }
+}
+
+/// Adds each assigned pad on top of the keyboard input of its player.
+pub fn gamepad_input_system(
+    gamepads: Query<&Gamepad>,
+    slots: Res<state::GamepadSlots>,
+    mut inputs: ResMut<state::PlayerInputs>,
+) {
+    for (slot, entity) in slots.0.iter().enumerate() {
+        let Some(gamepad) = entity.and_then(|entity| gamepads.get(entity).ok()) else { continue };
+        let Some(input) = inputs.0.get_mut(slot) else { continue };
+
+        let pad = gamepad_input(gamepad);
+        input.left |= pad.left;
+        input.right |= pad.right;
+        input.jump |= pad.jump;
+        input.serve |= pad.serve;
+    }
+}
+
+fn gamepad_input(gamepad: &Gamepad) -> PlayerInput {
+    let stick = gamepad.left_stick();
+    let dead_zone = config::GAMEPAD_STICK_DEADZONE;
+    PlayerInput {
+        left: stick.x < -dead_zone || gamepad.pressed(GamepadButton::DPadLeft),
+        right: stick.x > dead_zone || gamepad.pressed(GamepadButton::DPadRight),
+        jump: stick.y > config::GAMEPAD_STICK_JUMP
+            || gamepad.pressed(GamepadButton::DPadUp)
+            || gamepad.pressed(GamepadButton::South)
+            || gamepad.pressed(GamepadButton::East),
+        serve: gamepad.pressed(GamepadButton::West) || gamepad.pressed(GamepadButton::North),
+    }
+}
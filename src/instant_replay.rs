@@ -0,0 +1,178 @@
+use bevy::prelude::*;
+use std::collections::VecDeque;
+use crate::bindings::Action;
+use crate::config;
+use crate::settings::Settings;
+use crate::sim::{MatchStatus, PlayerState, SimEvent};
+use crate::state;
+use crate::types::*;
+
+#[derive(Clone, Copy, Debug)]
+pub struct PlayerFrame {
+    pub pos: Vec2,
+    pub facing_left: bool,
+    pub state: PlayerState,
+}
+
+/// Where everything was after one simulation step.
+#[derive(Clone, Debug)]
+pub struct ReplayFrame {
+    pub players: Vec<PlayerFrame>,
+    pub ball_pos: Vec2,
+    pub ball_rotation: f32,
+}
+
+/// Rolling history of the last few seconds of play and the rally that ended with the last point.
+#[derive(Resource, Default)]
+pub struct InstantReplay {
+    history: VecDeque<ReplayFrame>,
+    frames_since_serve: usize,
+    last_rally: Vec<ReplayFrame>,
+    cursor: f32,
+    /// Match point has been reached and replayed; saved match points don't show it again.
+    match_point_replayed: bool,
+}
+
+pub fn record_frames(game: Res<state::Match>, mut replay: ResMut<InstantReplay>) {
+    if game.events.iter().any(|event| matches!(event, SimEvent::Served { .. })) {
+        replay.frames_since_serve = 0;
+    }
+    let capacity = (config::INSTANT_REPLAY_SECONDS * config::SIMULATION_HZ) as usize;
+    if replay.history.len() >= capacity {
+        replay.history.pop_front();
+    }
+    replay.history.push_back(ReplayFrame {
+        players: game.players.iter()
+            .map(|p| PlayerFrame { pos: p.pos, facing_left: p.facing_left, state: p.state })
+            .collect(),
+        ball_pos: game.ball.pos,
+        ball_rotation: game.ball.rotation,
+    });
+    replay.frames_since_serve += 1;
+
+    if !game.events.iter().any(|event| matches!(event, SimEvent::PointScored { .. })) {
+        return;
+    }
+    let lead_in = (config::INSTANT_REPLAY_LEAD_IN * config::SIMULATION_HZ) as usize;
+    let len = (replay.frames_since_serve + lead_in).min(replay.history.len());
+    let start = replay.history.len() - len;
+    replay.last_rally = replay.history.range(start..).cloned().collect();
+}
+
+/// Replays the rally that first set up match point. Local matches only: an online peer can't stop for it.
+pub fn replay_match_point(
+    game: Res<state::Match>,
+    mut replay: ResMut<InstantReplay>,
+    mut app_state: ResMut<NextState<state::AppMode>>,
+) {
+    let scored = game.events.iter().any(|event| matches!(event, SimEvent::PointScored { .. }));
+    if scored && !replay.match_point_replayed && matches!(game.match_status(), MatchStatus::MatchPoint(_)) {
+        replay.match_point_replayed = true;
+        app_state.set(state::AppMode::InstantReplay);
+    }
+}
+
+/// Forgets the previous match so its rallies can't be replayed in the next one.
+pub fn clear_instant_replay(mut replay: ResMut<InstantReplay>) {
+    *replay = InstantReplay::default();
+}
+
+/// Shows the last rally again while the next serve is waiting.
+pub fn request_replay(
+    keyboard: Res<ButtonInput<KeyCode>>,
+    settings: Res<Settings>,
+    gamepads: Query<&Gamepad>,
+    game: Res<state::Match>,
+    replay: Res<InstantReplay>,
+    mut app_state: ResMut<NextState<state::AppMode>>,
+) {
+    let pressed = settings.controls.any_just_pressed(&keyboard, Action::Replay)
+        || gamepads.iter().any(|gamepad| gamepad.just_pressed(GamepadButton::Select));
+    if pressed && !replay.last_rally.is_empty() && !game.state.is_ball_active && !game.state.game_over {
+        app_state.set(state::AppMode::InstantReplay);
+    }
+}
+
+pub fn setup_instant_replay(mut commands: Commands, mut replay: ResMut<InstantReplay>) {
+    replay.cursor = 0.0;
+
+    commands.spawn((
+        Node {
+            width: Val::Percent(100.0),
+            position_type: PositionType::Absolute,
+            top: Val::Px(140.0),
+            justify_content: JustifyContent::Center,
+            ..default()
+        },
+        GlobalZIndex(10),
+        InstantReplayUI,
+    )).with_children(|parent| {
+        parent.spawn((
+            Text::new("REPLAY"),
+            TextFont {
+                font_size: 64.0,
+                ..default()
+            },
+            TextColor(Color::srgb(1.0, 0.3, 0.2)),
+        ));
+    });
+}
+
+pub fn cleanup_instant_replay(mut commands: Commands, replay_ui: Query<Entity, With<InstantReplayUI>>) {
+    for entity in replay_ui.iter() {
+        commands.entity(entity).despawn();
+    }
+}
+
+/// Plays the stored rally in slow motion, then hands control back to the match.
+#[allow(clippy::too_many_arguments)]
+pub fn play_instant_replay(
+    time: Res<Time>,
+    keyboard: Res<ButtonInput<KeyCode>>,
+    settings: Res<Settings>,
+    gamepads: Query<&Gamepad>,
+    mut replay: ResMut<InstantReplay>,
+    mut app_state: ResMut<NextState<state::AppMode>>,
+    mut player_query: Query<(&PlayerSlot, &PlayerAnimations, &mut Transform, &mut Sprite), Without<Ball>>,
+    mut ball_query: Query<&mut Transform, With<Ball>>,
+) {
+    let skip = [Action::Serve, Action::Jump, Action::Pause, Action::Replay]
+        .iter()
+        .any(|action| settings.controls.any_just_pressed(&keyboard, *action))
+        || gamepads.iter().any(|gamepad| gamepad.get_just_pressed().next().is_some());
+
+    replay.cursor += time.delta_secs() * config::SIMULATION_HZ as f32 * config::INSTANT_REPLAY_SPEED;
+    let last = replay.last_rally.len().saturating_sub(1);
+    if skip || replay.cursor >= last as f32 {
+        app_state.set(state::AppMode::Playing);
+        return;
+    }
+
+    let index = replay.cursor as usize;
+    let alpha = replay.cursor.fract();
+    let (from, to) = (&replay.last_rally[index], &replay.last_rally[index + 1]);
+
+    // Alternate the run frames on simulation time so they slow down with the replay.
+    let run_frame_ticks = (config::SIMULATION_HZ * 0.1) as usize;
+    for (slot, anims, mut transform, mut sprite) in player_query.iter_mut() {
+        let (Some(a), Some(b)) = (from.players.get(slot.0), to.players.get(slot.0)) else { continue };
+        let pos = a.pos.lerp(b.pos, alpha);
+        transform.translation.x = pos.x;
+        transform.translation.y = pos.y;
+        sprite.flip_x = b.facing_left;
+        sprite.image = match b.state {
+            PlayerState::Idle => anims.stationary.clone(),
+            PlayerState::Jumping => anims.jump.clone(),
+            PlayerState::Running if (index / run_frame_ticks).is_multiple_of(2) => anims.run1.clone(),
+            PlayerState::Running => anims.run2.clone(),
+        };
+    }
+
+    let ball_pos = from.ball_pos.lerp(to.ball_pos, alpha);
+    let ball_rotation = from.ball_rotation + (to.ball_rotation - from.ball_rotation) * alpha;
+    for mut transform in ball_query.iter_mut() {
+        transform.translation.x = ball_pos.x;
+        transform.translation.y = ball_pos.y;
+        transform.rotation = Quat::from_rotation_z(ball_rotation);
+    }
+}
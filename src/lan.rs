@@ -0,0 +1,143 @@
+use bevy::prelude::*;
+use std::net::{Ipv4Addr, SocketAddr, UdpSocket};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use crate::config::{self, SpeedLevel};
+use crate::netplay::{NetRole, NetSession};
+use crate::profiles::Profiles;
+use crate::protocol::{Packet, PROTOCOL_VERSION};
+use crate::settings::Settings;
+use crate::sim::MatchRules;
+
+/// A game someone on the local network is hosting.
+#[derive(Debug, Clone)]
+pub struct LanHost {
+    id: u32,
+    pub addr: SocketAddr,
+    pub name: String,
+    pub speed: SpeedLevel,
+    pub rules: MatchRules,
+    last_seen: Instant,
+}
+
+impl LanHost {
+    pub fn describe(&self) -> String {
+        let mut text = format!("{}   SPEED {}   TO {}", self.name, self.speed.to_u32(), self.rules.winning_score);
+        if self.rules.win_by_two {
+            text.push_str(", WIN BY 2");
+        }
+        if self.rules.touch_rules {
+            text.push_str(", 3 TOUCHES");
+        }
+        if self.rules.sets > 1 {
+            text.push_str(&format!(", BEST OF {}", self.rules.sets));
+        }
+        text
+    }
+}
+
+/// Listens for announcements while the online screen is open.
+///
+/// Only one process per machine can hold the discovery port, so a host gives it up and
+/// whoever is still browsing keeps retrying.
+#[derive(Resource, Default)]
+pub struct LanBrowser {
+    socket: Option<UdpSocket>,
+    last_bind: Option<Instant>,
+    pub hosts: Vec<LanHost>,
+}
+
+pub fn start_browsing(mut commands: Commands) {
+    commands.insert_resource(LanBrowser::default());
+}
+
+pub fn stop_browsing(mut commands: Commands) {
+    commands.remove_resource::<LanBrowser>();
+}
+
+pub fn browse_system(mut browser: ResMut<LanBrowser>, session: Option<Res<NetSession>>) {
+    let browser = &mut *browser;
+    if session.is_some() {
+        browser.socket = None;
+        browser.hosts.clear();
+        return;
+    }
+    if browser.socket.is_none() && browser.last_bind.is_none_or(|tried| tried.elapsed() > Duration::from_secs(1)) {
+        browser.last_bind = Some(Instant::now());
+        browser.socket = UdpSocket::bind((Ipv4Addr::UNSPECIFIED, config::LAN_DISCOVERY_PORT))
+            .and_then(|socket| socket.set_nonblocking(true).map(|_| socket))
+            .ok();
+    }
+    let Some(socket) = &browser.socket else { return };
+
+    let mut buf = [0u8; 512];
+    loop {
+        let (len, from) = match socket.recv_from(&mut buf) {
+            Ok(received) => received,
+            Err(e) if e.kind() == std::io::ErrorKind::WouldBlock => break,
+            Err(e) => {
+                eprintln!("LAN discovery receive failed: {}", e);
+                break;
+            }
+        };
+        let Some(Packet::Announce { version, id, port, speed, rules, name }) = Packet::decode(&buf[..len]) else { continue };
+        if version != PROTOCOL_VERSION {
+            continue;
+        }
+        let host = LanHost { id, addr: SocketAddr::new(from.ip(), port), name, speed, rules, last_seen: Instant::now() };
+        // The same host can be heard over loopback and broadcast; keep the first address.
+        if let Some(known) = browser.hosts.iter_mut().find(|known| known.id == id) {
+            *known = LanHost { addr: known.addr, ..host };
+        } else if browser.hosts.len() < config::LAN_MAX_LISTED {
+            browser.hosts.push(host);
+        }
+    }
+
+    let timeout = Duration::from_secs_f64(config::LAN_HOST_TIMEOUT);
+    browser.hosts.retain(|host| host.last_seen.elapsed() < timeout);
+}
+
+pub struct Announcer {
+    socket: UdpSocket,
+    id: u32,
+    last_sent: Option<Instant>,
+}
+
+/// Tells the local network about a hosted game until someone joins.
+pub fn announce_system(
+    session: Res<NetSession>,
+    settings: Res<Settings>,
+    profiles: Res<Profiles>,
+    mut announcer: Local<Option<Announcer>>,
+) {
+    let (NetRole::Host, Some(port)) = (session.role, session.port()) else { return };
+    if announcer.is_none() {
+        let socket = match UdpSocket::bind((Ipv4Addr::UNSPECIFIED, 0)).and_then(|socket| socket.set_broadcast(true).map(|_| socket)) {
+            Ok(socket) => socket,
+            Err(e) => {
+                eprintln!("LAN announce socket failed: {}", e);
+                return;
+            }
+        };
+        let nanos = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.subsec_nanos()).unwrap_or(0);
+        *announcer = Some(Announcer { socket, id: nanos ^ std::process::id().rotate_left(16), last_sent: None });
+    }
+    let Some(announcer) = announcer.as_mut() else { return };
+    if announcer.last_sent.is_some_and(|sent| sent.elapsed() < Duration::from_secs_f64(config::LAN_ANNOUNCE_INTERVAL)) {
+        return;
+    }
+    announcer.last_sent = Some(Instant::now());
+
+    let packet = Packet::Announce {
+        version: PROTOCOL_VERSION,
+        id: announcer.id,
+        port,
+        speed: settings.speed,
+        rules: settings.match_rules(),
+        name: profiles.slot_name(0).unwrap_or(&settings.net.player_name).to_string(),
+    }
+    .encode();
+    // Loopback covers a second copy of the game on this machine; broadcast fails harmlessly without a network.
+    for target in [Ipv4Addr::BROADCAST, Ipv4Addr::LOCALHOST] {
+        let _ = announcer.socket.send_to(&packet, (target, config::LAN_DISCOVERY_PORT));
+    }
+}
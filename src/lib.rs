@@ -0,0 +1,6 @@
+//! Match simulation and wire protocol, shared by the game and the headless server.
+
+pub mod config;
+pub mod protocol;
+pub mod server;
+pub mod sim;
@@ -1,21 +1,47 @@
 use bevy::prelude::*;
 
-mod config;
+mod audio;
+mod bindings;
+mod cpu;
+mod dinos_menu;
+mod history;
+mod input;
+mod instant_replay;
+mod lan;
+mod netplay;
+mod online_menu;
+mod pause_menu;
+mod practice;
+mod profiles;
+mod profiles_menu;
+mod records_menu;
+mod remote;
+mod replay;
+mod settings;
+mod settings_menu;
+mod skins;
+mod spectate;
 mod state;
+mod training_menu;
 mod types;
 mod util;
 
+use dino_ball::{config, protocol, server, sim};
 use types::*;
-use util::{close_on_esc, load_and_shift};
+use util::close_on_esc;
 use bevy::winit::WinitWindows;
 use winit::window::Icon;
 
 fn main() {
+    let settings = settings::Settings::load();
+    let dino_skins = skins::DinoSkins::default();
+
     App::new()
+        .register_asset_source(skins::SKIN_SOURCE, dino_skins.source())
         .add_plugins(DefaultPlugins.set(WindowPlugin {
             primary_window: Some(Window {
                 title: "Dino Ball".to_string(),
-                mode: bevy::window::WindowMode::Windowed,
+                mode: settings.window_mode.window_mode(),
                 ..default()
             }),
             ..default()
@@ -45,57 +71,171 @@ fn main() {
             },
             ..default()
         }))
-        .init_resource::<state::GameState>()
-        .init_resource::<state::GameSpeed>()
+        .insert_resource(settings)
+        .insert_resource(practice::PersonalBests::load())
+        .insert_resource(practice::DrillRecords::load())
+        .insert_resource(history::MatchHistory::load())
+        .insert_resource(profiles::Profiles::load())
+        .init_resource::<profiles::Players>()
+        .init_resource::<profiles_menu::EditingProfile>()
+        .insert_resource(dino_skins)
+        .init_asset_loader::<skins::HueShiftLoader>()
+        .init_resource::<state::Match>()
+        .init_resource::<state::PlayerInputs>()
+        .init_resource::<state::CpuPlayers>()
+        .init_resource::<state::GamepadSlots>()
+        .init_resource::<settings_menu::RebindTarget>()
+        .init_resource::<state::SettingsOrigin>()
+        .init_resource::<replay::Recorder>()
+        .init_resource::<instant_replay::InstantReplay>()
+        .init_resource::<online_menu::OnlineStatus>()
+        .init_resource::<online_menu::OnlineFocus>()
         .init_state::<state::AppMode>()
-        .add_systems(Startup, (setup, setup_menu))
+        .add_message::<state::GameEvent>()
+        .insert_resource(Time::<Fixed>::from_hz(config::SIMULATION_HZ))
+        .add_systems(Startup, (setup, setup_menu, spectate::setup_spectator_text, audio::load_sound_effects))
         .add_systems(Update, (
-            close_on_esc,
+            close_on_esc.run_if(in_state(state::AppMode::Menu)),
             set_window_icon,
+            sync_arena_to_window.run_if(local_match.and(not(in_state(state::AppMode::Replay)))),
             dynamic_layout,
+            input::assign_gamepads,
+            settings::save_settings,
+            settings::apply_window_mode,
+            audio::play_sound_effects,
+            spectate::sync_publisher,
+            spectate::publish_idle.run_if(resource_exists::<spectate::Publisher>),
+            spectate::spectator_text_system,
+            practice::target_zone_system,
+            profiles::save_profiles,
+            skins::apply_dino_colors,
         ))
         .add_systems(Update, (
             menu_button_system,
             menu_color_system,
         ).run_if(in_state(state::AppMode::Menu)))
+        .add_systems(OnEnter(state::AppMode::Settings), settings_menu::setup_settings_menu)
+        .add_systems(OnExit(state::AppMode::Settings), settings_menu::cleanup_settings_menu)
+        .add_systems(Update, (
+            settings_menu::settings_button_system,
+            settings_menu::capture_rebind_system,
+            settings_menu::settings_display_system,
+        ).chain().run_if(in_state(state::AppMode::Settings)))
+        .add_systems(OnEnter(state::AppMode::Paused), pause_menu::setup_pause_menu)
+        .add_systems(OnExit(state::AppMode::Paused), pause_menu::cleanup_pause_menu)
+        .add_systems(Update, pause_menu::toggle_pause_system
+            .run_if(in_state(state::AppMode::Playing).or(in_state(state::AppMode::Paused))))
+        .add_systems(Update, pause_menu::pause_button_system.run_if(in_state(state::AppMode::Paused)))
+        .add_systems(OnEnter(state::AppMode::Menu), (
+            replay::finish_recording,
+            instant_replay::clear_instant_replay,
+            netplay::end_session,
+            remote::end_session,
+        ))
+        .add_systems(OnEnter(state::AppMode::Online), (online_menu::setup_online_menu, lan::start_browsing))
+        .add_systems(OnExit(state::AppMode::Online), (online_menu::cleanup_online_menu, lan::stop_browsing))
+        .add_systems(Update, (
+            online_menu::online_button_system,
+            online_menu::text_input_system,
+            lan::browse_system.run_if(resource_exists::<lan::LanBrowser>),
+            online_menu::lan_list_system,
+            online_menu::online_display_system,
+            lan::announce_system.run_if(resource_exists::<netplay::NetSession>),
+            netplay::lobby_system.run_if(resource_exists::<netplay::NetSession>),
+            remote::connect_system.run_if(resource_exists::<remote::RemoteSession>),
+        ).chain().run_if(in_state(state::AppMode::Online)))
+        .add_systems(OnEnter(state::AppMode::Training), training_menu::setup_training_menu)
+        .add_systems(OnExit(state::AppMode::Training), training_menu::cleanup_training_menu)
         .add_systems(Update, (
-            input_system,
-            player_movement, 
-            ball_system,
-            score_and_reset_system,
+            training_menu::training_button_system,
+            training_menu::training_display_system,
+        ).chain().run_if(in_state(state::AppMode::Training)))
+        .add_systems(OnEnter(state::AppMode::Profiles), profiles_menu::setup_profiles_menu)
+        .add_systems(OnExit(state::AppMode::Profiles), profiles_menu::cleanup_profiles_menu)
+        .add_systems(Update, (
+            profiles_menu::profiles_button_system,
+            profiles_menu::name_input_system,
+            profiles_menu::profiles_display_system,
+        ).chain().run_if(in_state(state::AppMode::Profiles)))
+        .add_systems(OnEnter(state::AppMode::Dinos), dinos_menu::setup_dinos_menu)
+        .add_systems(OnExit(state::AppMode::Dinos), dinos_menu::cleanup_dinos_menu)
+        .add_systems(Update, (
+            dinos_menu::dinos_button_system,
+            dinos_menu::dinos_display_system,
+        ).chain().run_if(in_state(state::AppMode::Dinos)))
+        .add_systems(OnEnter(state::AppMode::Records), records_menu::setup_records_menu)
+        .add_systems(OnExit(state::AppMode::Records), records_menu::cleanup_records_menu)
+        .add_systems(Update, records_menu::records_button_system.run_if(in_state(state::AppMode::Records)))
+        .add_systems(OnEnter(state::AppMode::Replay), replay::setup_playback)
+        .add_systems(OnExit(state::AppMode::Replay), replay::cleanup_playback)
+        .add_systems(Update, (replay::playback_controls, replay::replay_text_system)
+            .chain()
+            .run_if(in_state(state::AppMode::Replay)))
+        .add_systems(Update, profiles::resolve_players
+            .before(name_text_system)
+            .before(win_system)
+            .run_if(in_state(state::AppMode::Playing)))
+        .add_systems(Update, (
+            sync_transforms,
+            score_text_system,
+            name_text_system,
             player_animation,
+        ).run_if(in_state(state::AppMode::Playing).or(in_state(state::AppMode::Replay))))
+        .add_systems(Update, (
+            remote::snapshot_system.before(sync_transforms).before(score_text_system),
+            remote::status_system.after(score_text_system),
+        ).run_if(in_state(state::AppMode::Playing).or(in_state(state::AppMode::Paused)).and(resource_exists::<remote::RemoteSession>)))
+        .add_systems(Update, (
+            pause_menu::pause_on_focus_loss.run_if(local_match),
+            practice::record_keep_up.run_if(local_match),
+            practice::record_drills.run_if(local_match),
+            history::record_match,
+            instant_replay::request_replay.run_if(local_match),
             win_system,
             button_system,
         ).run_if(in_state(state::AppMode::Playing)))
+        .add_systems(OnEnter(state::AppMode::InstantReplay), instant_replay::setup_instant_replay)
+        .add_systems(OnExit(state::AppMode::InstantReplay), instant_replay::cleanup_instant_replay)
+        .add_systems(Update, instant_replay::play_instant_replay.run_if(in_state(state::AppMode::InstantReplay)))
+        .add_systems(RunFixedMainLoop, (input::keyboard_input_system, input::gamepad_input_system)
+            .chain()
+            .in_set(RunFixedMainLoopSystems::BeforeFixedMainLoop)
+            .run_if(in_state(state::AppMode::Playing)))
+        .add_systems(FixedUpdate, (
+            cpu_input_system,
+            simulate_match,
+            replay::record_tick,
+            instant_replay::record_frames,
+            instant_replay::replay_match_point,
+            spectate::publish_tick.run_if(resource_exists::<spectate::Publisher>),
+        )
+            .chain()
+            .run_if(in_state(state::AppMode::Playing).and(local_match)))
+        .add_systems(FixedUpdate, (
+            netplay::net_tick,
+            instant_replay::record_frames,
+            spectate::publish_tick.run_if(resource_exists::<spectate::Publisher>),
+        )
+            .chain()
+            .run_if(in_state(state::AppMode::Playing).or(in_state(state::AppMode::Paused)).and(resource_exists::<netplay::NetSession>)))
+        .add_systems(FixedUpdate, remote::input_system
+            .run_if(in_state(state::AppMode::Playing).or(in_state(state::AppMode::Paused)).and(resource_exists::<remote::RemoteSession>)))
+        .add_systems(FixedUpdate, replay::playback_tick.run_if(in_state(state::AppMode::Replay)))
         .run();
 }
 
-fn input_system(
-    keyboard: Res<ButtonInput<KeyCode>>,
-    mut game_state: ResMut<state::GameState>,
-    mut app_state: ResMut<NextState<state::AppMode>>,
-    menu_ui: Query<Entity, With<MenuUI>>,
-    mut commands: Commands,
-) {
-    if keyboard.just_pressed(KeyCode::KeyR) {
-        game_state.player1_score = 0;
-        game_state.player2_score = 0;
-        game_state.is_ball_active = false;
-        game_state.game_over = false;
-        game_state.winner = None;
-        app_state.set(state::AppMode::Menu);
-        for entity in menu_ui.iter() {
-            commands.entity(entity).insert(Visibility::Visible);
-        }
-    }
+/// Neither a peer nor a server is driving the match.
+fn local_match(peer: Option<Res<netplay::NetSession>>, server: Option<Res<remote::RemoteSession>>) -> bool {
+    peer.is_none() && server.is_none()
 }
 
+#[allow(clippy::type_complexity)]
 fn button_system(
     mut interaction_query: Query<
         (&Interaction, &mut BackgroundColor),
         (Changed<Interaction>, With<Button>, With<RestartButton>),
     >,
-    mut game_state: ResMut<state::GameState>,
+    mut game: ResMut<state::Match>,
     mut commands: Commands,
     win_ui: Query<Entity, With<WinUI>>,
     mut app_state: ResMut<NextState<state::AppMode>>,
@@ -104,11 +244,7 @@ fn button_system(
     for (interaction, mut color) in interaction_query.iter_mut() {
         match *interaction {
             Interaction::Pressed => {
-                game_state.player1_score = 0;
-                game_state.player2_score = 0;
-                game_state.is_ball_active = false;
-                game_state.game_over = false;
-                game_state.winner = None;
+                game.reset();
                 for entity in win_ui.iter() {
                     commands.entity(entity).despawn();
                 }
@@ -127,21 +263,117 @@ fn button_system(
     }
 }
 
+#[allow(clippy::type_complexity, clippy::too_many_arguments)]
 fn menu_button_system(
     mut interaction_query: Query<
-        (&Interaction, Option<&SpeedButton>, Option<&StartButton>),
+        (
+            &Interaction,
+            Option<&SpeedButton>,
+            Option<&TargetScoreButton>,
+            Option<&SetsButton>,
+            Option<&RuleToggle>,
+            Option<&DifficultyButton>,
+            Option<&StartButton>,
+            Has<SettingsButton>,
+            Has<WatchReplayButton>,
+            Has<OnlineMenuButton>,
+            Has<TrainingMenuButton>,
+            Has<RecordsMenuButton>,
+            Has<ProfilesMenuButton>,
+            Has<DinosMenuButton>,
+        ),
         (Changed<Interaction>, With<Button>),
     >,
-    mut game_speed: ResMut<state::GameSpeed>,
+    mut settings: ResMut<settings::Settings>,
+    mut cpu_players: ResMut<state::CpuPlayers>,
+    mut game: ResMut<state::Match>,
+    mut settings_origin: ResMut<state::SettingsOrigin>,
+    mut recorder: ResMut<replay::Recorder>,
     mut app_state: ResMut<NextState<state::AppMode>>,
     mut menu_ui: Query<Entity, With<MenuUI>>,
     mut commands: Commands,
 ) {
-    for (interaction, speed_btn, start_btn) in interaction_query.iter_mut() {
+    for (interaction, speed_btn, target_btn, sets_btn, rule_btn, difficulty_btn, start_btn, settings_btn, replay_btn, online_btn, training_btn, records_btn, profiles_btn, dinos_btn) in interaction_query.iter_mut() {
         if *interaction == Interaction::Pressed {
-            if let Some(sb) = speed_btn {
-                game_speed.level = sb.0;
-            } else if start_btn.is_some() {
+            if settings_btn {
+                settings_origin.0 = state::AppMode::Menu;
+                app_state.set(state::AppMode::Settings);
+                for entity in menu_ui.iter_mut() {
+                    commands.entity(entity).insert(Visibility::Hidden);
+                }
+            } else if training_btn {
+                app_state.set(state::AppMode::Training);
+                for entity in menu_ui.iter_mut() {
+                    commands.entity(entity).insert(Visibility::Hidden);
+                }
+            } else if records_btn {
+                app_state.set(state::AppMode::Records);
+                for entity in menu_ui.iter_mut() {
+                    commands.entity(entity).insert(Visibility::Hidden);
+                }
+            } else if profiles_btn {
+                app_state.set(state::AppMode::Profiles);
+                for entity in menu_ui.iter_mut() {
+                    commands.entity(entity).insert(Visibility::Hidden);
+                }
+            } else if dinos_btn {
+                app_state.set(state::AppMode::Dinos);
+                for entity in menu_ui.iter_mut() {
+                    commands.entity(entity).insert(Visibility::Hidden);
+                }
+            } else if online_btn {
+                app_state.set(state::AppMode::Online);
+                for entity in menu_ui.iter_mut() {
+                    commands.entity(entity).insert(Visibility::Hidden);
+                }
+            } else if replay_btn {
+                let loaded = replay::Replay::latest()
+                    .ok_or_else(|| std::io::Error::other("no replays recorded yet"))
+                    .and_then(|path| replay::Replay::load(&path));
+                match loaded {
+                    Ok(replay) => {
+                        commands.insert_resource(replay::Playback::new(replay));
+                        app_state.set(state::AppMode::Replay);
+                        for entity in menu_ui.iter_mut() {
+                            commands.entity(entity).insert(Visibility::Hidden);
+                        }
+                    }
+                    Err(e) => eprintln!("Failed to load replay: {}", e),
+                }
+            } else if let Some(sb) = speed_btn {
+                settings.speed = sb.0;
+            } else if let Some(tb) = target_btn {
+                settings.winning_score = tb.0;
+            } else if let Some(sb) = sets_btn {
+                settings.sets = sb.0;
+            } else if let Some(rule) = rule_btn {
+                match rule {
+                    RuleToggle::WinByTwo => settings.win_by_two = !settings.win_by_two,
+                    RuleToggle::TouchRules => settings.touch_rules = !settings.touch_rules,
+                    RuleToggle::KeepUpOverNet => settings.keep_up_over_net = !settings.keep_up_over_net,
+                }
+            } else if let Some(db) = difficulty_btn {
+                cpu_players.difficulty = db.0;
+            } else if let Some(start) = start_btn {
+                game.speed = settings.speed;
+                game.rules = sim::MatchRules { team_size: start.team_size, ..settings.match_rules() };
+                if start.keep_up {
+                    // Alone on court there's nobody to pass to, so touch limits don't apply.
+                    game.rules.mode = sim::GameMode::KeepUp { over_net: settings.keep_up_over_net };
+                    game.rules.touch_rules = false;
+                    game.rules.sets = 1;
+                }
+                game.reset();
+                cpu_players.controllers.clear();
+                let seed = std::time::SystemTime::now()
+                    .duration_since(std::time::UNIX_EPOCH)
+                    .map(|d| d.subsec_nanos())
+                    .unwrap_or(1);
+                recorder.start(&game, seed);
+                if start.vs_cpu {
+                    let difficulty = cpu_players.difficulty;
+                    cpu_players.controllers.push((1, cpu::CpuController::new(difficulty, seed)));
+                }
                 app_state.set(state::AppMode::Playing);
                 for entity in menu_ui.iter_mut() {
                     commands.entity(entity).insert(Visibility::Hidden);
@@ -151,30 +383,73 @@ fn menu_button_system(
     }
 }
 
+fn option_color(interaction: Interaction, is_selected: bool) -> BackgroundColor {
+    match interaction {
+        Interaction::Pressed => BackgroundColor(Color::srgb(0.5, 0.5, 0.2)),
+        Interaction::Hovered => {
+            if is_selected {
+                BackgroundColor(Color::srgb(0.7, 0.7, 0.3))
+            } else {
+                BackgroundColor(Color::srgb(0.4, 0.4, 0.4))
+            }
+        }
+        Interaction::None => {
+            if is_selected {
+                BackgroundColor(Color::srgb(0.6, 0.6, 0.2))
+            } else {
+                BackgroundColor(Color::srgb(0.2, 0.2, 0.2))
+            }
+        }
+    }
+}
+
+#[allow(clippy::type_complexity, clippy::too_many_arguments)]
 fn menu_color_system(
-    game_speed: Res<state::GameSpeed>,
+    settings: Res<settings::Settings>,
+    cpu_players: Res<state::CpuPlayers>,
     mut speed_buttons: Query<(&Interaction, &mut BackgroundColor, &SpeedButton)>,
-    mut start_button: Query<(&Interaction, &mut BackgroundColor), (With<StartButton>, Without<SpeedButton>)>,
+    mut target_buttons: Query<(&Interaction, &mut BackgroundColor, &TargetScoreButton), Without<SpeedButton>>,
+    mut sets_buttons: Query<(&Interaction, &mut BackgroundColor, &SetsButton), (Without<SpeedButton>, Without<TargetScoreButton>)>,
+    mut rule_buttons: Query<(&Interaction, &mut BackgroundColor, &RuleToggle), (Without<SpeedButton>, Without<TargetScoreButton>, Without<SetsButton>)>,
+    mut difficulty_buttons: Query<
+        (&Interaction, &mut BackgroundColor, &DifficultyButton),
+        (Without<SpeedButton>, Without<TargetScoreButton>, Without<SetsButton>, Without<RuleToggle>),
+    >,
+    mut start_button: Query<
+        (&Interaction, &mut BackgroundColor),
+        (
+            Or<(With<StartButton>, With<SettingsButton>, With<WatchReplayButton>, With<OnlineMenuButton>, With<TrainingMenuButton>, With<RecordsMenuButton>, With<ProfilesMenuButton>, With<DinosMenuButton>)>,
+            Without<SpeedButton>,
+            Without<TargetScoreButton>,
+            Without<SetsButton>,
+            Without<RuleToggle>,
+            Without<DifficultyButton>,
+        ),
+    >,
 ) {
     for (interaction, mut color, sb) in speed_buttons.iter_mut() {
-        let is_selected = sb.0 == game_speed.level;
-        match *interaction {
-            Interaction::Pressed => { *color = BackgroundColor(Color::srgb(0.5, 0.5, 0.2)); }
-            Interaction::Hovered => {
-                if is_selected {
-                    *color = BackgroundColor(Color::srgb(0.7, 0.7, 0.3));
-                } else {
-                    *color = BackgroundColor(Color::srgb(0.4, 0.4, 0.4));
-                }
-            }
-            Interaction::None => {
-                if is_selected {
-                    *color = BackgroundColor(Color::srgb(0.6, 0.6, 0.2));
-                } else {
-                    *color = BackgroundColor(Color::srgb(0.2, 0.2, 0.2));
-                }
-            }
-        }
+        *color = option_color(*interaction, sb.0 == settings.speed);
+    }
+
+    for (interaction, mut color, tb) in target_buttons.iter_mut() {
+        *color = option_color(*interaction, tb.0 == settings.winning_score);
+    }
+
+    for (interaction, mut color, sb) in sets_buttons.iter_mut() {
+        *color = option_color(*interaction, sb.0 == settings.sets);
+    }
+
+    for (interaction, mut color, rule) in rule_buttons.iter_mut() {
+        let enabled = match rule {
+            RuleToggle::WinByTwo => settings.win_by_two,
+            RuleToggle::TouchRules => settings.touch_rules,
+            RuleToggle::KeepUpOverNet => settings.keep_up_over_net,
+        };
+        *color = option_color(*interaction, enabled);
+    }
+
+    for (interaction, mut color, db) in difficulty_buttons.iter_mut() {
+        *color = option_color(*interaction, db.0 == cpu_players.difficulty);
     }
 
     for (interaction, mut color) in start_button.iter_mut() {
@@ -221,18 +496,34 @@ fn set_window_icon(
     }
 }
 
+#[allow(clippy::type_complexity, clippy::too_many_arguments)]
 fn dynamic_layout(
-    mut background_query: Query<&mut Sprite, (With<Background>, Without<Net>, Without<Player1>, Without<Player2>, Without<Ball>)>,
-    mut net_query: Query<(&mut Sprite, &mut Transform), (With<Net>, Without<Background>, Without<Player1>, Without<Player2>, Without<Ball>)>,
-    mut player_query: Query<&mut Sprite, (Or<(With<Player1>, With<Player2>)>, Without<Background>, Without<Net>, Without<Ball>)>,
-    mut ball_query: Query<&mut Sprite, (With<Ball>, Without<Background>, Without<Net>, Without<Player1>, Without<Player2>)>,
+    mut background_query: Query<&mut Sprite, (With<Background>, Without<Net>, Without<Team>, Without<Ball>)>,
+    mut net_query: Query<(&mut Sprite, &mut Transform), (With<Net>, Without<Background>, Without<Team>, Without<Ball>)>,
+    mut player_query: Query<&mut Sprite, (With<Team>, Without<Background>, Without<Net>, Without<Ball>)>,
+    mut ball_query: Query<&mut Sprite, (With<Ball>, Without<Background>, Without<Net>, Without<Team>)>,
     mut score1_query: Query<&mut Transform, (With<ScoreText1>, Without<ScoreText2>, Without<Sprite>)>,
     mut score2_query: Query<&mut Transform, (With<ScoreText2>, Without<ScoreText1>, Without<Sprite>)>,
+    mut status_query: Query<&mut Transform, (With<StatusText>, Without<ScoreText1>, Without<ScoreText2>, Without<Sprite>)>,
+    mut touch_query: Query<(&mut Transform, &TouchText), (Without<StatusText>, Without<ScoreText1>, Without<ScoreText2>, Without<Sprite>)>,
+    mut set_query: Query<(&mut Transform, &SetText), (Without<TouchText>, Without<StatusText>, Without<ScoreText1>, Without<ScoreText2>, Without<Sprite>)>,
+    mut name_query: Query<(&mut Transform, &NameText), (Without<SetText>, Without<TouchText>, Without<StatusText>, Without<ScoreText1>, Without<ScoreText2>, Without<Sprite>)>,
+    mut camera_query: Query<&mut Projection, With<Camera2d>>,
+    game: Res<state::Match>,
     windows: Query<&Window, With<bevy::window::PrimaryWindow>>,
 ) {
     let Some(window) = windows.iter().next() else { return };
-    let width = window.width();
-    let height = window.height();
+    // Lay out the court the match is played on and zoom the camera so it fits the window.
+    let width = game.arena.width;
+    let height = game.arena.height;
+    let scale = (width / window.width()).max(height / window.height());
+    for mut projection in camera_query.iter_mut() {
+        if let Projection::Orthographic(ortho) = &mut *projection
+            && ortho.scale != scale
+        {
+            ortho.scale = scale;
+        }
+    }
 
     for mut sprite in background_query.iter_mut() {
         sprite.custom_size = Some(Vec2::new(width, height));
@@ -262,10 +553,38 @@ fn dynamic_layout(
     for mut transform in score2_query.iter_mut() {
         transform.translation = Vec3::new(width / 2.0 - 100.0, height / 2.0 - 80.0, 10.0);
     }
+    for mut transform in status_query.iter_mut() {
+        transform.translation = Vec3::new(0.0, height / 2.0 - 80.0, 10.0);
+    }
+    for (mut transform, touch) in touch_query.iter_mut() {
+        let x = match touch.0 {
+            sim::Side::Left => -width / 2.0 + 100.0,
+            sim::Side::Right => width / 2.0 - 100.0,
+        };
+        transform.translation = Vec3::new(x, height / 2.0 - 140.0, 10.0);
+    }
+    for (mut transform, set) in set_query.iter_mut() {
+        let x = match set.0 {
+            sim::Side::Left => -width / 2.0 + 190.0,
+            sim::Side::Right => width / 2.0 - 190.0,
+        };
+        transform.translation = Vec3::new(x, height / 2.0 - 80.0, 10.0);
+    }
+    for (mut transform, name) in name_query.iter_mut() {
+        let x = match name.0 {
+            sim::Side::Left => -width / 2.0 + 100.0,
+            sim::Side::Right => width / 2.0 - 100.0,
+        };
+        transform.translation = Vec3::new(x, height / 2.0 - 28.0, 10.0);
+    }
 }
 
-fn setup(mut commands: Commands, asset_server: Res<AssetServer>, mut images: ResMut<Assets<Image>>) {
-    commands.spawn(Camera2d::default());
+fn setup(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    mut skins: ResMut<skins::DinoSkins>,
+) {
+    commands.spawn(Camera2d);
 
     commands.spawn((
         Sprite {
@@ -285,50 +604,32 @@ fn setup(mut commands: Commands, asset_server: Res<AssetServer>, mut images: Res
         Net,
     ));
 
-    let p1_anims = PlayerAnimations {
-        stationary: asset_server.load(config::ASSET_PLAYER_STATIONARY),
-        jump: asset_server.load(config::ASSET_PLAYER_JUMP),
-        run1: asset_server.load(config::ASSET_PLAYER_RUN1),
-        run2: asset_server.load(config::ASSET_PLAYER_RUN2),
-    };
-
-    commands.spawn((
-        Sprite {
-            image: p1_anims.stationary.clone(),
-            color: Color::WHITE,
-            ..default()
-        },
-        Transform::from_xyz(-300.0, 0.0, 2.0),
-        Player1,
-        Velocity::default(),
-        p1_anims.clone(),
-        PlayerState::Idle,
-        AnimationTimer(Timer::from_seconds(0.1, TimerMode::Repeating)),
-        FacingLeft(false),
-    ));
-
-    let shift = 0.45;
-    let p2_anims = PlayerAnimations {
-        stationary: load_and_shift(config::ASSET_PLAYER_STATIONARY, shift).map(|i| images.add(i)).unwrap_or_else(|| p1_anims.stationary.clone()),
-        jump: load_and_shift(config::ASSET_PLAYER_JUMP, shift).map(|i| images.add(i)).unwrap_or_else(|| p1_anims.jump.clone()),
-        run1: load_and_shift(config::ASSET_PLAYER_RUN1, shift).map(|i| images.add(i)).unwrap_or_else(|| p1_anims.run1.clone()),
-        run2: load_and_shift(config::ASSET_PLAYER_RUN2, shift).map(|i| images.add(i)).unwrap_or_else(|| p1_anims.run2.clone()),
-    };
+    // Slots alternate sides like `MatchSim::players`; dinos beyond the current match stay hidden.
+    for (slot, shift) in config::PLAYER_HUE_SHIFTS.into_iter().enumerate() {
+        let anims = skins.get(shift, &asset_server);
+        let side = if slot % 2 == 0 { sim::Side::Left } else { sim::Side::Right };
+        commands.spawn((
+            Sprite {
+                image: anims.stationary.clone(),
+                color: Color::WHITE,
+                flip_x: side == sim::Side::Right,
+                ..default()
+            },
+            Transform::from_xyz(if side == sim::Side::Left { -300.0 } else { 300.0 }, 0.0, 2.0 + slot as f32 * 0.1),
+            if slot < 2 { Visibility::Inherited } else { Visibility::Hidden },
+            Team(side),
+            PlayerSlot(slot),
+            DinoHue(shift),
+            anims,
+            AnimationTimer(Timer::from_seconds(0.1, TimerMode::Repeating)),
+        ));
+    }
 
     commands.spawn((
-        Sprite {
-            image: p2_anims.stationary.clone(),
-            color: Color::WHITE, 
-            flip_x: true,
-            ..default()
-        },
-        Transform::from_xyz(300.0, 0.0, 2.0),
-        Player2,
-        Velocity::default(),
-        p2_anims,
-        PlayerState::Idle,
-        AnimationTimer(Timer::from_seconds(0.1, TimerMode::Repeating)),
-        FacingLeft(true),
+        Sprite::from_color(Color::srgba(0.3, 1.0, 0.3, 0.4), Vec2::ONE),
+        Transform::from_xyz(0.0, 0.0, 1.5),
+        Visibility::Hidden,
+        TargetZoneMarker,
     ));
 
     commands.spawn((
@@ -338,8 +639,6 @@ fn setup(mut commands: Commands, asset_server: Res<AssetServer>, mut images: Res
         },
         Transform::from_xyz(0.0, 200.0, 5.0),
         Ball,
-        Velocity::default(),
-        AngularVelocity::default(),
     ));
 
     commands.spawn((
@@ -363,6 +662,50 @@ fn setup(mut commands: Commands, asset_server: Res<AssetServer>, mut images: Res
         Transform::from_xyz(0.0, 0.0, 10.0),
         ScoreText2,
     ));
+
+    commands.spawn((
+        Text2d::new(""),
+        TextFont {
+            font_size: 48.0,
+            ..default()
+        },
+        TextColor(Color::srgb(1.0, 0.85, 0.2)),
+        Transform::from_xyz(0.0, 0.0, 10.0),
+        StatusText,
+    ));
+
+    for side in [sim::Side::Left, sim::Side::Right] {
+        commands.spawn((
+            Text2d::new(""),
+            TextFont {
+                font_size: 24.0,
+                ..default()
+            },
+            TextColor(Color::WHITE),
+            Transform::from_xyz(0.0, 0.0, 10.0),
+            TouchText(side),
+        ));
+        commands.spawn((
+            Text2d::new(""),
+            TextFont {
+                font_size: 40.0,
+                ..default()
+            },
+            TextColor(Color::srgb(0.8, 0.8, 0.8)),
+            Transform::from_xyz(0.0, 0.0, 10.0),
+            SetText(side),
+        ));
+        commands.spawn((
+            Text2d::new(""),
+            TextFont {
+                font_size: 24.0,
+                ..default()
+            },
+            TextColor(Color::WHITE),
+            Transform::from_xyz(0.0, 0.0, 10.0),
+            NameText(side),
+        ));
+    }
 }
 
 fn setup_menu(mut commands: Commands) {
@@ -386,20 +729,137 @@ fn setup_menu(mut commands: Commands) {
             },
             TextColor(Color::WHITE),
             Node {
-                margin: UiRect::bottom(Val::Px(40.0)),
+                margin: UiRect::bottom(Val::Px(20.0)),
                 ..default()
             },
         ));
 
+        // Speed and set count share a row to keep the menu within a 720px window.
+        parent.spawn(Node {
+            flex_direction: FlexDirection::Row,
+            justify_content: JustifyContent::Center,
+            margin: UiRect::bottom(Val::Px(10.0)),
+            ..default()
+        }).with_children(|columns| {
+            columns.spawn(Node {
+                flex_direction: FlexDirection::Column,
+                align_items: AlignItems::Center,
+                margin: UiRect::horizontal(Val::Px(30.0)),
+                ..default()
+            }).with_children(|column| {
+                column.spawn((
+                    Text::new("SELECT GAME SPEED"),
+                    TextFont {
+                        font_size: 32.0,
+                        ..default()
+                    },
+                    TextColor(Color::WHITE),
+                    Node {
+                        margin: UiRect::bottom(Val::Px(8.0)),
+                        ..default()
+                    },
+                ));
+
+                column.spawn(Node {
+                    flex_direction: FlexDirection::Row,
+                    justify_content: JustifyContent::Center,
+                    align_items: AlignItems::Center,
+                    ..default()
+                }).with_children(|row| {
+                    let levels = [
+                        config::SpeedLevel::Slow,
+                        config::SpeedLevel::Normal,
+                        config::SpeedLevel::Fast,
+                    ];
+                    for level in levels {
+                        row.spawn((
+                            Button,
+                            Node {
+                                width: Val::Px(60.0),
+                                height: Val::Px(60.0),
+                                margin: UiRect::all(Val::Px(10.0)),
+                                justify_content: JustifyContent::Center,
+                                align_items: AlignItems::Center,
+                                ..default()
+                            },
+                            BackgroundColor(Color::srgb(0.2, 0.2, 0.2)),
+                            SpeedButton(level),
+                        )).with_children(|btn| {
+                            btn.spawn((
+                                Text::new(level.to_u32().to_string()),
+                                TextFont {
+                                    font_size: 24.0,
+                                    ..default()
+                                },
+                                TextColor(Color::WHITE),
+                            ));
+                        });
+                    }
+                });
+            });
+
+            columns.spawn(Node {
+                flex_direction: FlexDirection::Column,
+                align_items: AlignItems::Center,
+                margin: UiRect::horizontal(Val::Px(30.0)),
+                ..default()
+            }).with_children(|column| {
+                column.spawn((
+                    Text::new("BEST OF"),
+                    TextFont {
+                        font_size: 32.0,
+                        ..default()
+                    },
+                    TextColor(Color::WHITE),
+                    Node {
+                        margin: UiRect::bottom(Val::Px(8.0)),
+                        ..default()
+                    },
+                ));
+
+                column.spawn(Node {
+                    flex_direction: FlexDirection::Row,
+                    justify_content: JustifyContent::Center,
+                    align_items: AlignItems::Center,
+                    ..default()
+                }).with_children(|row| {
+                    for sets in config::SET_COUNTS {
+                        row.spawn((
+                            Button,
+                            Node {
+                                width: Val::Px(60.0),
+                                height: Val::Px(60.0),
+                                margin: UiRect::all(Val::Px(10.0)),
+                                justify_content: JustifyContent::Center,
+                                align_items: AlignItems::Center,
+                                ..default()
+                            },
+                            BackgroundColor(Color::srgb(0.2, 0.2, 0.2)),
+                            SetsButton(sets),
+                        )).with_children(|btn| {
+                            btn.spawn((
+                                Text::new(sets.to_string()),
+                                TextFont {
+                                    font_size: 24.0,
+                                    ..default()
+                                },
+                                TextColor(Color::WHITE),
+                            ));
+                        });
+                    }
+                });
+            });
+        });
+
         parent.spawn((
-            Text::new("SELECT GAME SPEED"),
+            Text::new("MATCH LENGTH"),
             TextFont {
                 font_size: 32.0,
                 ..default()
             },
             TextColor(Color::WHITE),
             Node {
-                margin: UiRect::bottom(Val::Px(20.0)),
+                margin: UiRect::bottom(Val::Px(8.0)),
                 ..default()
             },
         ));
@@ -408,15 +868,10 @@ fn setup_menu(mut commands: Commands) {
             flex_direction: FlexDirection::Row,
             justify_content: JustifyContent::Center,
             align_items: AlignItems::Center,
-            margin: UiRect::bottom(Val::Px(40.0)),
+            margin: UiRect::bottom(Val::Px(10.0)),
             ..default()
         }).with_children(|row| {
-            let levels = [
-                config::SpeedLevel::Slow,
-                config::SpeedLevel::Normal,
-                config::SpeedLevel::Fast,
-            ];
-            for level in levels {
+            for score in config::TARGET_SCORES {
                 row.spawn((
                     Button,
                     Node {
@@ -428,10 +883,34 @@ fn setup_menu(mut commands: Commands) {
                         ..default()
                     },
                     BackgroundColor(Color::srgb(0.2, 0.2, 0.2)),
-                    SpeedButton(level),
+                    TargetScoreButton(score),
+                )).with_children(|btn| {
+                    btn.spawn((
+                        Text::new(score.to_string()),
+                        TextFont {
+                            font_size: 24.0,
+                            ..default()
+                        },
+                        TextColor(Color::WHITE),
+                    ));
+                });
+            }
+            for (label, rule) in [("WIN BY 2", RuleToggle::WinByTwo), ("3 TOUCHES", RuleToggle::TouchRules)] {
+                row.spawn((
+                    Button,
+                    Node {
+                        width: Val::Px(150.0),
+                        height: Val::Px(60.0),
+                        margin: UiRect::all(Val::Px(10.0)),
+                        justify_content: JustifyContent::Center,
+                        align_items: AlignItems::Center,
+                        ..default()
+                    },
+                    BackgroundColor(Color::srgb(0.2, 0.2, 0.2)),
+                    rule,
                 )).with_children(|btn| {
                     btn.spawn((
-                        Text::new(level.to_u32().to_string()),
+                        Text::new(label),
                         TextFont {
                             font_size: 24.0,
                             ..default()
@@ -443,240 +922,503 @@ fn setup_menu(mut commands: Commands) {
         });
 
         parent.spawn((
-            Button,
+            Text::new("CPU DIFFICULTY"),
+            TextFont {
+                font_size: 32.0,
+                ..default()
+            },
+            TextColor(Color::WHITE),
             Node {
-                width: Val::Px(240.0),
-                height: Val::Px(80.0),
-                justify_content: JustifyContent::Center,
-                align_items: AlignItems::Center,
+                margin: UiRect::bottom(Val::Px(8.0)),
                 ..default()
             },
-            BackgroundColor(Color::srgb(0.2, 0.2, 0.2)),
-            StartButton,
-        )).with_children(|btn| {
-            btn.spawn((
-                Text::new("START"),
-                TextFont {
-                    font_size: 40.0,
-                    ..default()
-                },
-                TextColor(Color::WHITE),
-            ));
-        });
-    });
-}
-
-fn ball_system(
-    mut ball_query: Query<(&mut Transform, &mut Velocity, &mut AngularVelocity), (With<Ball>, Without<Player1>, Without<Player2>)>,
-    player1_query: Query<(&Transform, &Velocity), (With<Player1>, Without<Ball>, Without<Player2>)>,
-    player2_query: Query<(&Transform, &Velocity), (With<Player2>, Without<Ball>, Without<Player1>)>,
-    mut game_state: ResMut<state::GameState>,
-    game_speed: Res<state::GameSpeed>,
-    time: Res<Time>,
-    windows: Query<&Window, With<bevy::window::PrimaryWindow>>,
-    keyboard_input: Res<ButtonInput<KeyCode>>,
-) {
-    let Some(window) = windows.iter().next() else { return };
-    let delta = (time.delta_secs_f64() as f32) * game_speed.level.factor();
-    let width = window.width();
-    let height = window.height();
-    let screen_half_width = width / 2.0;
-    let screen_half_height = height / 2.0;
-
-    let Some((mut ball_transform, mut ball_velocity, mut ball_angular_velocity)) = ball_query.iter_mut().next() else { return };
-
-    if game_state.game_over {
-        ball_velocity.0 = Vec2::ZERO;
-        return;
-    }
-
-    if !game_state.is_ball_active {
-        let target_x = if game_state.serving_player == state::ServingPlayer::Player1 {
-            if let Some(p1) = player1_query.iter().next() {
-                p1.0.translation.x
-            } else { 0.0 }
-        } else {
-            if let Some(p2) = player2_query.iter().next() {
-                p2.0.translation.x
-            } else { 0.0 }
-        };
-        ball_transform.translation.x = target_x;
-        ball_transform.translation.y = -screen_half_height + height * config::GROUND_OFFSET_RATIO + 150.0;
-        ball_transform.rotation = Quat::IDENTITY;
-        ball_velocity.0 = Vec2::ZERO;
-        ball_angular_velocity.0 = 0.0;
-
-        let ball_pos = ball_transform.translation.xy();
-        let ball_radius = (height * config::BALL_SIZE_RATIO) / 2.0;
-        let player_height = height * config::PLAYER_HEIGHT_RATIO;
-        let player_width = player_height * config::PLAYER_ASPECT_RATIO;
-
-        let players = if game_state.serving_player == state::ServingPlayer::Player1 {
-            player1_query.iter().collect::<Vec<_>>()
-        } else {
-            player2_query.iter().collect::<Vec<_>>()
-        };
-
-        let net_height = height * config::NET_HEIGHT_RATIO;
-        let target_jump_height = net_height * config::JUMP_HEIGHT_RATIO_OF_POLE;
-        let serve_velocity = (2.0 * config::GRAVITY * target_jump_height).sqrt();
-
-        for (p_transform, p_velocity) in players {
-            let p_pos = p_transform.translation.xy();
-            
-            let serve_pressed = if game_state.serving_player == state::ServingPlayer::Player1 {
-                keyboard_input.pressed(KeyCode::KeyW) || keyboard_input.pressed(KeyCode::Space) || keyboard_input.pressed(KeyCode::KeyD) || keyboard_input.pressed(KeyCode::KeyA)
-            } else {
-                keyboard_input.pressed(KeyCode::ArrowUp) || keyboard_input.pressed(KeyCode::Space) || keyboard_input.pressed(KeyCode::ArrowLeft) || keyboard_input.pressed(KeyCode::ArrowRight)
-            };
+        ));
 
-            let is_moving_up = p_velocity.0.y > 10.0;
-
-            if (serve_pressed || is_moving_up) && ball_pos.x + ball_radius > p_pos.x - player_width/2.0 &&
-               ball_pos.x - ball_radius < p_pos.x + player_width/2.0 &&
-               ball_pos.y + ball_radius > p_pos.y - player_height/2.0 &&
-               ball_pos.y - ball_radius < p_pos.y + player_height/2.0 
-            {
-                game_state.is_ball_active = true;
-                ball_velocity.0.y = serve_velocity; 
-                ball_velocity.0.x = (ball_pos.x - p_pos.x) * 15.0;
-                
-                let hit_dir = (ball_pos.x - p_pos.x) / (player_width / 2.0);
-                ball_angular_velocity.0 = (-hit_dir * 30.0).clamp(-config::BALL_MAX_SPIN, config::BALL_MAX_SPIN);
+        parent.spawn(Node {
+            flex_direction: FlexDirection::Row,
+            justify_content: JustifyContent::Center,
+            align_items: AlignItems::Center,
+            margin: UiRect::bottom(Val::Px(10.0)),
+            ..default()
+        }).with_children(|row| {
+            let difficulties = [
+                cpu::Difficulty::Easy,
+                cpu::Difficulty::Normal,
+                cpu::Difficulty::Hard,
+            ];
+            for difficulty in difficulties {
+                row.spawn((
+                    Button,
+                    Node {
+                        width: Val::Px(120.0),
+                        height: Val::Px(60.0),
+                        margin: UiRect::all(Val::Px(10.0)),
+                        justify_content: JustifyContent::Center,
+                        align_items: AlignItems::Center,
+                        ..default()
+                    },
+                    BackgroundColor(Color::srgb(0.2, 0.2, 0.2)),
+                    DifficultyButton(difficulty),
+                )).with_children(|btn| {
+                    btn.spawn((
+                        Text::new(difficulty.label()),
+                        TextFont {
+                            font_size: 24.0,
+                            ..default()
+                        },
+                        TextColor(Color::WHITE),
+                    ));
+                });
             }
-        }
-    } else {
-        ball_velocity.0.y -= config::BALL_GRAVITY * delta;
-        ball_velocity.0.x *= config::BALL_HORIZONTAL_FRICTION;
-        
-        ball_transform.translation.x += ball_velocity.0.x * delta;
-        ball_transform.translation.y += ball_velocity.0.y * delta;
-        
-        ball_transform.rotate_z(ball_angular_velocity.0 * delta); // angular velocity
-        ball_transform.rotate_z(-ball_velocity.0.x * config::BALL_ROTATION_FACTOR * delta);
-        ball_angular_velocity.0 *= 0.98; // friction
-
-        let ball_radius = (height * config::BALL_SIZE_RATIO) / 2.0;
-
-        if ball_transform.translation.x - ball_radius < -screen_half_width {
-            ball_transform.translation.x = -screen_half_width + ball_radius;
-            ball_velocity.0.x *= -config::BALL_BOUNCE;
-        }
-        if ball_transform.translation.x + ball_radius > screen_half_width {
-            ball_transform.translation.x = screen_half_width - ball_radius;
-            ball_velocity.0.x *= -config::BALL_BOUNCE;
-        }
-        // Removed roof boundary: ball can go off screen at the top
-        /*
-        if ball_transform.translation.y + ball_radius > screen_half_height {
-            ball_transform.translation.y = screen_half_height - ball_radius;
-            ball_velocity.0.y *= -config::BALL_BOUNCE;
-        }
-        */
-
-        let net_half_thickness = config::NET_COLLISION_WIDTH;
-        let net_height = height * config::NET_HEIGHT_RATIO;
-        let net_top_y = -screen_half_height + net_height;
-        
-        if ball_transform.translation.y < net_top_y + ball_radius {
-            if ball_transform.translation.x.abs() < net_half_thickness + ball_radius {
-                if ball_transform.translation.y > net_top_y - 10.0 {
-                    let pole_top_center = Vec2::new(0.0, net_top_y);
-                    let b_pos = ball_transform.translation.xy();
-                    let dist_vec = b_pos - pole_top_center;
-                    let normal = dist_vec.normalize_or_zero();
-                    
-                    let speed = ball_velocity.0.length().max(300.0);
-                    ball_velocity.0 = normal * (speed + 50.0);
-                    
-                    let overlap = ball_radius - dist_vec.length();
-                    if overlap > 0.0 {
-                        ball_transform.translation.x += normal.x * overlap;
-                        ball_transform.translation.y += normal.y * overlap;
-                    }
-                } else {
-                    ball_velocity.0.x *= -config::BALL_BOUNCE;
-                    ball_transform.translation.x = ball_transform.translation.x.signum() * (net_half_thickness + ball_radius + 1.0);
-                }
-            }
-        }
+        });
 
-        let player_height = height * config::PLAYER_HEIGHT_RATIO;
-        let player_width = player_height * config::PLAYER_ASPECT_RATIO;
-        let p_radius = (player_width + player_height) / 4.0;
-
-        for (p_transform, _) in player1_query.iter().chain(player2_query.iter()) {
-            let p_pos = p_transform.translation.xy();
-            let b_pos = ball_transform.translation.xy();
-            let dist_vec = b_pos - p_pos;
-            let distance = dist_vec.length();
-
-            if distance < p_radius + ball_radius {
-                let normal = dist_vec.normalize_or_zero();
-                let speed = ball_velocity.0.length().max(900.0);
-                ball_velocity.0 = normal * (speed + 600.0);
-                ball_velocity.0 = ball_velocity.0.clamp_length_max(config::BALL_MAX_SPEED);
-                
-                // spin on hit
-                let hit_dir = (b_pos.x - p_pos.x) / (player_width / 2.0);
-                ball_angular_velocity.0 = (-hit_dir * 50.0).clamp(-config::BALL_MAX_SPIN, config::BALL_MAX_SPIN);
-                
-                let overlap = (p_radius + ball_radius) - distance;
-                ball_transform.translation.x += normal.x * overlap;
-                ball_transform.translation.y += normal.y * overlap;
+        parent.spawn(Node {
+            flex_direction: FlexDirection::Row,
+            justify_content: JustifyContent::Center,
+            align_items: AlignItems::Center,
+            ..default()
+        }).with_children(|row| {
+            for (label, vs_cpu, team_size) in [("1 PLAYER", true, 1), ("2 PLAYERS", false, 1), ("2 VS 2", false, 2)] {
+                row.spawn((
+                    Button,
+                    Node {
+                        width: Val::Px(240.0),
+                        height: Val::Px(80.0),
+                        margin: UiRect::all(Val::Px(10.0)),
+                        justify_content: JustifyContent::Center,
+                        align_items: AlignItems::Center,
+                        ..default()
+                    },
+                    BackgroundColor(Color::srgb(0.2, 0.2, 0.2)),
+                    StartButton { vs_cpu, team_size, keep_up: false },
+                )).with_children(|btn| {
+                    btn.spawn((
+                        Text::new(label),
+                        TextFont {
+                            font_size: 40.0,
+                            ..default()
+                        },
+                        TextColor(Color::WHITE),
+                    ));
+                });
             }
+
+            row.spawn((
+                Button,
+                Node {
+                    width: Val::Px(240.0),
+                    height: Val::Px(80.0),
+                    margin: UiRect::all(Val::Px(10.0)),
+                    justify_content: JustifyContent::Center,
+                    align_items: AlignItems::Center,
+                    ..default()
+                },
+                BackgroundColor(Color::srgb(0.2, 0.2, 0.2)),
+                OnlineMenuButton,
+            )).with_children(|btn| {
+                btn.spawn((
+                    Text::new("ONLINE"),
+                    TextFont {
+                        font_size: 40.0,
+                        ..default()
+                    },
+                    TextColor(Color::WHITE),
+                ));
+            });
+        });
+
+        parent.spawn(Node {
+            flex_direction: FlexDirection::Row,
+            justify_content: JustifyContent::Center,
+            align_items: AlignItems::Center,
+            margin: UiRect::top(Val::Px(20.0)),
+            ..default()
+        }).with_children(|row| {
+            row.spawn((
+                Button,
+                Node {
+                    width: Val::Px(200.0),
+                    height: Val::Px(50.0),
+                    margin: UiRect::horizontal(Val::Px(10.0)),
+                    justify_content: JustifyContent::Center,
+                    align_items: AlignItems::Center,
+                    ..default()
+                },
+                BackgroundColor(Color::srgb(0.2, 0.2, 0.2)),
+                SettingsButton,
+            )).with_children(|btn| {
+                btn.spawn((
+                    Text::new("SETTINGS"),
+                    TextFont {
+                        font_size: 24.0,
+                        ..default()
+                    },
+                    TextColor(Color::WHITE),
+                ));
+            });
+
+            row.spawn((
+                Button,
+                Node {
+                    width: Val::Px(200.0),
+                    height: Val::Px(50.0),
+                    margin: UiRect::horizontal(Val::Px(10.0)),
+                    justify_content: JustifyContent::Center,
+                    align_items: AlignItems::Center,
+                    ..default()
+                },
+                BackgroundColor(Color::srgb(0.2, 0.2, 0.2)),
+                WatchReplayButton,
+            )).with_children(|btn| {
+                btn.spawn((
+                    Text::new("LAST REPLAY"),
+                    TextFont {
+                        font_size: 24.0,
+                        ..default()
+                    },
+                    TextColor(Color::WHITE),
+                ));
+            });
+
+            row.spawn((
+                Button,
+                Node {
+                    width: Val::Px(200.0),
+                    height: Val::Px(50.0),
+                    margin: UiRect::horizontal(Val::Px(10.0)),
+                    justify_content: JustifyContent::Center,
+                    align_items: AlignItems::Center,
+                    ..default()
+                },
+                BackgroundColor(Color::srgb(0.2, 0.2, 0.2)),
+                StartButton { vs_cpu: false, team_size: 1, keep_up: true },
+            )).with_children(|btn| {
+                btn.spawn((
+                    Text::new("KEEP UP"),
+                    TextFont {
+                        font_size: 24.0,
+                        ..default()
+                    },
+                    TextColor(Color::WHITE),
+                ));
+            });
+
+            row.spawn((
+                Button,
+                Node {
+                    width: Val::Px(150.0),
+                    height: Val::Px(50.0),
+                    margin: UiRect::horizontal(Val::Px(10.0)),
+                    justify_content: JustifyContent::Center,
+                    align_items: AlignItems::Center,
+                    ..default()
+                },
+                BackgroundColor(Color::srgb(0.2, 0.2, 0.2)),
+                RuleToggle::KeepUpOverNet,
+            )).with_children(|btn| {
+                btn.spawn((
+                    Text::new("OVER NET"),
+                    TextFont {
+                        font_size: 24.0,
+                        ..default()
+                    },
+                    TextColor(Color::WHITE),
+                ));
+            });
+
+            row.spawn((
+                Button,
+                Node {
+                    width: Val::Px(200.0),
+                    height: Val::Px(50.0),
+                    margin: UiRect::horizontal(Val::Px(10.0)),
+                    justify_content: JustifyContent::Center,
+                    align_items: AlignItems::Center,
+                    ..default()
+                },
+                BackgroundColor(Color::srgb(0.2, 0.2, 0.2)),
+                TrainingMenuButton,
+            )).with_children(|btn| {
+                btn.spawn((
+                    Text::new("TRAINING"),
+                    TextFont {
+                        font_size: 24.0,
+                        ..default()
+                    },
+                    TextColor(Color::WHITE),
+                ));
+            });
+        });
+
+        parent.spawn(Node {
+            flex_direction: FlexDirection::Row,
+            justify_content: JustifyContent::Center,
+            align_items: AlignItems::Center,
+            margin: UiRect::top(Val::Px(10.0)),
+            ..default()
+        }).with_children(|row| {
+            row.spawn((
+                Button,
+                Node {
+                    width: Val::Px(200.0),
+                    height: Val::Px(50.0),
+                    margin: UiRect::horizontal(Val::Px(10.0)),
+                    justify_content: JustifyContent::Center,
+                    align_items: AlignItems::Center,
+                    ..default()
+                },
+                BackgroundColor(Color::srgb(0.2, 0.2, 0.2)),
+                ProfilesMenuButton,
+            )).with_children(|btn| {
+                btn.spawn((
+                    Text::new("PROFILES"),
+                    TextFont {
+                        font_size: 24.0,
+                        ..default()
+                    },
+                    TextColor(Color::WHITE),
+                ));
+            });
+
+            row.spawn((
+                Button,
+                Node {
+                    width: Val::Px(200.0),
+                    height: Val::Px(50.0),
+                    margin: UiRect::horizontal(Val::Px(10.0)),
+                    justify_content: JustifyContent::Center,
+                    align_items: AlignItems::Center,
+                    ..default()
+                },
+                BackgroundColor(Color::srgb(0.2, 0.2, 0.2)),
+                DinosMenuButton,
+            )).with_children(|btn| {
+                btn.spawn((
+                    Text::new("DINOS"),
+                    TextFont {
+                        font_size: 24.0,
+                        ..default()
+                    },
+                    TextColor(Color::WHITE),
+                ));
+            });
+
+            row.spawn((
+                Button,
+                Node {
+                    width: Val::Px(200.0),
+                    height: Val::Px(50.0),
+                    margin: UiRect::horizontal(Val::Px(10.0)),
+                    justify_content: JustifyContent::Center,
+                    align_items: AlignItems::Center,
+                    ..default()
+                },
+                BackgroundColor(Color::srgb(0.2, 0.2, 0.2)),
+                RecordsMenuButton,
+            )).with_children(|btn| {
+                btn.spawn((
+                    Text::new("RECORDS"),
+                    TextFont {
+                        font_size: 24.0,
+                        ..default()
+                    },
+                    TextColor(Color::WHITE),
+                ));
+            });
+        });
+    });
+}
+
+fn cpu_input_system(
+    time: Res<Time>,
+    game: Res<state::Match>,
+    mut inputs: ResMut<state::PlayerInputs>,
+    mut cpu_players: ResMut<state::CpuPlayers>,
+) {
+    let dt = time.delta_secs();
+    for (slot, controller) in cpu_players.controllers.iter_mut() {
+        let input = controller.update(&game, *slot, dt);
+        if inputs.0.len() <= *slot {
+            inputs.0.resize(*slot + 1, sim::PlayerInput::default());
         }
+        inputs.0[*slot] = input;
     }
 }
 
-fn score_and_reset_system(
-    mut ball_query: Query<(&mut Transform, &mut Velocity), With<Ball>>,
-    mut game_state: ResMut<state::GameState>,
-    mut score1_query: Query<&mut Text2d, (With<ScoreText1>, Without<ScoreText2>)>,
-    mut score2_query: Query<&mut Text2d, (With<ScoreText2>, Without<ScoreText1>)>,
+/// Local matches play on a court the size of the window.
+fn sync_arena_to_window(
+    mut game: ResMut<state::Match>,
     windows: Query<&Window, With<bevy::window::PrimaryWindow>>,
 ) {
     let Some(window) = windows.iter().next() else { return };
-    let height = window.height();
-    let ground_y = -height / 2.0 + height * config::GROUND_OFFSET_RATIO;
-    let Some((ball_transform, mut ball_velocity)) = ball_query.iter_mut().next() else { return };
-
-    if game_state.is_ball_active && ball_transform.translation.y < ground_y {
-        if ball_transform.translation.x < 0.0 {
-            game_state.player2_score += 1;
-            game_state.serving_player = state::ServingPlayer::Player2;
-            if game_state.player2_score >= config::WINNING_SCORE {
-                game_state.game_over = true;
-                game_state.winner = Some(2);
+    let arena = sim::Arena {
+        width: window.width(),
+        height: window.height(),
+    };
+    if game.arena != arena {
+        game.arena = arena;
+    }
+}
+
+fn simulate_match(
+    time: Res<Time>,
+    inputs: Res<state::PlayerInputs>,
+    mut game: ResMut<state::Match>,
+    mut game_events: MessageWriter<state::GameEvent>,
+) {
+    game.step(&inputs.0, time.delta_secs());
+    game_events.write_batch(game.events.iter().copied().map(state::GameEvent));
+}
+
+fn sync_transforms(
+    game: Res<state::Match>,
+    fixed_time: Res<Time<Fixed>>,
+    mut player_query: Query<(&PlayerSlot, &Team, &mut Transform, &mut Sprite, &mut Visibility), Without<Ball>>,
+    mut ball_query: Query<&mut Transform, With<Ball>>,
+) {
+    // Blend between the last two simulation steps so motion stays smooth at any frame rate.
+    let alpha = fixed_time.overstep_fraction();
+
+    // Dinos without a player on their side of this match (the doubles partners in 1v1) stay hidden.
+    for (slot, team, mut transform, mut sprite, mut visibility) in player_query.iter_mut() {
+        let Some(player) = game.players.get(slot.0).filter(|player| player.side == team.0) else {
+            visibility.set_if_neq(Visibility::Hidden);
+            continue;
+        };
+        visibility.set_if_neq(Visibility::Inherited);
+        let pos = player.prev_pos.lerp(player.pos, alpha);
+        transform.translation.x = pos.x;
+        transform.translation.y = pos.y;
+        sprite.flip_x = player.facing_left;
+    }
+
+    let ball = &game.ball;
+    let ball_pos = ball.prev_pos.lerp(ball.pos, alpha);
+    let ball_rotation = ball.prev_rotation + (ball.rotation - ball.prev_rotation) * alpha;
+    for mut transform in ball_query.iter_mut() {
+        transform.translation.x = ball_pos.x;
+        transform.translation.y = ball_pos.y;
+        transform.rotation = Quat::from_rotation_z(ball_rotation);
+    }
+}
+
+#[allow(clippy::type_complexity)]
+fn score_text_system(
+    game: Res<state::Match>,
+    bests: Res<practice::PersonalBests>,
+    mut score1_query: Query<&mut Text2d, (With<ScoreText1>, Without<ScoreText2>)>,
+    mut score2_query: Query<&mut Text2d, (With<ScoreText2>, Without<ScoreText1>)>,
+    mut status_query: Query<&mut Text2d, (With<StatusText>, Without<ScoreText1>, Without<ScoreText2>)>,
+    mut touch_query: Query<(&mut Text2d, &TouchText), (Without<StatusText>, Without<ScoreText1>, Without<ScoreText2>)>,
+    mut set_query: Query<(&mut Text2d, &SetText), (Without<TouchText>, Without<StatusText>, Without<ScoreText1>, Without<ScoreText2>)>,
+) {
+    if let sim::GameMode::KeepUp { over_net } = game.rules.mode {
+        // Live hit counter on the left, personal best in the middle.
+        let best = bests.keep_up(over_net);
+        if let Some(mut text) = score1_query.iter_mut().next() {
+            text.0 = format!("{:02}", game.keep_up.hits);
+        }
+        if let Some(mut text) = score2_query.iter_mut().next() {
+            text.0.clear();
+        }
+        if let Some(mut text) = status_query.iter_mut().next() {
+            let status = if best > 0 && game.keep_up.hits > best {
+                "NEW BEST!".to_string()
+            } else {
+                format!("BEST {}", best)
+            };
+            if text.0 != status {
+                text.0 = status;
             }
-        } else {
-            game_state.player1_score += 1;
-            game_state.serving_player = state::ServingPlayer::Player1;
-            if game_state.player1_score >= config::WINNING_SCORE {
-                game_state.game_over = true;
-                game_state.winner = Some(1);
+        }
+        return;
+    }
+    if let (sim::GameMode::Training(drill), Some(launcher)) = (game.rules.mode, &game.launcher) {
+        // Returned balls on the left, this session's tally in the middle.
+        if let Some(mut text) = score1_query.iter_mut().next() {
+            text.0 = format!("{:02}", launcher.returned);
+        }
+        if let Some(mut text) = score2_query.iter_mut().next() {
+            text.0.clear();
+        }
+        if let Some(mut text) = status_query.iter_mut().next() {
+            let mut status = format!("{}  {}/{} RETURNED", training_menu::drill_label(drill.kind), launcher.returned, launcher.returned + launcher.missed);
+            if drill.target != sim::TargetZone::Anywhere {
+                status.push_str(&format!(", {} ON TARGET", launcher.on_target));
+            }
+            if text.0 != status {
+                text.0 = status;
             }
         }
-        game_state.is_ball_active = false;
-        ball_velocity.0 = Vec2::ZERO;
+        return;
     }
-
     if let Some(mut text) = score1_query.iter_mut().next() {
-        text.0 = format!("{:02}", game_state.player1_score);
+        text.0 = format!("{:02}", game.state.player1_score);
     }
     if let Some(mut text) = score2_query.iter_mut().next() {
-        text.0 = format!("{:02}", game_state.player2_score);
+        text.0 = format!("{:02}", game.state.player2_score);
+    }
+    if let Some(mut text) = status_query.iter_mut().next() {
+        let status = match game.match_status() {
+            sim::MatchStatus::MatchPoint(_) => "MATCH POINT",
+            sim::MatchStatus::SetPoint(_) => "SET POINT",
+            sim::MatchStatus::Deuce => "DEUCE",
+            sim::MatchStatus::Playing | sim::MatchStatus::Finished => "",
+        };
+        if text.0 != status {
+            text.0 = status.to_string();
+        }
+    }
+    for (mut text, touch) in touch_query.iter_mut() {
+        let touches = &game.touches;
+        let label = if game.rules.touch_rules && game.state.is_ball_active && touches.side == Some(touch.0) {
+            format!("TOUCHES {}/{}", touches.count, config::MAX_TOUCHES)
+        } else {
+            String::new()
+        };
+        if text.0 != label {
+            text.0 = label;
+        }
+    }
+    for (mut text, set) in set_query.iter_mut() {
+        let label = if game.rules.sets > 1 {
+            format!("({})", game.sets_won(set.0))
+        } else {
+            String::new()
+        };
+        if text.0 != label {
+            text.0 = label;
+        }
+    }
+}
+
+/// Who's playing each side, above their score.
+fn name_text_system(
+    players: Res<profiles::Players>,
+    mut name_query: Query<(&mut Text2d, &NameText)>,
+) {
+    for (mut text, name) in name_query.iter_mut() {
+        let label = players.side_name(name.0);
+        if text.0 != label {
+            text.0 = label;
+        }
     }
 }
 
 fn win_system(
     mut commands: Commands,
-    game_state: Res<state::GameState>,
+    game: Res<state::Match>,
+    players: Res<profiles::Players>,
     win_ui: Query<Entity, With<WinUI>>,
 ) {
-    if game_state.game_over && win_ui.iter().next().is_none() {
-        let winner_text = format!("Player {} won!", game_state.winner.unwrap_or(1));
-        
+    if game.state.game_over && win_ui.iter().next().is_none() {
+        let side = if game.state.winner == Some(2) { sim::Side::Right } else { sim::Side::Left };
+        let winner = players.side_name(side);
+        let verb = if winner.contains(" & ") { "WIN" } else { "WINS" };
+        let winner_text = format!("{winner} {verb}!");
+        // Two long names don't fit at full size.
+        let font_size = if winner_text.len() > 16 { 56.0 } else { 80.0 };
+
         commands.spawn((
             Node {
                 width: Val::Percent(100.0),
@@ -692,12 +1434,34 @@ fn win_system(
             parent.spawn((
                 Text::new(winner_text),
                 TextFont {
-                    font_size: 80.0,
+                    font_size,
                     ..default()
                 },
                 TextColor(Color::WHITE),
             ));
 
+            if game.state.set_scores.len() > 1 {
+                let breakdown = game.state.set_scores.iter()
+                    .map(|(p1, p2)| format!("{p1}-{p2}"))
+                    .collect::<Vec<_>>()
+                    .join("   ");
+                parent.spawn((
+                    Text::new(format!("SETS {}-{}\n{}", game.state.player1_sets, game.state.player2_sets, breakdown)),
+                    TextFont {
+                        font_size: 36.0,
+                        ..default()
+                    },
+                    TextColor(Color::WHITE),
+                    TextLayout::new_with_justify(Justify::Center),
+                    Node {
+                        margin: UiRect::top(Val::Px(20.0)),
+                        ..default()
+                    },
+                ));
+            }
+
+            spawn_match_summary(parent, &game, &players);
+
             parent.spawn((
                 Button,
                 Node {
@@ -724,111 +1488,103 @@ fn win_system(
     }
 }
 
-fn player_movement(
-    keyboard_input: Res<ButtonInput<KeyCode>>,
-    time: Res<Time>,
-    mut player_query: Query<(&mut Transform, &mut Velocity, &mut PlayerState, &mut FacingLeft, &mut Sprite, Option<&Player1>, Option<&Player2>)>,
-    windows: Query<&Window, With<bevy::window::PrimaryWindow>>,
-    game_state: Res<state::GameState>,
-) {
-    let delta = time.delta_secs_f64() as f32;
-    let Some(window) = windows.iter().next() else { return };
-
-    if game_state.game_over { return; }
-
-    let width = window.width();
-    let height = window.height();
-    let screen_half_width = width / 2.0;
-    let ground_y = -height / 2.0 + height * config::GROUND_OFFSET_RATIO;
-    
-    let current_player_speed = width * config::SPEED_RATIO; 
-    let player_height = height * config::PLAYER_HEIGHT_RATIO;
-    let player_width = player_height * config::PLAYER_ASPECT_RATIO;
-
-    let net_height = height * config::NET_HEIGHT_RATIO;
-    let target_jump_height = net_height * config::JUMP_HEIGHT_RATIO_OF_POLE;
-    let jump_velocity = (2.0 * config::GRAVITY * target_jump_height).sqrt();
-
-    for (mut transform, mut velocity, mut state, mut facing, mut sprite, p1, p2) in player_query.iter_mut() {
-        let mut horizontal_direction = 0.0;
-
-        if p1.is_some() {
-            if keyboard_input.pressed(KeyCode::KeyA) {
-                horizontal_direction -= 1.0;
-            }
-            if keyboard_input.pressed(KeyCode::KeyD) {
-                horizontal_direction += 1.0;
-            }
-            if keyboard_input.just_pressed(KeyCode::KeyW) && transform.translation.y <= ground_y + 1.0 {
-                velocity.0.y = jump_velocity;
-            }
-        } else if p2.is_some() {
-            if keyboard_input.pressed(KeyCode::ArrowLeft) {
-                horizontal_direction -= 1.0;
-            }
-            if keyboard_input.pressed(KeyCode::ArrowRight) {
-                horizontal_direction += 1.0;
-            }
-            if keyboard_input.just_pressed(KeyCode::ArrowUp) && transform.translation.y <= ground_y + 1.0 {
-                velocity.0.y = jump_velocity;
-            }
-        }
-
-        transform.translation.x += horizontal_direction * current_player_speed * delta;
-
-        if horizontal_direction < 0.0 {
-            facing.0 = true;
-        } else if horizontal_direction > 0.0 {
-            facing.0 = false;
-        }
-        sprite.flip_x = facing.0;
-
-        velocity.0.y -= config::GRAVITY * delta;
-        transform.translation.y += velocity.0.y * delta;
-
-        if transform.translation.y < ground_y {
-            transform.translation.y = ground_y;
-            velocity.0.y = 0.0;
-        }
-
-        if transform.translation.y > ground_y + 1.0 {
-            *state = PlayerState::Jumping;
-        } else if horizontal_direction.abs() > 0.1 {
-            *state = PlayerState::Running;
-        } else {
-            *state = PlayerState::Idle;
-        }
-
-        let half_player_width = player_width / 2.0;
-        let net_half_thickness = config::NET_COLLISION_WIDTH;
+/// Stats panel under the winner: per-side numbers in two columns, then the whole match.
+fn spawn_match_summary(parent: &mut ChildSpawnerCommands, game: &sim::MatchSim, players: &profiles::Players) {
+    let stats = &game.stats;
+    // Teammates' hits are listed separately, in player order.
+    let hits = |side: sim::Side| {
+        game.players.iter()
+            .zip(&stats.hits)
+            .filter(|(player, _)| player.side == side)
+            .map(|(_, hits)| hits.to_string())
+            .collect::<Vec<_>>()
+            .join(" + ")
+    };
+    let per_side = [
+        (players.side_name(sim::Side::Left), String::new(), players.side_name(sim::Side::Right)),
+        (hits(sim::Side::Left), "HITS".to_string(), hits(sim::Side::Right)),
+        (stats.aces[0].to_string(), "ACES".to_string(), stats.aces[1].to_string()),
+        (stats.serve_points[0].to_string(), "POINTS ON OWN SERVE".to_string(), stats.serve_points[1].to_string()),
+    ];
+    let whole_match = [
+        ("LONGEST RALLY", stats.longest_rally.to_string()),
+        ("AVERAGE RALLY", format!("{:.1}", stats.average_rally())),
+        ("POLE HITS", stats.pole_hits.to_string()),
+        ("WALL BOUNCES", stats.wall_bounces.to_string()),
+    ];
+
+    let cell = |text: String, width: f32, color: Color| {
+        (
+            Text::new(text),
+            TextFont {
+                font_size: 24.0,
+                ..default()
+            },
+            TextColor(color),
+            TextLayout::new_with_justify(Justify::Center),
+            Node {
+                width: Val::Px(width),
+                ..default()
+            },
+        )
+    };
+    let label_color = Color::srgb(0.7, 0.7, 0.7);
 
-        if p1.is_some() {
-            transform.translation.x = transform.translation.x.clamp(
-                -screen_half_width + half_player_width,
-                -net_half_thickness - half_player_width
-            );
-        } else {
-            transform.translation.x = transform.translation.x.clamp(
-                net_half_thickness + half_player_width,
-                screen_half_width - half_player_width
-            );
+    parent.spawn((
+        Node {
+            flex_direction: FlexDirection::Column,
+            align_items: AlignItems::Center,
+            padding: UiRect::all(Val::Px(16.0)),
+            margin: UiRect::top(Val::Px(30.0)),
+            ..default()
+        },
+        BackgroundColor(Color::srgba(1.0, 1.0, 1.0, 0.06)),
+    )).with_children(|panel| {
+        for (left, label, right) in per_side {
+            panel.spawn(Node {
+                flex_direction: FlexDirection::Row,
+                margin: UiRect::vertical(Val::Px(2.0)),
+                ..default()
+            }).with_children(|row| {
+                row.spawn(cell(left, 160.0, Color::WHITE));
+                row.spawn(cell(label, 280.0, label_color));
+                row.spawn(cell(right, 160.0, Color::WHITE));
+            });
         }
-    }
+        panel.spawn(Node {
+            flex_direction: FlexDirection::Row,
+            margin: UiRect::top(Val::Px(14.0)),
+            ..default()
+        }).with_children(|row| {
+            for (label, value) in whole_match {
+                row.spawn(Node {
+                    flex_direction: FlexDirection::Column,
+                    align_items: AlignItems::Center,
+                    ..default()
+                }).with_children(|column| {
+                    column.spawn(cell(value, 150.0, Color::WHITE));
+                    column.spawn(cell(label.to_string(), 150.0, label_color));
+                });
+            }
+        });
+    });
 }
 
 fn player_animation(
     time: Res<Time>,
-    mut query: Query<(&PlayerAnimations, &PlayerState, &mut AnimationTimer, &mut Sprite)>,
+    game: Res<state::Match>,
+    mut query: Query<(&PlayerSlot, &PlayerAnimations, &mut AnimationTimer, &mut Sprite)>,
 ) {
-    for (anims, state, mut timer, mut sprite) in query.iter_mut() {
-        match state {
-            PlayerState::Idle => {
+    for (slot, anims, mut timer, mut sprite) in query.iter_mut() {
+        let Some(player) = game.players.get(slot.0) else { continue };
+        match player.state {
+            sim::PlayerState::Idle => {
                 sprite.image = anims.stationary.clone();
             }
-            PlayerState::Jumping => {
+            sim::PlayerState::Jumping => {
                 sprite.image = anims.jump.clone();
             }
-            PlayerState::Running => {
+            sim::PlayerState::Running => {
                 timer.0.tick(time.delta());
                 if timer.0.just_finished() {
                     if sprite.image == anims.run1 {
@@ -0,0 +1,539 @@
+use bevy::prelude::*;
+use std::collections::VecDeque;
+use std::net::{SocketAddr, ToSocketAddrs, UdpSocket};
+use std::time::{Duration, Instant};
+use crate::config;
+use crate::protocol::{Packet, PROTOCOL_VERSION};
+use crate::settings::NetSettings;
+use crate::sim::{Arena, MatchSim, PlayerInput, Rng};
+use crate::state;
+use crate::types::*;
+
+/// Non-blocking UDP socket that can hold back or drop outgoing packets to mimic a bad connection.
+pub struct Link {
+    socket: UdpSocket,
+    latency: Duration,
+    loss: f32,
+    rng: Rng,
+    outgoing: VecDeque<(Instant, SocketAddr, Vec<u8>)>,
+}
+
+impl Link {
+    pub fn bind(port: u16, net: &NetSettings) -> std::io::Result<Self> {
+        let socket = UdpSocket::bind(("0.0.0.0", port))?;
+        socket.set_nonblocking(true)?;
+        let seed = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.subsec_nanos())
+            .unwrap_or(1);
+        Ok(Self {
+            socket,
+            latency: Duration::from_millis(net.sim_latency_ms as u64),
+            loss: net.sim_loss_percent as f32 / 100.0,
+            rng: Rng::new(seed),
+            outgoing: VecDeque::new(),
+        })
+    }
+
+    pub fn send(&mut self, to: SocketAddr, packet: &Packet) {
+        if self.rng.next_f32() < self.loss {
+            return;
+        }
+        self.outgoing.push_back((Instant::now() + self.latency, to, packet.encode()));
+        self.flush();
+    }
+
+    /// Sends straight away, skipping the simulated latency and loss, for packets
+    /// that have to go out before the link is dropped.
+    pub fn send_now(&self, to: SocketAddr, packet: &Packet) {
+        if let Err(e) = self.socket.send_to(&packet.encode(), to) {
+            eprintln!("Failed to send packet to {}: {}", to, e);
+        }
+    }
+
+    /// Sends every held-back packet whose simulated latency has passed.
+    pub fn flush(&mut self) {
+        let now = Instant::now();
+        while let Some((due, to, bytes)) = self.outgoing.front() {
+            if *due > now {
+                break;
+            }
+            if let Err(e) = self.socket.send_to(bytes, to)
+                && e.kind() != std::io::ErrorKind::WouldBlock
+            {
+                eprintln!("Failed to send packet to {}: {}", to, e);
+            }
+            self.outgoing.pop_front();
+        }
+    }
+
+    pub fn recv(&mut self) -> Option<(SocketAddr, Packet)> {
+        let mut buf = [0u8; 1500];
+        loop {
+            match self.socket.recv_from(&mut buf) {
+                Ok((len, from)) => {
+                    if let Some(packet) = Packet::decode(&buf[..len]) {
+                        return Some((from, packet));
+                    }
+                }
+                // Windows reports an unreachable peer on the next receive; treat it like no data.
+                Err(e) if e.kind() == std::io::ErrorKind::ConnectionReset => continue,
+                Err(_) => return None,
+            }
+        }
+    }
+}
+
+/// Input-delay plus rollback bookkeeping for one peer. Both peers simulate every
+/// frame; the remote player's input is predicted until it arrives, and frames
+/// simulated with a wrong guess are re-simulated from a saved snapshot.
+pub struct Rollback {
+    /// Next frame to simulate.
+    pub frame: u32,
+    local_slot: usize,
+    input_delay: u32,
+    /// Frame at the front of `local`, `remote` and `used_remote`; older ones are no longer needed.
+    base: u32,
+    local: VecDeque<PlayerInput>,
+    remote: VecDeque<Option<PlayerInput>>,
+    /// Every remote input before this frame is known.
+    remote_confirmed: u32,
+    /// Remote input each simulated frame actually ran with.
+    used_remote: VecDeque<PlayerInput>,
+    snapshots: VecDeque<(u32, MatchSim)>,
+    rollback_from: Option<u32>,
+    /// How many of our inputs the peer has acknowledged.
+    peer_ack: u32,
+}
+
+impl Rollback {
+    pub fn new(local_slot: usize, input_delay: u32) -> Self {
+        // The first `input_delay` frames run without input on both peers.
+        let delay = input_delay as usize;
+        Self {
+            frame: 0,
+            local_slot,
+            input_delay,
+            base: 0,
+            local: VecDeque::from(vec![PlayerInput::default(); delay]),
+            remote: VecDeque::from(vec![Some(PlayerInput::default()); delay]),
+            remote_confirmed: input_delay,
+            used_remote: VecDeque::new(),
+            snapshots: VecDeque::new(),
+            rollback_from: None,
+            peer_ack: 0,
+        }
+    }
+
+    /// Queues this tick's local input; it takes effect `input_delay` frames from now.
+    /// Ticks spent waiting for the peer don't queue anything, so the delay stays constant.
+    pub fn add_local(&mut self, input: PlayerInput) {
+        if self.local_end() <= self.frame + self.input_delay {
+            self.local.push_back(input);
+        }
+    }
+
+    /// Records the peer's inputs from frame `start` on. An honest peer can't be further ahead
+    /// than our inputs plus its prediction window, so anything past that is dropped unread.
+    pub fn add_remote(&mut self, start: u32, inputs: &[PlayerInput]) {
+        let limit = self.local_end() + self.input_delay + config::NET_MAX_PREDICTION;
+        for (i, input) in inputs.iter().enumerate() {
+            let Some(frame) = start.checked_add(i as u32).filter(|frame| *frame < limit) else { break };
+            // Frames before `base` were confirmed long ago.
+            let Some(index) = frame.checked_sub(self.base).map(|index| index as usize) else { continue };
+            if self.remote.get(index).is_some_and(|known| known.is_some()) {
+                continue;
+            }
+            if self.remote.len() <= index {
+                self.remote.resize(index + 1, None);
+            }
+            self.remote[index] = Some(*input);
+            if self.used_remote.get(index).is_some_and(|used| used != input) {
+                self.rollback_from = Some(self.rollback_from.map_or(frame, |from| from.min(frame)));
+            }
+        }
+        while self.remote.get((self.remote_confirmed - self.base) as usize).is_some_and(|known| known.is_some()) {
+            self.remote_confirmed += 1;
+        }
+    }
+
+    pub fn on_ack(&mut self, ack: u32) {
+        self.peer_ack = self.peer_ack.max(ack).min(self.local_end());
+    }
+
+    /// Frame after the last local input queued.
+    fn local_end(&self) -> u32 {
+        self.base + self.local.len() as u32
+    }
+
+    /// Re-simulates any mispredicted frames, then steps one new frame unless
+    /// we are too far ahead of the peer. Returns whether a new frame ran.
+    pub fn advance(&mut self, sim: &mut MatchSim, dt: f32) -> bool {
+        if let Some(from) = self.rollback_from.take()
+            && from < self.frame
+            && let Some(index) = self.snapshots.iter().position(|(frame, _)| *frame == from)
+        {
+            let target = self.frame;
+            *sim = self.snapshots[index].1.clone();
+            self.snapshots.truncate(index);
+            self.frame = from;
+            while self.frame < target {
+                self.simulate_frame(sim, dt);
+            }
+        }
+
+        let can_advance = self.frame < self.remote_confirmed + config::NET_MAX_PREDICTION
+            && self.frame < self.local_end();
+        if can_advance {
+            self.simulate_frame(sim, dt);
+        }
+        while self.snapshots.front().is_some_and(|(frame, _)| *frame < self.remote_confirmed) {
+            self.snapshots.pop_front();
+        }
+        self.prune();
+        can_advance
+    }
+
+    fn simulate_frame(&mut self, sim: &mut MatchSim, dt: f32) {
+        let index = (self.frame - self.base) as usize;
+        self.snapshots.push_back((self.frame, sim.clone()));
+
+        let predicted = self.remote_confirmed.checked_sub(1)
+            .and_then(|last| self.remote.get((last - self.base) as usize).copied().flatten())
+            .unwrap_or_default();
+        let remote = self.remote.get(index).copied().flatten().unwrap_or(predicted);
+        let mut inputs = [PlayerInput::default(); 2];
+        inputs[self.local_slot] = self.local[index];
+        inputs[1 - self.local_slot] = remote;
+        sim.step(&inputs, dt);
+
+        self.used_remote.truncate(index);
+        self.used_remote.push_back(remote);
+        self.frame += 1;
+    }
+
+    /// Drops inputs no rollback can reach and the peer already has. The last confirmed
+    /// remote input stays, since it is the prediction for the frames after it.
+    fn prune(&mut self) {
+        let oldest_snapshot = self.snapshots.front().map_or(self.frame, |(frame, _)| *frame);
+        let keep_from = self.peer_ack
+            .min(self.remote_confirmed.saturating_sub(1))
+            .min(oldest_snapshot)
+            .min(self.frame);
+        let drop = keep_from.saturating_sub(self.base) as usize;
+        if drop == 0 {
+            return;
+        }
+        self.local.drain(..drop.min(self.local.len()));
+        self.remote.drain(..drop.min(self.remote.len()));
+        self.used_remote.drain(..drop.min(self.used_remote.len()));
+        self.base = keep_from;
+    }
+
+    /// Every local input the peer hasn't acknowledged yet, so lost packets are covered by the next one.
+    pub fn outgoing(&self) -> Packet {
+        let start = (self.peer_ack - self.base) as usize;
+        let end = self.local.len().min(start + config::NET_MAX_INPUTS_PER_PACKET);
+        Packet::Inputs {
+            ack: self.remote_confirmed,
+            start: self.peer_ack,
+            inputs: self.local.range(start..end).copied().collect(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NetRole {
+    Host,
+    Join(SocketAddr),
+}
+
+/// An online match being set up or played.
+#[derive(Resource)]
+pub struct NetSession {
+    link: Link,
+    pub role: NetRole,
+    peer: Option<SocketAddr>,
+    welcome: Option<Packet>,
+    rollback: Option<Rollback>,
+    last_heard: Instant,
+    last_hello: Option<Instant>,
+}
+
+impl NetSession {
+    pub fn host(net: &NetSettings) -> std::io::Result<Self> {
+        Ok(Self::new(Link::bind(net.port, net)?, NetRole::Host))
+    }
+
+    /// Player slot on this machine, once the match has started.
+    pub fn local_slot(&self) -> Option<usize> {
+        self.rollback.as_ref().map(|rollback| rollback.local_slot)
+    }
+
+    pub fn join(net: &NetSettings) -> std::io::Result<Self> {
+        let addr = net.join_address.to_socket_addrs()?
+            .next()
+            .ok_or_else(|| std::io::Error::other("address did not resolve"))?;
+        Ok(Self::new(Link::bind(0, net)?, NetRole::Join(addr)))
+    }
+
+    fn new(link: Link, role: NetRole) -> Self {
+        let peer = match role {
+            NetRole::Host => None,
+            NetRole::Join(addr) => Some(addr),
+        };
+        Self { link, role, peer, welcome: None, rollback: None, last_heard: Instant::now(), last_hello: None }
+    }
+
+    pub fn port(&self) -> Option<u16> {
+        self.link.socket.local_addr().ok().map(|addr| addr.port())
+    }
+}
+
+/// Handshake: the joiner repeats `Hello` until the host answers with `Welcome`,
+/// then both sides start the same match.
+pub fn lobby_system(
+    mut session: ResMut<NetSession>,
+    settings: Res<crate::settings::Settings>,
+    mut game: ResMut<state::Match>,
+    mut cpu_players: ResMut<state::CpuPlayers>,
+    mut status: ResMut<crate::online_menu::OnlineStatus>,
+    mut app_state: ResMut<NextState<state::AppMode>>,
+) {
+    let session = &mut *session;
+    if let NetRole::Join(addr) = session.role
+        && session.last_hello.is_none_or(|sent| sent.elapsed() > Duration::from_millis(500))
+    {
+        session.link.send(addr, &Packet::Hello { version: PROTOCOL_VERSION });
+        session.last_hello = Some(Instant::now());
+    }
+    session.link.flush();
+
+    while let Some((from, packet)) = session.link.recv() {
+        let started = match (session.role, packet) {
+            (NetRole::Host, Packet::Hello { version }) if version != PROTOCOL_VERSION => {
+                status.0 = format!("{} USES AN INCOMPATIBLE VERSION", from);
+                None
+            }
+            (NetRole::Host, Packet::Hello { .. }) => {
+                let welcome = Packet::Welcome {
+                    version: PROTOCOL_VERSION,
+                    speed: settings.speed,
+                    rules: settings.match_rules(),
+                    input_delay: settings.net.input_delay as u8,
+                };
+                session.link.send(from, &welcome);
+                session.peer = Some(from);
+                let started = Some((welcome.clone(), 0));
+                session.welcome = Some(welcome);
+                started
+            }
+            (NetRole::Join(addr), Packet::Welcome { version, .. }) if from == addr && version != PROTOCOL_VERSION => {
+                status.0 = "HOST USES AN INCOMPATIBLE VERSION".to_string();
+                None
+            }
+            (NetRole::Join(addr), welcome @ Packet::Welcome { .. }) if from == addr => Some((welcome, 1)),
+            _ => None,
+        };
+
+        if let Some((Packet::Welcome { speed, rules, input_delay, .. }, local_slot)) = started {
+            // Both peers need the same court, so online matches ignore the window size.
+            game.0 = MatchSim::new(Arena::default(), speed, rules);
+            cpu_players.controllers.clear();
+            session.rollback = Some(Rollback::new(local_slot, input_delay as u32));
+            session.last_heard = Instant::now();
+            app_state.set(state::AppMode::Playing);
+            return;
+        }
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn net_tick(
+    time: Res<Time>,
+    inputs: Res<state::PlayerInputs>,
+    mut session: ResMut<NetSession>,
+    mut game: ResMut<state::Match>,
+    mut game_events: MessageWriter<state::GameEvent>,
+    mut app_state: ResMut<NextState<state::AppMode>>,
+    menu_ui: Query<Entity, With<MenuUI>>,
+    win_ui: Query<Entity, With<WinUI>>,
+    mut commands: Commands,
+) {
+    let session = &mut *session;
+    let Some(peer) = session.peer else { return };
+    let Some(rollback) = session.rollback.as_mut() else { return };
+
+    while let Some((from, packet)) = session.link.recv() {
+        if from != peer {
+            continue;
+        }
+        session.last_heard = Instant::now();
+        match packet {
+            Packet::Inputs { ack, start, inputs } => {
+                rollback.on_ack(ack);
+                rollback.add_remote(start, &inputs);
+            }
+            // Our welcome got lost and the joiner is still knocking.
+            Packet::Hello { .. } => {
+                if let Some(welcome) = &session.welcome {
+                    session.link.send(from, welcome);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    if session.last_heard.elapsed().as_secs_f64() > config::NET_TIMEOUT_SECONDS {
+        eprintln!("Lost connection to {}", peer);
+        for entity in win_ui.iter() {
+            commands.entity(entity).despawn();
+        }
+        app_state.set(state::AppMode::Menu);
+        for entity in menu_ui.iter() {
+            commands.entity(entity).insert(Visibility::Visible);
+        }
+        return;
+    }
+
+    // Local player always uses the first player's bindings and gamepad.
+    rollback.add_local(inputs.0.first().copied().unwrap_or_default());
+    if rollback.advance(&mut game, time.delta_secs()) {
+        game_events.write_batch(game.events.iter().copied().map(state::GameEvent));
+    }
+    let packet = rollback.outgoing();
+    session.link.send(peer, &packet);
+    session.link.flush();
+}
+
+pub fn end_session(mut commands: Commands) {
+    commands.remove_resource::<NetSession>();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DELAY: u32 = 2;
+    const DT: f32 = 1.0 / config::SIMULATION_HZ as f32;
+
+    /// Our buttons on `tick`: serve, then run back and forth with the odd jump.
+    fn local_input(tick: u32) -> PlayerInput {
+        let right = (tick / 40).is_multiple_of(2);
+        PlayerInput { left: !right, right, jump: tick < 30 || tick % 50 < 6, serve: false }
+    }
+
+    /// The peer's buttons, changing often enough that holding the last one keeps guessing wrong.
+    fn remote_input(tick: u32) -> PlayerInput {
+        let phase = (tick / 9) % 3;
+        PlayerInput { left: phase == 0, right: phase == 1, jump: tick % 17 < 4, serve: false }
+    }
+
+    /// What frame `frame` runs with on both peers once the input delay is applied, in slot order.
+    fn frame_inputs(frame: u32) -> [PlayerInput; 2] {
+        match frame.checked_sub(DELAY) {
+            Some(tick) => [local_input(tick), remote_input(tick)],
+            None => [PlayerInput::default(); 2],
+        }
+    }
+
+    /// The match as it should be, stepped with every input known up front.
+    fn straight(frames: u32) -> MatchSim {
+        let mut sim = MatchSim::default();
+        for frame in 0..frames {
+            sim.step(&frame_inputs(frame), DT);
+        }
+        sim
+    }
+
+    /// Plays `ticks` ticks as slot 0, hearing from the peer `latency` ticks late. Every
+    /// `lose`th packet goes missing, which the next one covers by resending from our ack.
+    /// Ends by delivering everything outstanding so the last predictions get corrected.
+    fn play(ticks: u32, latency: u32, lose: u32) -> (Rollback, MatchSim) {
+        let mut rollback = Rollback::new(0, DELAY);
+        let mut sim = MatchSim::default();
+        let deliver = |rollback: &mut Rollback, end: u32| {
+            let start = rollback.remote_confirmed;
+            let inputs: Vec<PlayerInput> = (start..end).map(|frame| frame_inputs(frame)[1]).collect();
+            rollback.add_remote(start, &inputs);
+        };
+        for tick in 0..ticks {
+            rollback.add_local(local_input(tick));
+            if let Some(sent) = tick.checked_sub(latency)
+                && (lose == 0 || !tick.is_multiple_of(lose))
+            {
+                deliver(&mut rollback, sent + DELAY + 1);
+                rollback.on_ack(sent.saturating_sub(latency) + DELAY + 1);
+            }
+            assert!(rollback.advance(&mut sim, DT), "stalled on tick {tick}");
+        }
+        let end = rollback.local_end();
+        deliver(&mut rollback, end);
+        rollback.advance(&mut sim, DT);
+        (rollback, sim)
+    }
+
+    fn assert_same(sim: &MatchSim, expected: &MatchSim) {
+        assert_eq!(sim.ball.pos, expected.ball.pos);
+        assert_eq!(sim.ball.vel, expected.ball.vel);
+        for (player, other) in sim.players.iter().zip(&expected.players) {
+            assert_eq!(player.pos, other.pos);
+        }
+        assert_eq!(sim.state, expected.state);
+        assert_eq!(sim.stats, expected.stats);
+    }
+
+    #[test]
+    fn late_remote_inputs_are_rolled_back_into_the_straight_result() {
+        let (rollback, sim) = play(600, 6, 0);
+        assert_same(&sim, &straight(rollback.frame));
+    }
+
+    #[test]
+    fn lost_and_mispredicted_inputs_are_rolled_back_into_the_straight_result() {
+        let (rollback, sim) = play(600, 9, 3);
+        assert!(sim.stats.rallies > 0 || sim.state.is_ball_active, "the match should get going");
+        assert_same(&sim, &straight(rollback.frame));
+    }
+
+    #[test]
+    fn a_correct_prediction_needs_no_rollback() {
+        let mut rollback = Rollback::new(0, DELAY);
+        let mut sim = MatchSim::default();
+        rollback.add_local(PlayerInput::default());
+        assert!(rollback.advance(&mut sim, DT));
+        rollback.add_remote(0, &[PlayerInput::default(); DELAY as usize + 1]);
+        assert_eq!(rollback.rollback_from, None);
+        rollback.add_remote(DELAY + 1, &[PlayerInput { jump: true, ..PlayerInput::default() }]);
+        assert_eq!(rollback.rollback_from, None, "frames not simulated yet don't need a rollback");
+    }
+
+    #[test]
+    fn inputs_too_far_ahead_are_dropped() {
+        let mut rollback = Rollback::new(0, DELAY);
+        let inputs = [PlayerInput { jump: true, ..PlayerInput::default() }; 4];
+        rollback.add_remote(u32::MAX - 1, &inputs);
+        rollback.add_remote(1 << 30, &inputs);
+        assert_eq!(rollback.remote.len(), DELAY as usize);
+        assert_eq!(rollback.remote_confirmed, DELAY);
+
+        // Inputs just inside the window still count.
+        rollback.add_remote(DELAY, &inputs);
+        assert_eq!(rollback.remote_confirmed, DELAY + 4);
+    }
+
+    #[test]
+    fn confirmed_and_acknowledged_inputs_are_pruned() {
+        let (rollback, _) = play(3000, 6, 4);
+        let bound = (2 * 6 + DELAY + config::NET_MAX_PREDICTION) as usize;
+        assert!(rollback.base > 0);
+        assert!(rollback.local.len() <= bound, "{} local inputs kept", rollback.local.len());
+        assert!(rollback.remote.len() <= bound, "{} remote inputs kept", rollback.remote.len());
+        assert!(rollback.used_remote.len() <= bound, "{} used inputs kept", rollback.used_remote.len());
+
+        let Packet::Inputs { start, inputs, .. } = rollback.outgoing() else { panic!("expected inputs") };
+        assert_eq!(start, rollback.peer_ack);
+        let expected: Vec<PlayerInput> = (start..rollback.local_end()).map(|frame| frame_inputs(frame)[0]).collect();
+        assert_eq!(inputs, expected);
+    }
+}
@@ -0,0 +1,439 @@
+use bevy::input::keyboard::{Key, KeyboardInput};
+use bevy::prelude::*;
+use crate::lan::LanBrowser;
+use crate::netplay::{NetRole, NetSession};
+use crate::profiles::Profiles;
+use crate::profiles_menu::push_name;
+use crate::remote::RemoteSession;
+use crate::settings::Settings;
+use crate::state;
+use crate::types::*;
+
+const LATENCY_STEP: u32 = 20;
+const MAX_LATENCY_MS: u32 = 500;
+const LOSS_STEP: u32 = 5;
+const MAX_LOSS_PERCENT: u32 = 50;
+const MAX_INPUT_DELAY: u32 = 8;
+
+/// Last error or notice shown on the online screen.
+#[derive(Resource, Default)]
+pub struct OnlineStatus(pub String);
+
+/// Text field the keyboard types into.
+#[derive(Resource, Default)]
+pub struct OnlineFocus(pub OnlineField);
+
+pub fn setup_online_menu(mut commands: Commands, mut status: ResMut<OnlineStatus>, mut focus: ResMut<OnlineFocus>) {
+    status.0.clear();
+    focus.0 = OnlineField::default();
+
+    commands.spawn((
+        Node {
+            width: Val::Percent(100.0),
+            height: Val::Percent(100.0),
+            align_items: AlignItems::Center,
+            justify_content: JustifyContent::Center,
+            flex_direction: FlexDirection::Column,
+            ..default()
+        },
+        BackgroundColor(Color::srgba(0.0, 0.0, 0.0, 0.85)),
+        OnlineUI,
+    )).with_children(|parent| {
+        parent.spawn((
+            Text::new("ONLINE"),
+            TextFont {
+                font_size: 48.0,
+                ..default()
+            },
+            TextColor(Color::WHITE),
+            Node {
+                margin: UiRect::bottom(Val::Px(16.0)),
+                ..default()
+            },
+        ));
+
+        let rows = [
+            ("YOUR NAME", None, OnlineValue::Name),
+            ("JOIN ADDRESS", None, OnlineValue::Address),
+            ("INPUT DELAY", Some((OnlineButton::DelayDown, OnlineButton::DelayUp)), OnlineValue::Delay),
+            ("SIM. LATENCY", Some((OnlineButton::LatencyDown, OnlineButton::LatencyUp)), OnlineValue::Latency),
+            ("SIM. LOSS", Some((OnlineButton::LossDown, OnlineButton::LossUp)), OnlineValue::Loss),
+        ];
+        for (label, steppers, value) in rows {
+            parent.spawn(Node {
+                flex_direction: FlexDirection::Row,
+                align_items: AlignItems::Center,
+                ..default()
+            }).with_children(|row| {
+                row.spawn((
+                    Text::new(label),
+                    TextFont {
+                        font_size: 22.0,
+                        ..default()
+                    },
+                    TextColor(Color::WHITE),
+                    Node {
+                        width: Val::Px(180.0),
+                        margin: UiRect::all(Val::Px(4.0)),
+                        ..default()
+                    },
+                ));
+                let Some((down, up)) = steppers else {
+                    // Text fields are buttons, clicking one moves the typing there.
+                    let field = if value == OnlineValue::Name { OnlineField::Name } else { OnlineField::Address };
+                    row.spawn((
+                        Button,
+                        Node {
+                            width: Val::Px(280.0),
+                            height: Val::Px(36.0),
+                            margin: UiRect::all(Val::Px(4.0)),
+                            justify_content: JustifyContent::Center,
+                            align_items: AlignItems::Center,
+                            ..default()
+                        },
+                        BackgroundColor(Color::srgb(0.2, 0.2, 0.2)),
+                        OnlineButton::Focus(field),
+                    )).with_children(|btn| {
+                        btn.spawn((
+                            Text::new(""),
+                            TextFont {
+                                font_size: 22.0,
+                                ..default()
+                            },
+                            TextColor(Color::WHITE),
+                            value,
+                        ));
+                    });
+                    return;
+                };
+                spawn_stepper(row, "-", down);
+                row.spawn((
+                    Text::new(""),
+                    TextFont {
+                        font_size: 22.0,
+                        ..default()
+                    },
+                    TextColor(Color::WHITE),
+                    Node {
+                        width: Val::Px(100.0),
+                        margin: UiRect::all(Val::Px(4.0)),
+                        justify_content: JustifyContent::Center,
+                        ..default()
+                    },
+                    TextLayout::new_with_justify(Justify::Center),
+                    value,
+                ));
+                spawn_stepper(row, "+", up);
+            });
+        }
+
+        parent.spawn((
+            Text::new(""),
+            TextFont {
+                font_size: 20.0,
+                ..default()
+            },
+            TextColor(Color::srgb(1.0, 0.85, 0.2)),
+            Node {
+                margin: UiRect::vertical(Val::Px(20.0)),
+                ..default()
+            },
+            OnlineValue::Status,
+        ));
+
+        parent.spawn((
+            Text::new("GAMES ON THIS NETWORK"),
+            TextFont {
+                font_size: 22.0,
+                ..default()
+            },
+            TextColor(Color::WHITE),
+        ));
+        parent.spawn((
+            Node {
+                flex_direction: FlexDirection::Column,
+                align_items: AlignItems::Center,
+                min_height: Val::Px(60.0),
+                margin: UiRect::bottom(Val::Px(10.0)),
+                ..default()
+            },
+            LanList::default(),
+        ));
+
+        parent.spawn(Node {
+            flex_direction: FlexDirection::Row,
+            ..default()
+        }).with_children(|row| {
+            let buttons = [
+                ("HOST", OnlineButton::Host),
+                ("JOIN", OnlineButton::Join),
+                ("SERVER", OnlineButton::Server),
+                ("WATCH", OnlineButton::Watch),
+                ("BACK", OnlineButton::Back),
+            ];
+            for (label, action) in buttons {
+                row.spawn((
+                    Button,
+                    Node {
+                        width: Val::Px(170.0),
+                        height: Val::Px(60.0),
+                        margin: UiRect::all(Val::Px(10.0)),
+                        justify_content: JustifyContent::Center,
+                        align_items: AlignItems::Center,
+                        ..default()
+                    },
+                    BackgroundColor(Color::srgb(0.2, 0.2, 0.2)),
+                    action,
+                )).with_children(|btn| {
+                    btn.spawn((
+                        Text::new(label),
+                        TextFont {
+                            font_size: 28.0,
+                            ..default()
+                        },
+                        TextColor(Color::WHITE),
+                    ));
+                });
+            }
+        });
+    });
+}
+
+fn spawn_stepper(row: &mut ChildSpawnerCommands, symbol: &str, action: OnlineButton) {
+    row.spawn((
+        Button,
+        Node {
+            width: Val::Px(40.0),
+            height: Val::Px(36.0),
+            margin: UiRect::all(Val::Px(4.0)),
+            justify_content: JustifyContent::Center,
+            align_items: AlignItems::Center,
+            ..default()
+        },
+        BackgroundColor(Color::srgb(0.2, 0.2, 0.2)),
+        action,
+    )).with_children(|btn| {
+        btn.spawn((
+            Text::new(symbol),
+            TextFont {
+                font_size: 22.0,
+                ..default()
+            },
+            TextColor(Color::WHITE),
+        ));
+    });
+}
+
+pub fn cleanup_online_menu(mut commands: Commands, online_ui: Query<Entity, With<OnlineUI>>) {
+    for entity in online_ui.iter() {
+        commands.entity(entity).despawn();
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn online_button_system(
+    interaction_query: Query<(&Interaction, &OnlineButton), Changed<Interaction>>,
+    mut settings: ResMut<Settings>,
+    session: Option<Res<NetSession>>,
+    remote: Option<Res<RemoteSession>>,
+    mut status: ResMut<OnlineStatus>,
+    mut focus: ResMut<OnlineFocus>,
+    mut app_state: ResMut<NextState<state::AppMode>>,
+    menu_ui: Query<Entity, With<MenuUI>>,
+    mut commands: Commands,
+) {
+    for (interaction, action) in interaction_query.iter() {
+        if *interaction != Interaction::Pressed {
+            continue;
+        }
+        let net = &mut settings.net;
+        match action {
+            OnlineButton::Host | OnlineButton::Join | OnlineButton::Server | OnlineButton::Watch | OnlineButton::JoinLan(_)
+                if session.is_some() || remote.is_some() =>
+            {
+                status.0 = "PRESS BACK TO CANCEL FIRST".to_string();
+            }
+            OnlineButton::Server | OnlineButton::Watch => match RemoteSession::connect(net, *action == OnlineButton::Watch) {
+                Ok(new_session) => {
+                    status.0.clear();
+                    commands.insert_resource(new_session);
+                }
+                Err(e) => status.0 = format!("NETWORK ERROR: {}", e).to_uppercase(),
+            },
+            OnlineButton::Host | OnlineButton::Join | OnlineButton::JoinLan(_) => {
+                if let OnlineButton::JoinLan(addr) = action {
+                    net.join_address = addr.to_string();
+                }
+                let result = if *action == OnlineButton::Host { NetSession::host(net) } else { NetSession::join(net) };
+                match result {
+                    Ok(new_session) => {
+                        status.0.clear();
+                        commands.insert_resource(new_session);
+                    }
+                    Err(e) => status.0 = format!("NETWORK ERROR: {}", e).to_uppercase(),
+                }
+            }
+            OnlineButton::Focus(field) => focus.0 = *field,
+            OnlineButton::DelayDown => net.input_delay = net.input_delay.saturating_sub(1),
+            OnlineButton::DelayUp => net.input_delay = (net.input_delay + 1).min(MAX_INPUT_DELAY),
+            OnlineButton::LatencyDown => net.sim_latency_ms = net.sim_latency_ms.saturating_sub(LATENCY_STEP),
+            OnlineButton::LatencyUp => net.sim_latency_ms = (net.sim_latency_ms + LATENCY_STEP).min(MAX_LATENCY_MS),
+            OnlineButton::LossDown => net.sim_loss_percent = net.sim_loss_percent.saturating_sub(LOSS_STEP),
+            OnlineButton::LossUp => net.sim_loss_percent = (net.sim_loss_percent + LOSS_STEP).min(MAX_LOSS_PERCENT),
+            OnlineButton::Back => {
+                commands.remove_resource::<NetSession>();
+                commands.remove_resource::<RemoteSession>();
+                app_state.set(state::AppMode::Menu);
+                for entity in menu_ui.iter() {
+                    commands.entity(entity).insert(Visibility::Visible);
+                }
+            }
+        }
+    }
+}
+
+/// Types into the focused text field while no connection attempt is running.
+/// The name only takes typing while player 1 has no named profile to go by.
+pub fn text_input_system(
+    mut keyboard_events: MessageReader<KeyboardInput>,
+    mut settings: ResMut<Settings>,
+    profiles: Res<Profiles>,
+    focus: Res<OnlineFocus>,
+    session: Option<Res<NetSession>>,
+    remote: Option<Res<RemoteSession>>,
+) {
+    for event in keyboard_events.read() {
+        if !event.state.is_pressed() || session.is_some() || remote.is_some() {
+            continue;
+        }
+        if focus.0 == OnlineField::Name && profiles.slot_name(0).is_some() {
+            continue;
+        }
+        let net = &mut settings.net;
+        match (&event.logical_key, focus.0) {
+            (Key::Backspace, OnlineField::Name) => {
+                net.player_name.pop();
+            }
+            (Key::Backspace, OnlineField::Address) => {
+                net.join_address.pop();
+            }
+            (Key::Space, OnlineField::Name) => push_name(&mut net.player_name, " "),
+            (Key::Character(text), OnlineField::Name) => push_name(&mut net.player_name, text),
+            (Key::Character(text), OnlineField::Address) => {
+                let allowed = text.chars().filter(|c| c.is_ascii_alphanumeric() || matches!(c, '.' | ':' | '-' | '[' | ']'));
+                net.join_address.extend(allowed);
+            }
+            _ => {}
+        }
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn online_display_system(
+    settings: Res<Settings>,
+    profiles: Res<Profiles>,
+    status: Res<OnlineStatus>,
+    focus: Res<OnlineFocus>,
+    session: Option<Res<NetSession>>,
+    remote: Option<Res<RemoteSession>>,
+    mut texts: Query<(&OnlineValue, &mut Text)>,
+    mut buttons: Query<(&Interaction, &mut BackgroundColor), With<OnlineButton>>,
+) {
+    let net = &settings.net;
+    let typing = session.is_none() && remote.is_none();
+    for (value, mut text) in texts.iter_mut() {
+        let label = match value {
+            OnlineValue::Name if let Some(name) = profiles.slot_name(0) => format!("{} (PROFILE)", name),
+            OnlineValue::Name if typing && focus.0 == OnlineField::Name => format!("{}_", net.player_name),
+            OnlineValue::Name => net.player_name.clone(),
+            OnlineValue::Address if typing && focus.0 == OnlineField::Address => format!("{}_", net.join_address),
+            OnlineValue::Address => net.join_address.clone(),
+            OnlineValue::Delay => format!("{} FRAMES", net.input_delay),
+            OnlineValue::Latency => format!("{} MS", net.sim_latency_ms),
+            OnlineValue::Loss => format!("{}%", net.sim_loss_percent),
+            OnlineValue::Status if !status.0.is_empty() => status.0.clone(),
+            OnlineValue::Status if let Some(remote) = remote.as_ref().filter(|remote| remote.spectate) => {
+                format!("ASKING {} TO WATCH...", remote.server)
+            }
+            OnlineValue::Status if let Some(remote) = &remote => format!("CONNECTING TO SERVER {}...", remote.server),
+            OnlineValue::Status => match session.as_ref().map(|session| session.role) {
+                Some(NetRole::Host) => format!(
+                    "WAITING FOR AN OPPONENT ON PORT {}",
+                    session.as_ref().and_then(|session| session.port()).unwrap_or(net.port),
+                ),
+                Some(NetRole::Join(addr)) => format!("CONNECTING TO {}...", addr),
+                None => "HOST A MATCH, JOIN THE ADDRESS ABOVE OR PICK A GAME BELOW".to_string(),
+            },
+        };
+        if text.0 != label {
+            text.0 = label;
+        }
+    }
+
+    for (interaction, mut color) in buttons.iter_mut() {
+        *color = match interaction {
+            Interaction::Pressed => BackgroundColor(Color::srgb(0.5, 0.5, 0.5)),
+            Interaction::Hovered => BackgroundColor(Color::srgb(0.4, 0.4, 0.4)),
+            Interaction::None => BackgroundColor(Color::srgb(0.2, 0.2, 0.2)),
+        };
+    }
+}
+
+/// Keeps one join button per discovered game.
+pub fn lan_list_system(
+    browser: Option<Res<LanBrowser>>,
+    mut list: Query<(Entity, &mut LanList, Option<&Children>)>,
+    mut commands: Commands,
+) {
+    let Some(browser) = browser else { return };
+    let hosts: Vec<_> = browser.hosts.iter().map(|host| (host.addr, host.describe())).collect();
+    for (entity, mut shown, children) in list.iter_mut() {
+        if shown.0 == hosts && children.is_some() {
+            continue;
+        }
+        for child in children.into_iter().flatten() {
+            commands.entity(*child).despawn();
+        }
+        commands.entity(entity).with_children(|parent| {
+            if hosts.is_empty() {
+                parent.spawn((
+                    Text::new("SEARCHING..."),
+                    TextFont {
+                        font_size: 20.0,
+                        ..default()
+                    },
+                    TextColor(Color::srgb(0.6, 0.6, 0.6)),
+                    Node {
+                        margin: UiRect::all(Val::Px(10.0)),
+                        ..default()
+                    },
+                ));
+            }
+            for (addr, label) in &hosts {
+                parent.spawn((
+                    Button,
+                    Node {
+                        width: Val::Px(640.0),
+                        height: Val::Px(44.0),
+                        margin: UiRect::all(Val::Px(4.0)),
+                        justify_content: JustifyContent::Center,
+                        align_items: AlignItems::Center,
+                        ..default()
+                    },
+                    BackgroundColor(Color::srgb(0.2, 0.2, 0.2)),
+                    OnlineButton::JoinLan(*addr),
+                )).with_children(|btn| {
+                    btn.spawn((
+                        Text::new(label.clone()),
+                        TextFont {
+                            font_size: 20.0,
+                            ..default()
+                        },
+                        TextColor(Color::WHITE),
+                    ));
+                });
+            }
+        });
+        shown.0 = hosts.clone();
+    }
+}
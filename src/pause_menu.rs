@@ -0,0 +1,177 @@
+use bevy::prelude::*;
+use bevy::window::WindowFocused;
+use crate::bindings::Action;
+use crate::instant_replay::InstantReplay;
+use crate::netplay::NetSession;
+use crate::remote::RemoteSession;
+use crate::replay::Recorder;
+use crate::settings::Settings;
+use crate::state;
+use crate::types::*;
+
+pub fn toggle_pause_system(
+    keyboard: Res<ButtonInput<KeyCode>>,
+    settings: Res<Settings>,
+    gamepads: Query<&Gamepad>,
+    app_mode: Res<State<state::AppMode>>,
+    mut app_state: ResMut<NextState<state::AppMode>>,
+) {
+    let pressed = settings.controls.any_just_pressed(&keyboard, Action::Pause)
+        || gamepads.iter().any(|gamepad| gamepad.just_pressed(GamepadButton::Start));
+    if !pressed {
+        return;
+    }
+    match app_mode.get() {
+        state::AppMode::Playing => app_state.set(state::AppMode::Paused),
+        state::AppMode::Paused => app_state.set(state::AppMode::Playing),
+        _ => {}
+    }
+}
+
+pub fn pause_on_focus_loss(
+    mut focus_events: MessageReader<WindowFocused>,
+    mut app_state: ResMut<NextState<state::AppMode>>,
+) {
+    if focus_events.read().any(|event| !event.focused) {
+        app_state.set(state::AppMode::Paused);
+    }
+}
+
+pub fn setup_pause_menu(
+    mut commands: Commands,
+    mut time: ResMut<Time<Virtual>>,
+    mut inputs: ResMut<state::PlayerInputs>,
+    session: Option<Res<NetSession>>,
+    remote: Option<Res<RemoteSession>>,
+) {
+    let online = session.is_some() || remote.is_some();
+    // Online the match can't wait for us: keep it running behind the menu, with our dino standing still.
+    if !online {
+        time.pause();
+    }
+    inputs.0.clear();
+
+    commands.spawn((
+        Node {
+            width: Val::Percent(100.0),
+            height: Val::Percent(100.0),
+            align_items: AlignItems::Center,
+            justify_content: JustifyContent::Center,
+            flex_direction: FlexDirection::Column,
+            ..default()
+        },
+        BackgroundColor(Color::srgba(0.0, 0.0, 0.0, 0.7)),
+        GlobalZIndex(10),
+        PauseUI,
+    )).with_children(|parent| {
+        parent.spawn((
+            Text::new("PAUSED"),
+            TextFont {
+                font_size: 80.0,
+                ..default()
+            },
+            TextColor(Color::WHITE),
+            Node {
+                margin: UiRect::bottom(Val::Px(40.0)),
+                ..default()
+            },
+        ));
+
+        let buttons = [
+            ("RESUME", PauseButton::Resume),
+            ("RESTART MATCH", PauseButton::Restart),
+            ("SETTINGS", PauseButton::Settings),
+            ("QUIT TO MENU", PauseButton::Quit),
+        ];
+        for (label, action) in buttons {
+            // Restarting only one side of an online match would desync it, and the
+            // settings screen would stop it sending inputs.
+            if matches!(action, PauseButton::Restart | PauseButton::Settings) && online {
+                continue;
+            }
+            parent.spawn((
+                Button,
+                Node {
+                    width: Val::Px(320.0),
+                    height: Val::Px(60.0),
+                    margin: UiRect::all(Val::Px(8.0)),
+                    justify_content: JustifyContent::Center,
+                    align_items: AlignItems::Center,
+                    ..default()
+                },
+                BackgroundColor(Color::srgb(0.2, 0.2, 0.2)),
+                action,
+            )).with_children(|btn| {
+                btn.spawn((
+                    Text::new(label),
+                    TextFont {
+                        font_size: 28.0,
+                        ..default()
+                    },
+                    TextColor(Color::WHITE),
+                ));
+            });
+        }
+    });
+}
+
+pub fn cleanup_pause_menu(
+    mut commands: Commands,
+    pause_ui: Query<Entity, With<PauseUI>>,
+    mut time: ResMut<Time<Virtual>>,
+) {
+    for entity in pause_ui.iter() {
+        commands.entity(entity).despawn();
+    }
+    time.unpause();
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn pause_button_system(
+    mut interaction_query: Query<(&Interaction, &mut BackgroundColor, &PauseButton), Changed<Interaction>>,
+    mut game: ResMut<state::Match>,
+    mut settings_origin: ResMut<state::SettingsOrigin>,
+    mut recorder: ResMut<Recorder>,
+    mut instant_replay: ResMut<InstantReplay>,
+    mut app_state: ResMut<NextState<state::AppMode>>,
+    win_ui: Query<Entity, With<WinUI>>,
+    menu_ui: Query<Entity, With<MenuUI>>,
+    mut commands: Commands,
+) {
+    for (interaction, mut color, action) in interaction_query.iter_mut() {
+        match *interaction {
+            Interaction::Pressed => match action {
+                PauseButton::Resume => app_state.set(state::AppMode::Playing),
+                PauseButton::Restart => {
+                    game.reset();
+                    recorder.restart(&game);
+                    *instant_replay = InstantReplay::default();
+                    for entity in win_ui.iter() {
+                        commands.entity(entity).despawn();
+                    }
+                    app_state.set(state::AppMode::Playing);
+                }
+                PauseButton::Settings => {
+                    settings_origin.0 = state::AppMode::Paused;
+                    app_state.set(state::AppMode::Settings);
+                }
+                PauseButton::Quit => {
+                    game.reset();
+                    for entity in win_ui.iter() {
+                        commands.entity(entity).despawn();
+                    }
+                    app_state.set(state::AppMode::Menu);
+                    for entity in menu_ui.iter() {
+                        commands.entity(entity).insert(Visibility::Visible);
+                    }
+                }
+            },
+            Interaction::Hovered => {
+                *color = BackgroundColor(Color::srgb(0.4, 0.4, 0.4));
+            }
+            Interaction::None => {
+                *color = BackgroundColor(Color::srgb(0.2, 0.2, 0.2));
+            }
+        }
+    }
+}
@@ -0,0 +1,160 @@
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use crate::sim::{DrillKind, GameMode, SimEvent, TargetZone};
+use crate::state;
+use crate::types::*;
+use crate::util;
+
+const BESTS_FILE: &str = "practice.toml";
+const DRILLS_FILE: &str = "drills.toml";
+
+/// Longest keep-up attempts so far, kept between sessions.
+#[derive(Resource, Serialize, Deserialize, Default, Debug, Clone, PartialEq)]
+#[serde(default)]
+pub struct PersonalBests {
+    pub keep_up: u32,
+    pub keep_up_over_net: u32,
+}
+
+impl PersonalBests {
+    pub fn load() -> Self {
+        util::load_data(BESTS_FILE)
+    }
+
+    fn save(&self) {
+        util::save_data(BESTS_FILE, self);
+    }
+
+    pub fn keep_up(&self, over_net: bool) -> u32 {
+        if over_net { self.keep_up_over_net } else { self.keep_up }
+    }
+}
+
+/// Raises the personal best when a keep-up attempt beats it.
+pub fn record_keep_up(
+    mut events: MessageReader<state::GameEvent>,
+    game: Res<state::Match>,
+    mut bests: ResMut<PersonalBests>,
+) {
+    let GameMode::KeepUp { over_net } = game.rules.mode else {
+        events.clear();
+        return;
+    };
+    for event in events.read() {
+        if let SimEvent::Dropped { hits } = event.0
+            && hits > bests.keep_up(over_net)
+        {
+            if over_net {
+                bests.keep_up_over_net = hits;
+            } else {
+                bests.keep_up = hits;
+            }
+            bests.save();
+        }
+    }
+}
+
+/// Lifetime totals for one training drill.
+#[derive(Serialize, Deserialize, Default, Debug, Clone, Copy, PartialEq)]
+#[serde(default)]
+pub struct DrillRecord {
+    pub launched: u32,
+    pub returned: u32,
+    pub on_target: u32,
+    pub missed: u32,
+}
+
+impl DrillRecord {
+    /// Share of the balls that came into play that were returned, in percent.
+    pub fn success_rate(&self) -> u32 {
+        let played = self.returned + self.missed;
+        (self.returned * 100).checked_div(played).unwrap_or(0)
+    }
+}
+
+/// Training results per drill, kept between sessions.
+#[derive(Resource, Serialize, Deserialize, Default, Debug, Clone, PartialEq)]
+#[serde(default)]
+pub struct DrillRecords {
+    pub receive: DrillRecord,
+    pub pole: DrillRecord,
+    pub lobs: DrillRecord,
+    pub mixed: DrillRecord,
+}
+
+impl DrillRecords {
+    pub fn load() -> Self {
+        util::load_data(DRILLS_FILE)
+    }
+
+    pub fn get(&self, kind: DrillKind) -> &DrillRecord {
+        match kind {
+            DrillKind::Receive => &self.receive,
+            DrillKind::Pole => &self.pole,
+            DrillKind::Lobs => &self.lobs,
+            DrillKind::Mixed => &self.mixed,
+        }
+    }
+
+    fn get_mut(&mut self, kind: DrillKind) -> &mut DrillRecord {
+        match kind {
+            DrillKind::Receive => &mut self.receive,
+            DrillKind::Pole => &mut self.pole,
+            DrillKind::Lobs => &mut self.lobs,
+            DrillKind::Mixed => &mut self.mixed,
+        }
+    }
+}
+
+/// Adds every launch and its outcome to the drill's lifetime totals.
+pub fn record_drills(
+    mut events: MessageReader<state::GameEvent>,
+    game: Res<state::Match>,
+    mut records: ResMut<DrillRecords>,
+) {
+    let GameMode::Training(drill) = game.rules.mode else {
+        events.clear();
+        return;
+    };
+    let mut changed = false;
+    for event in events.read() {
+        let record = records.get_mut(drill.kind);
+        match event.0 {
+            SimEvent::Launched => record.launched += 1,
+            SimEvent::Returned { on_target } => {
+                record.returned += 1;
+                record.on_target += on_target as u32;
+            }
+            SimEvent::Missed => record.missed += 1,
+            _ => continue,
+        }
+        changed = true;
+    }
+    // Write once per ball, after it's decided, rather than on the launch as well.
+    if changed && !game.state.is_ball_active {
+        util::save_data(DRILLS_FILE, &*records);
+    }
+}
+
+/// Shows the target zone on the launcher's side while training.
+pub fn target_zone_system(
+    game: Res<state::Match>,
+    mut marker: Query<(&mut Sprite, &mut Transform, &mut Visibility), With<TargetZoneMarker>>,
+) {
+    let zone = match game.rules.mode {
+        GameMode::Training(drill) if drill.target != TargetZone::Anywhere => Some(drill.target),
+        _ => None,
+    };
+    for (mut sprite, mut transform, mut visibility) in marker.iter_mut() {
+        let Some(zone) = zone else {
+            visibility.set_if_neq(Visibility::Hidden);
+            continue;
+        };
+        let (min_x, max_x) = zone.range(&game.arena);
+        let height = 12.0;
+        sprite.custom_size = Some(Vec2::new(max_x - min_x, height));
+        transform.translation.x = (min_x + max_x) / 2.0;
+        transform.translation.y = game.arena.ground_y() - game.arena.player_size().y / 2.0 + height / 2.0;
+        visibility.set_if_neq(Visibility::Inherited);
+    }
+}
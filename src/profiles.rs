@@ -0,0 +1,345 @@
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use crate::bindings::PlayerBindings;
+use crate::config;
+use crate::netplay::NetSession;
+use crate::remote::RemoteSession;
+use crate::settings::Settings;
+use crate::sim::Side;
+use crate::skins::is_distinct;
+use crate::state;
+use crate::util;
+
+const PROFILES_FILE: &str = "profiles.toml";
+
+/// A named player with their preferred look and keys.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Profile {
+    /// Stays the same through renames, so match history can tell who played.
+    #[serde(default)]
+    pub id: u32,
+    pub name: String,
+    /// Hue shift the dino is drawn with.
+    pub hue: f32,
+    /// Preferred keys; `None` plays with the default keys of whichever slot the profile is picked for.
+    #[serde(default)]
+    pub controls: Option<PlayerBindings>,
+}
+
+impl Profile {
+    /// Keys this profile plays with in `slot`.
+    pub fn controls_for(&self, slot: usize) -> PlayerBindings {
+        self.controls.clone().unwrap_or_else(|| PlayerBindings::for_slot(slot))
+    }
+}
+
+/// Saved profiles and who is picked for each player slot.
+#[derive(Resource, Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(from = "SavedProfiles", into = "SavedProfiles")]
+pub struct Profiles {
+    pub profiles: Vec<Profile>,
+    /// Index into `profiles` for each player slot; `None` plays as a guest.
+    pub selected: Vec<Option<usize>>,
+    /// Last id handed out; deleted profiles' ids aren't reused.
+    next_id: u32,
+}
+
+/// `Profiles` as written to disk. TOML can't hold a guest's `None`, so slots are stored
+/// as profile ids, 0 for a guest.
+#[derive(Serialize, Deserialize, Default)]
+#[serde(default)]
+struct SavedProfiles {
+    profiles: Vec<Profile>,
+    selected: Vec<u32>,
+    next_id: u32,
+}
+
+impl From<SavedProfiles> for Profiles {
+    fn from(saved: SavedProfiles) -> Self {
+        let selected = saved.selected.iter()
+            .map(|id| saved.profiles.iter().position(|profile| *id != 0 && profile.id == *id))
+            .collect();
+        Self { profiles: saved.profiles, selected, next_id: saved.next_id }
+    }
+}
+
+impl From<Profiles> for SavedProfiles {
+    fn from(profiles: Profiles) -> Self {
+        let selected = (0..profiles.selected.len())
+            .map(|slot| profiles.selected(slot).map_or(0, |profile| profile.id))
+            .collect();
+        Self { profiles: profiles.profiles, selected, next_id: profiles.next_id }
+    }
+}
+
+impl Profiles {
+    pub fn load() -> Self {
+        let mut profiles: Self = util::load_data(PROFILES_FILE);
+        let count = profiles.profiles.len();
+        profiles.selected.resize(config::MAX_PLAYERS, None);
+        for selected in &mut profiles.selected {
+            *selected = selected.filter(|index| *index < count);
+        }
+        // Profiles saved before ids existed get one now.
+        for index in 0..count {
+            let id = profiles.profiles[index].id;
+            if id == 0 || profiles.profiles[..index].iter().any(|other| other.id == id) {
+                profiles.profiles[index].id = profiles.new_id();
+            }
+        }
+        profiles
+    }
+
+    fn new_id(&mut self) -> u32 {
+        let highest = self.profiles.iter().map(|profile| profile.id).max().unwrap_or(0);
+        self.next_id = self.next_id.max(highest) + 1;
+        self.next_id
+    }
+
+    pub fn selected(&self, slot: usize) -> Option<&Profile> {
+        self.selected.get(slot).copied().flatten().and_then(|index| self.profiles.get(index))
+    }
+
+    /// Name of the profile in `slot`, unless it is a guest or the name is blank.
+    pub fn slot_name(&self, slot: usize) -> Option<&str> {
+        self.selected(slot).map(|profile| profile.name.trim()).filter(|name| !name.is_empty())
+    }
+
+    /// Slot `index` is picked for, if any.
+    pub fn slot_of(&self, index: usize) -> Option<usize> {
+        self.selected.iter().position(|selected| *selected == Some(index))
+    }
+
+    /// Picks the next profile nobody else is using for `slot`, wrapping back to the guest.
+    /// Profiles whose keys would clash with another slot's are skipped.
+    pub fn cycle_slot(&mut self, slot: usize, settings: &mut Settings) {
+        let Some(current) = self.selected.get(slot).copied() else { return };
+        let start = current.map_or(0, |index| index + 1);
+        let next = (start..self.profiles.len()).find(|index| {
+            self.slot_of(*index).is_none()
+                && !settings.controls.clashes(slot, &self.profiles[*index].controls_for(slot))
+        });
+        self.selected[slot] = next;
+        if let Some(index) = next {
+            self.apply_controls(index, settings);
+        }
+    }
+
+    /// Colour `slot` plays in: their profile's if they have one, otherwise the slot's own.
+    pub fn slot_hue(&self, slot: usize, settings: &Settings) -> f32 {
+        self.selected(slot).map_or_else(
+            || settings.dino_hues.get(slot).copied().unwrap_or(config::PLAYER_HUE_SHIFTS[slot % config::MAX_PLAYERS]),
+            |profile| profile.hue,
+        )
+    }
+
+    /// Stores a colour picked for `slot`, on their profile if they have one.
+    pub fn set_slot_hue(&mut self, slot: usize, hue: f32, settings: &mut Settings) {
+        match self.selected.get(slot).copied().flatten() {
+            Some(index) => self.profiles[index].hue = hue,
+            None => {
+                if let Some(slot_hue) = settings.dino_hues.get_mut(slot) {
+                    *slot_hue = hue;
+                }
+            }
+        }
+    }
+
+    /// Gives the profile's keys to whichever slot it's picked for.
+    pub fn apply_controls(&self, index: usize, settings: &mut Settings) {
+        if let (Some(slot), Some(profile)) = (self.slot_of(index), self.profiles.get(index))
+            && let Some(bindings) = settings.controls.players.get_mut(slot)
+        {
+            *bindings = profile.controls_for(slot);
+        }
+    }
+
+    /// Keys rebound in the settings become the preference of the profile playing that slot.
+    pub fn remember_controls(&mut self, slot: usize, bindings: &PlayerBindings) {
+        if let Some(index) = self.selected.get(slot).copied().flatten()
+            && let Some(profile) = self.profiles.get_mut(index)
+        {
+            profile.controls = Some(bindings.clone());
+        }
+    }
+
+    /// Adds a profile with a colour nobody has yet and returns its index.
+    pub fn create(&mut self) -> usize {
+        let taken = |hue: f32| self.profiles.iter().any(|profile| profile.hue == hue);
+        let hue = config::DINO_COLORS.iter()
+            .map(|(_, hue)| *hue)
+            .find(|hue| !taken(*hue))
+            .unwrap_or(0.0);
+        let id = self.new_id();
+        self.profiles.push(Profile {
+            id,
+            name: format!("DINO {}", self.profiles.len() + 1),
+            hue,
+            controls: None,
+        });
+        self.profiles.len() - 1
+    }
+
+    pub fn remove(&mut self, index: usize) {
+        if index >= self.profiles.len() {
+            return;
+        }
+        self.profiles.remove(index);
+        for selected in &mut self.selected {
+            *selected = match *selected {
+                Some(picked) if picked == index => None,
+                Some(picked) if picked > index => Some(picked - 1),
+                other => other,
+            };
+        }
+    }
+}
+
+pub fn save_profiles(profiles: Res<Profiles>) {
+    if profiles.is_changed() && !profiles.is_added() {
+        util::save_data(PROFILES_FILE, &*profiles);
+    }
+}
+
+/// Name and colour of whoever plays one slot.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SlotPlayer {
+    pub name: String,
+    pub hue: f32,
+    /// Playing under a saved profile rather than as a guest or CPU.
+    pub named: bool,
+    /// Id of the profile playing, even one with a blank name.
+    pub profile: Option<u32>,
+}
+
+/// Who is playing each slot of the current match.
+#[derive(Resource, Default, Debug)]
+pub struct Players(pub Vec<SlotPlayer>);
+
+impl Players {
+    pub fn names(&self) -> Vec<String> {
+        self.0.iter().map(|player| player.name.clone()).collect()
+    }
+
+    pub fn hue(&self, slot: usize) -> f32 {
+        self.0.get(slot).map_or(config::PLAYER_HUE_SHIFTS[slot % config::MAX_PLAYERS], |player| player.hue)
+    }
+
+    /// Everyone on `side` as one label; teams without a named player are just "TEAM n".
+    pub fn side_name(&self, side: Side) -> String {
+        let team: Vec<&SlotPlayer> = self.0.iter()
+            .enumerate()
+            .filter(|(slot, _)| slot.is_multiple_of(2) == (side == Side::Left))
+            .map(|(_, player)| player)
+            .collect();
+        if team.len() > 1 && team.iter().all(|player| !player.named) {
+            return format!("TEAM {}", side as usize + 1);
+        }
+        team.iter().map(|player| player.name.as_str()).collect::<Vec<_>>().join(" & ")
+    }
+}
+
+/// Works out names and colours for the match being played.
+/// Online, the local player is player 1 whatever slot they ended up in.
+pub fn resolve_players(
+    game: Res<state::Match>,
+    profiles: Res<Profiles>,
+    settings: Res<Settings>,
+    cpu_players: Res<state::CpuPlayers>,
+    net: Option<Res<NetSession>>,
+    remote: Option<Res<RemoteSession>>,
+    mut players: ResMut<Players>,
+) {
+    let online = net.is_some() || remote.is_some();
+    let local = net.and_then(|session| session.local_slot())
+        .or_else(|| remote.and_then(|session| session.slot));
+    let mut resolved: Vec<SlotPlayer> = (0..game.players.len())
+        .map(|slot| {
+            let slot_hue = profiles.slot_hue(slot, &settings);
+            let guest = |name: String| SlotPlayer { name, hue: slot_hue, named: false, profile: None };
+            let owner = match (online, local) {
+                (true, Some(local)) if local == slot => {
+                    // Our own colour, whichever side the host put us on.
+                    let hue = profiles.slot_hue(0, &settings);
+                    let profile = profiles.selected(0);
+                    let id = profile.map(|profile| profile.id);
+                    return match profile {
+                        Some(profile) if !profile.name.trim().is_empty() => SlotPlayer { name: profile.name.clone(), hue, named: true, profile: id },
+                        _ => SlotPlayer { name: "PLAYER 1".to_string(), hue, named: false, profile: id },
+                    };
+                }
+                (true, Some(_)) => return guest("OPPONENT".to_string()),
+                // Watching: nobody here is playing.
+                (true, None) => return guest(format!("PLAYER {}", slot + 1)),
+                (false, _) if cpu_players.controllers.iter().any(|(cpu, _)| *cpu == slot) => {
+                    return guest("CPU".to_string());
+                }
+                (false, _) => slot,
+            };
+            match profiles.selected(owner) {
+                // A profile renamed to nothing still needs something to show.
+                Some(profile) if profile.name.trim().is_empty() => {
+                    SlotPlayer { name: format!("PLAYER {}", owner + 1), hue: profile.hue, named: false, profile: Some(profile.id) }
+                }
+                Some(profile) => SlotPlayer { name: profile.name.clone(), hue: profile.hue, named: true, profile: Some(profile.id) },
+                None => guest(format!("PLAYER {}", owner + 1)),
+            }
+        })
+        .collect();
+    // Profiles can still share a colour; later slots give way to a free default.
+    for slot in 1..resolved.len() {
+        let taken: Vec<f32> = resolved[..slot].iter().map(|player| player.hue).collect();
+        if !is_distinct(resolved[slot].hue, taken.iter().copied()) {
+            let free = config::PLAYER_HUE_SHIFTS.iter()
+                .chain(config::DINO_COLORS.iter().map(|(_, hue)| hue))
+                .copied()
+                .find(|hue| is_distinct(*hue, taken.iter().copied()));
+            resolved[slot].hue = free.unwrap_or(resolved[slot].hue);
+        }
+    }
+    if players.0 != resolved {
+        players.0 = resolved;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn new_profiles_take_the_keys_of_their_slot() {
+        let mut profiles = Profiles { selected: vec![None; config::MAX_PLAYERS], ..default() };
+        let mut settings = Settings::default();
+        profiles.create();
+        profiles.create();
+        profiles.cycle_slot(0, &mut settings);
+        profiles.cycle_slot(1, &mut settings);
+        assert_eq!(profiles.selected[..2], [Some(0), Some(1)]);
+        assert_eq!(settings.controls.players[0], PlayerBindings::player1());
+        assert_eq!(settings.controls.players[1], PlayerBindings::player2());
+    }
+
+    #[test]
+    fn guest_slots_survive_saving() {
+        let mut profiles = Profiles { selected: vec![None; config::MAX_PLAYERS], ..default() };
+        profiles.create();
+        profiles.create();
+        profiles.selected[1] = Some(1);
+        let saved = toml::to_string_pretty(&profiles).unwrap();
+        let loaded: Profiles = toml::from_str(&saved).unwrap();
+        assert_eq!(loaded.selected, profiles.selected);
+        assert_eq!(loaded.profiles, profiles.profiles);
+    }
+
+    #[test]
+    fn profiles_with_another_slots_keys_are_skipped() {
+        let mut profiles = Profiles { selected: vec![None; config::MAX_PLAYERS], ..default() };
+        let mut settings = Settings::default();
+        let wasd = profiles.create();
+        profiles.profiles[wasd].controls = Some(PlayerBindings::player1());
+        profiles.create();
+        profiles.cycle_slot(1, &mut settings);
+        assert_eq!(profiles.selected[1], Some(1));
+        assert!(settings.controls.conflicts().is_empty());
+    }
+}
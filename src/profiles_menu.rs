@@ -0,0 +1,344 @@
+use bevy::input::keyboard::{Key, KeyboardInput};
+use bevy::prelude::*;
+use crate::bindings::{self, PlayerBindings};
+use crate::config;
+use crate::profiles::Profiles;
+use crate::settings::Settings;
+use crate::skins::{hue_label, swatch_color};
+use crate::state;
+use crate::types::*;
+
+#[allow(clippy::type_complexity)]
+const CONTROL_PRESETS: [(&str, fn() -> PlayerBindings); 4] = [
+    ("WASD", PlayerBindings::player1),
+    ("ARROWS", PlayerBindings::player2),
+    ("IJKL", PlayerBindings::player3),
+    ("NUMPAD", PlayerBindings::player4),
+];
+
+/// Profile the lower half of the profiles screen is editing.
+#[derive(Resource, Default)]
+pub struct EditingProfile(pub Option<usize>);
+
+pub fn setup_profiles_menu(mut commands: Commands, profiles: Res<Profiles>, mut editing: ResMut<EditingProfile>) {
+    // Start on whoever is playing as player 1, if anyone.
+    editing.0 = profiles.selected.first().copied().flatten()
+        .or((!profiles.profiles.is_empty()).then_some(0));
+
+    commands.spawn((
+        Node {
+            width: Val::Percent(100.0),
+            height: Val::Percent(100.0),
+            align_items: AlignItems::Center,
+            justify_content: JustifyContent::Center,
+            flex_direction: FlexDirection::Column,
+            ..default()
+        },
+        BackgroundColor(Color::srgba(0.0, 0.0, 0.0, 0.85)),
+        ProfilesUI,
+    )).with_children(|parent| {
+        parent.spawn((
+            text("PROFILES", 48.0, Color::WHITE),
+            Node {
+                margin: UiRect::bottom(Val::Px(16.0)),
+                ..default()
+            },
+        ));
+
+        parent.spawn(text("WHO'S PLAYING", 22.0, Color::WHITE));
+        parent.spawn(Node {
+            flex_direction: FlexDirection::Row,
+            margin: UiRect::bottom(Val::Px(20.0)),
+            ..default()
+        }).with_children(|row| {
+            for slot in 0..config::MAX_PLAYERS {
+                row.spawn(Node {
+                    flex_direction: FlexDirection::Column,
+                    align_items: AlignItems::Center,
+                    ..default()
+                }).with_children(|column| {
+                    column.spawn(text(&format!("PLAYER {}", slot + 1), 18.0, Color::srgb(0.7, 0.7, 0.7)));
+                    spawn_button(column, 200.0, ProfilesButton::Slot(slot), |btn| {
+                        btn.spawn((text("", 22.0, Color::WHITE), ProfilesValue::Slot(slot)));
+                    });
+                });
+            }
+        });
+
+        parent.spawn(text("EDIT PROFILE", 22.0, Color::WHITE));
+        parent.spawn(Node {
+            flex_direction: FlexDirection::Row,
+            align_items: AlignItems::Center,
+            ..default()
+        }).with_children(|row| {
+            spawn_button(row, 240.0, ProfilesButton::Edit, |btn| {
+                btn.spawn((text("", 22.0, Color::WHITE), ProfilesValue::Edit));
+            });
+            spawn_button(row, 120.0, ProfilesButton::New, |btn| {
+                btn.spawn(text("NEW", 22.0, Color::WHITE));
+            });
+            spawn_button(row, 120.0, ProfilesButton::Delete, |btn| {
+                btn.spawn(text("DELETE", 22.0, Color::WHITE));
+            });
+        });
+
+        let rows = [
+            ("NAME", None, ProfilesValue::Name),
+            ("COLOUR", Some(ProfilesButton::Color), ProfilesValue::Color),
+            ("CONTROLS", Some(ProfilesButton::Controls), ProfilesValue::Controls),
+        ];
+        for (label, action, value) in rows {
+            parent.spawn(Node {
+                flex_direction: FlexDirection::Row,
+                align_items: AlignItems::Center,
+                ..default()
+            }).with_children(|row| {
+                row.spawn((
+                    text(label, 22.0, Color::WHITE),
+                    Node {
+                        width: Val::Px(180.0),
+                        margin: UiRect::all(Val::Px(4.0)),
+                        ..default()
+                    },
+                ));
+                let Some(action) = action else {
+                    // Typed straight in, like the join address.
+                    row.spawn((
+                        text("", 22.0, Color::WHITE),
+                        Node {
+                            width: Val::Px(240.0),
+                            margin: UiRect::all(Val::Px(4.0)),
+                            ..default()
+                        },
+                        TextLayout::new_with_justify(Justify::Center),
+                        value,
+                    ));
+                    return;
+                };
+                spawn_button(row, 240.0, action, |btn| {
+                    if value == ProfilesValue::Color {
+                        btn.spawn((
+                            Node {
+                                width: Val::Px(20.0),
+                                height: Val::Px(20.0),
+                                margin: UiRect::right(Val::Px(8.0)),
+                                ..default()
+                            },
+                            BackgroundColor(Color::NONE),
+                            ProfileSwatch,
+                        ));
+                    }
+                    btn.spawn((text("", 22.0, Color::WHITE), value));
+                });
+            });
+        }
+
+        parent.spawn((
+            text("TYPE TO RENAME. KEYS REBOUND IN THE SETTINGS ARE SAVED TO THE PLAYER'S PROFILE. PROFILES WITH KEYS ANOTHER PLAYER USES ARE SKIPPED.", 16.0, Color::srgb(0.7, 0.7, 0.7)),
+            Node {
+                margin: UiRect::vertical(Val::Px(16.0)),
+                ..default()
+            },
+        ));
+        parent.spawn((text("", 18.0, Color::srgb(1.0, 0.4, 0.4)), ProfilesValue::Conflicts));
+
+        parent.spawn((
+            Button,
+            Node {
+                width: Val::Px(200.0),
+                height: Val::Px(60.0),
+                margin: UiRect::all(Val::Px(10.0)),
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
+                ..default()
+            },
+            BackgroundColor(Color::srgb(0.2, 0.2, 0.2)),
+            ProfilesButton::Back,
+        )).with_children(|btn| {
+            btn.spawn(text("BACK", 28.0, Color::WHITE));
+        });
+    });
+}
+
+fn spawn_button(parent: &mut ChildSpawnerCommands, width: f32, action: ProfilesButton, children: impl FnOnce(&mut ChildSpawnerCommands)) {
+    parent.spawn((
+        Button,
+        Node {
+            width: Val::Px(width),
+            height: Val::Px(36.0),
+            margin: UiRect::all(Val::Px(4.0)),
+            justify_content: JustifyContent::Center,
+            align_items: AlignItems::Center,
+            ..default()
+        },
+        BackgroundColor(Color::srgb(0.2, 0.2, 0.2)),
+        action,
+    )).with_children(children);
+}
+
+fn text(value: &str, size: f32, color: Color) -> (Text, TextFont, TextColor) {
+    (
+        Text::new(value),
+        TextFont {
+            font_size: size,
+            ..default()
+        },
+        TextColor(color),
+    )
+}
+
+pub fn cleanup_profiles_menu(mut commands: Commands, profiles_ui: Query<Entity, With<ProfilesUI>>) {
+    for entity in profiles_ui.iter() {
+        commands.entity(entity).despawn();
+    }
+}
+
+pub fn profiles_button_system(
+    interaction_query: Query<(&Interaction, &ProfilesButton), Changed<Interaction>>,
+    mut profiles: ResMut<Profiles>,
+    mut settings: ResMut<Settings>,
+    mut editing: ResMut<EditingProfile>,
+    mut app_state: ResMut<NextState<state::AppMode>>,
+    menu_ui: Query<Entity, With<MenuUI>>,
+    mut commands: Commands,
+) {
+    for (interaction, action) in interaction_query.iter() {
+        if *interaction != Interaction::Pressed {
+            continue;
+        }
+        let count = profiles.profiles.len();
+        match *action {
+            ProfilesButton::Slot(slot) => profiles.cycle_slot(slot, &mut settings),
+            ProfilesButton::Edit if count > 0 => {
+                editing.0 = Some(editing.0.map_or(0, |index| (index + 1) % count));
+            }
+            ProfilesButton::New => editing.0 = Some(profiles.create()),
+            ProfilesButton::Delete => {
+                if let Some(index) = editing.0 {
+                    profiles.remove(index);
+                    let left = profiles.profiles.len();
+                    editing.0 = (left > 0).then(|| index.min(left - 1));
+                }
+            }
+            ProfilesButton::Color => {
+                if let Some(profile) = editing.0.and_then(|index| profiles.profiles.get_mut(index)) {
+                    let current = config::DINO_COLORS.iter().position(|(_, hue)| *hue == profile.hue);
+                    let next = current.map_or(0, |i| (i + 1) % config::DINO_COLORS.len());
+                    profile.hue = config::DINO_COLORS[next].1;
+                }
+            }
+            ProfilesButton::Controls => {
+                if let Some(index) = editing.0.filter(|index| *index < count) {
+                    // The slot's own keys first, then each preset.
+                    let options: Vec<Option<PlayerBindings>> = std::iter::once(None)
+                        .chain(CONTROL_PRESETS.iter().map(|(_, keys)| Some(keys())))
+                        .collect();
+                    let current = options.iter().position(|keys| *keys == profiles.profiles[index].controls);
+                    let start = current.map_or(0, |i| i + 1);
+                    // While the profile is playing, skip keys another slot already uses.
+                    let slot = profiles.slot_of(index);
+                    let next = (0..options.len())
+                        .map(|n| &options[(start + n) % options.len()])
+                        .find(|keys| slot.is_none_or(|slot| {
+                            let keys = keys.as_ref().map_or_else(|| PlayerBindings::for_slot(slot), Clone::clone);
+                            !settings.controls.clashes(slot, &keys)
+                        }));
+                    if let Some(keys) = next.cloned() {
+                        profiles.profiles[index].controls = keys;
+                        profiles.apply_controls(index, &mut settings);
+                    }
+                }
+            }
+            ProfilesButton::Back => {
+                app_state.set(state::AppMode::Menu);
+                for entity in menu_ui.iter() {
+                    commands.entity(entity).insert(Visibility::Visible);
+                }
+            }
+            ProfilesButton::Edit => {}
+        }
+    }
+}
+
+pub fn name_input_system(
+    mut keyboard_events: MessageReader<KeyboardInput>,
+    mut profiles: ResMut<Profiles>,
+    editing: Res<EditingProfile>,
+) {
+    let Some(index) = editing.0.filter(|index| *index < profiles.profiles.len()) else {
+        keyboard_events.clear();
+        return;
+    };
+    for event in keyboard_events.read() {
+        if !event.state.is_pressed() {
+            continue;
+        }
+        match &event.logical_key {
+            Key::Backspace => {
+                profiles.profiles[index].name.pop();
+            }
+            Key::Space => push_name(&mut profiles.profiles[index].name, " "),
+            Key::Character(text) => push_name(&mut profiles.profiles[index].name, text),
+            _ => {}
+        }
+    }
+}
+
+/// Appends the characters a player name may use, upper-cased and capped in length.
+pub fn push_name(name: &mut String, text: &str) {
+    let allowed = text.chars()
+        .filter(|c| c.is_ascii_alphanumeric() || matches!(c, ' ' | '-' | '_' | '.'))
+        .map(|c| c.to_ascii_uppercase());
+    for c in allowed {
+        if name.len() < config::MAX_PROFILE_NAME {
+            name.push(c);
+        }
+    }
+}
+
+pub fn profiles_display_system(
+    profiles: Res<Profiles>,
+    settings: Res<Settings>,
+    editing: Res<EditingProfile>,
+    mut texts: Query<(&ProfilesValue, &mut Text)>,
+    mut swatches: Query<&mut BackgroundColor, (With<ProfileSwatch>, Without<ProfilesButton>)>,
+    mut buttons: Query<(&Interaction, &mut BackgroundColor), With<ProfilesButton>>,
+) {
+    let edited = editing.0.and_then(|index| profiles.profiles.get(index));
+    for (value, mut text) in texts.iter_mut() {
+        let label = match (value, edited) {
+            (ProfilesValue::Conflicts, _) => settings.controls.conflicts().iter()
+                .filter(|c| c.first.0 != c.second.0)
+                .map(|c| format!("P{} AND P{} BOTH USE {}", c.first.0 + 1, c.second.0 + 1, bindings::key_label(c.key)))
+                .collect::<Vec<_>>()
+                .join("\n"),
+            (ProfilesValue::Slot(slot), _) => profiles.selected(*slot).map_or("GUEST".to_string(), |profile| profile.name.clone()),
+            (ProfilesValue::Edit, None) => "NO PROFILES".to_string(),
+            (_, None) => "-".to_string(),
+            (ProfilesValue::Edit, Some(profile)) => profile.name.clone(),
+            (ProfilesValue::Name, Some(profile)) => format!("{}_", profile.name),
+            (ProfilesValue::Color, Some(profile)) => hue_label(profile.hue),
+            (ProfilesValue::Controls, Some(profile)) => match &profile.controls {
+                None => "SLOT DEFAULT",
+                Some(controls) => CONTROL_PRESETS.iter()
+                    .find(|(_, keys)| keys() == *controls)
+                    .map_or("CUSTOM", |(name, _)| *name),
+            }.to_string(),
+        };
+        if text.0 != label {
+            text.0 = label;
+        }
+    }
+
+    for mut color in swatches.iter_mut() {
+        color.0 = edited.map_or(Color::NONE, |profile| swatch_color(profile.hue));
+    }
+
+    for (interaction, mut color) in buttons.iter_mut() {
+        *color = match interaction {
+            Interaction::Pressed => BackgroundColor(Color::srgb(0.5, 0.5, 0.5)),
+            Interaction::Hovered => BackgroundColor(Color::srgb(0.4, 0.4, 0.4)),
+            Interaction::None => BackgroundColor(Color::srgb(0.2, 0.2, 0.2)),
+        };
+    }
+}
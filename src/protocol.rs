@@ -0,0 +1,707 @@
+use glam::Vec2;
+use crate::config::{self, SpeedLevel};
+use crate::sim::{Arena, Drill, DrillKind, Fault, GameMode, GameState, LaunchSpin, MatchRules, MatchSim, MatchStats, PlayerInput, PlayerState, ServingPlayer, Side, SimEvent, TargetZone};
+
+const MAGIC: [u8; 2] = *b"DB";
+/// Bump when packets change; peers with a different version refuse to connect.
+pub const PROTOCOL_VERSION: u8 = 7;
+
+const HELLO: u8 = 1;
+const WELCOME: u8 = 2;
+const INPUTS: u8 = 3;
+const ANNOUNCE: u8 = 4;
+const CONNECT: u8 = 5;
+const ACCEPTED: u8 = 6;
+const INPUT: u8 = 7;
+const SNAPSHOT: u8 = 8;
+const LEAVE: u8 = 9;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Packet {
+    Hello { version: u8 },
+    /// The host's answer to `Hello`, carrying everything both peers need to build the same match.
+    Welcome { version: u8, speed: SpeedLevel, rules: MatchRules, input_delay: u8 },
+    /// Sender's inputs from frame `start` on, plus how many of the receiver's frames it already has.
+    Inputs { ack: u32, start: u32, inputs: Vec<PlayerInput> },
+    /// Broadcast by waiting hosts for LAN discovery; `id` tells apart hosts heard on several routes.
+    Announce { version: u8, id: u32, port: u16, speed: SpeedLevel, rules: MatchRules, name: String },
+    /// Asks a server for a player slot, or just to watch. Repeated as a keep-alive, echoing the
+    /// `token` from the last `Accepted` (0 before there was one) to prove the address is ours.
+    Connect { version: u8, spectate: bool, token: u32 },
+    /// The server's answer to `Connect`; `slot` is `None` for spectators.
+    Accepted { version: u8, slot: Option<u8>, arena: Arena, speed: SpeedLevel, rules: MatchRules, token: u32 },
+    /// Buttons a player is holding right now; `seq` only ever grows so stale packets can be dropped.
+    Input { seq: u32, input: PlayerInput },
+    Snapshot(Box<Snapshot>),
+    Leave,
+}
+
+impl Packet {
+    pub fn encode(&self) -> Vec<u8> {
+        let mut bytes = MAGIC.to_vec();
+        match self {
+            Packet::Hello { version } => bytes.extend([HELLO, *version]),
+            Packet::Welcome { version, speed, rules, input_delay } => {
+                bytes.extend([WELCOME, *version, speed.to_u32() as u8]);
+                put_rules(&mut bytes, rules);
+                bytes.push(*input_delay);
+            }
+            Packet::Inputs { ack, start, inputs } => {
+                bytes.push(INPUTS);
+                bytes.extend(ack.to_le_bytes());
+                bytes.extend(start.to_le_bytes());
+                bytes.push(inputs.len() as u8);
+                bytes.extend(inputs.iter().map(|input| input.to_bits()));
+            }
+            Packet::Announce { version, id, port, speed, rules, name } => {
+                bytes.extend([ANNOUNCE, *version]);
+                bytes.extend(id.to_le_bytes());
+                bytes.extend(port.to_le_bytes());
+                bytes.push(speed.to_u32() as u8);
+                put_rules(&mut bytes, rules);
+                let name = &name.as_bytes()[..name.len().min(u8::MAX as usize)];
+                bytes.push(name.len() as u8);
+                bytes.extend(name);
+            }
+            Packet::Connect { version, spectate, token } => {
+                bytes.extend([CONNECT, *version, *spectate as u8]);
+                bytes.extend(token.to_le_bytes());
+            }
+            Packet::Accepted { version, slot, arena, speed, rules, token } => {
+                bytes.extend([ACCEPTED, *version, slot.unwrap_or(u8::MAX)]);
+                put_arena(&mut bytes, arena);
+                bytes.push(speed.to_u32() as u8);
+                put_rules(&mut bytes, rules);
+                bytes.extend(token.to_le_bytes());
+            }
+            Packet::Input { seq, input } => {
+                bytes.push(INPUT);
+                bytes.extend(seq.to_le_bytes());
+                bytes.push(input.to_bits());
+            }
+            Packet::Snapshot(snapshot) => {
+                bytes.push(SNAPSHOT);
+                snapshot.encode(&mut bytes);
+            }
+            Packet::Leave => bytes.push(LEAVE),
+        }
+        bytes
+    }
+
+    /// Parses a datagram, rejecting anything malformed or with trailing bytes.
+    pub fn decode(bytes: &[u8]) -> Option<Packet> {
+        let (magic, rest) = bytes.split_at_checked(2)?;
+        if magic != MAGIC {
+            return None;
+        }
+        let mut r = Reader(rest);
+        let packet = match r.u8()? {
+            HELLO => Packet::Hello { version: r.u8()? },
+            WELCOME => Packet::Welcome { version: r.u8()?, speed: r.speed()?, rules: r.rules()?, input_delay: r.u8()? },
+            INPUTS => {
+                let ack = r.u32()?;
+                let start = r.u32()?;
+                let len = r.u8()? as usize;
+                let inputs = r.take(len)?.iter().map(|bits| PlayerInput::from_bits(*bits)).collect();
+                Packet::Inputs { ack, start, inputs }
+            }
+            ANNOUNCE => {
+                let version = r.u8()?;
+                let id = r.u32()?;
+                let port = r.u16()?;
+                let speed = r.speed()?;
+                let rules = r.rules()?;
+                let len = r.u8()? as usize;
+                let name = String::from_utf8_lossy(r.take(len)?).into_owned();
+                Packet::Announce { version, id, port, speed, rules, name }
+            }
+            CONNECT => Packet::Connect { version: r.u8()?, spectate: r.bool()?, token: r.u32()? },
+            ACCEPTED => {
+                let version = r.u8()?;
+                let slot = Some(r.u8()?).filter(|slot| *slot != u8::MAX);
+                Packet::Accepted { version, slot, arena: r.arena()?, speed: r.speed()?, rules: r.rules()?, token: r.u32()? }
+            }
+            INPUT => Packet::Input { seq: r.u32()?, input: r.input()? },
+            SNAPSHOT => Packet::Snapshot(Box::new(Snapshot::decode(&mut r)?)),
+            LEAVE => Packet::Leave,
+            _ => return None,
+        };
+        r.0.is_empty().then_some(packet)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PlayerSnapshot {
+    pub pos: Vec2,
+    pub facing_left: bool,
+    pub state: PlayerState,
+}
+
+/// Everything a client needs to draw one server tick, plus the events since the previous snapshot.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Snapshot {
+    pub tick: u32,
+    /// The match is on hold until every player slot is taken.
+    pub waiting: bool,
+    pub spectators: u16,
+    /// Court and rules of the match, so clients can rebuild theirs when the host's changes.
+    pub arena: Arena,
+    pub speed: SpeedLevel,
+    pub rules: MatchRules,
+    pub players: Vec<PlayerSnapshot>,
+    pub ball_pos: Vec2,
+    pub ball_rotation: f32,
+    pub state: GameState,
+    pub touch_side: Option<Side>,
+    pub touch_count: u32,
+    pub stats: MatchStats,
+    /// Keep-up hits so far.
+    pub keep_up_hits: u32,
+    /// Training launcher tally: launched, returned, on target and missed.
+    pub drill_counts: [u32; 4],
+    pub events: Vec<SimEvent>,
+}
+
+impl Snapshot {
+    pub fn capture(sim: &MatchSim, tick: u32, events: Vec<SimEvent>) -> Self {
+        Self {
+            tick,
+            waiting: false,
+            spectators: 0,
+            arena: sim.arena,
+            speed: sim.speed,
+            rules: sim.rules,
+            players: sim.players.iter()
+                .map(|p| PlayerSnapshot { pos: p.pos, facing_left: p.facing_left, state: p.state })
+                .collect(),
+            ball_pos: sim.ball.pos,
+            ball_rotation: sim.ball.rotation,
+            state: sim.state.clone(),
+            touch_side: sim.touches.side,
+            touch_count: sim.touches.count,
+            stats: sim.stats.clone(),
+            keep_up_hits: sim.keep_up.hits,
+            drill_counts: sim.launcher.as_ref()
+                .map_or([0; 4], |launcher| [launcher.launched, launcher.returned, launcher.on_target, launcher.missed]),
+            events,
+        }
+    }
+
+    /// Copies this tick into `sim`, with positions blended `t` of the way towards `next`.
+    /// Both the current and previous positions are set, so renderers draw exactly this.
+    pub fn apply(&self, next: &Snapshot, t: f32, sim: &mut MatchSim) {
+        // The host resized their window or moved on to another kind of match.
+        if sim.arena != self.arena || sim.speed != self.speed || sim.rules != self.rules {
+            *sim = MatchSim::new(self.arena, self.speed, self.rules);
+        }
+        for ((player, from), to) in sim.players.iter_mut().zip(&self.players).zip(&next.players) {
+            player.pos = from.pos.lerp(to.pos, t);
+            player.prev_pos = player.pos;
+            player.facing_left = from.facing_left;
+            player.state = from.state;
+        }
+        sim.ball.pos = self.ball_pos.lerp(next.ball_pos, t);
+        sim.ball.prev_pos = sim.ball.pos;
+        sim.ball.rotation = self.ball_rotation + (next.ball_rotation - self.ball_rotation) * t;
+        sim.ball.prev_rotation = sim.ball.rotation;
+        sim.state = self.state.clone();
+        sim.touches.side = self.touch_side;
+        sim.touches.count = self.touch_count;
+        sim.stats = self.stats.clone();
+        sim.keep_up.hits = self.keep_up_hits;
+        if let Some(launcher) = sim.launcher.as_mut() {
+            [launcher.launched, launcher.returned, launcher.on_target, launcher.missed] = self.drill_counts;
+        }
+    }
+
+    fn encode(&self, bytes: &mut Vec<u8>) {
+        bytes.extend(self.tick.to_le_bytes());
+        bytes.push(self.waiting as u8);
+        bytes.extend(self.spectators.to_le_bytes());
+        put_arena(bytes, &self.arena);
+        bytes.push(self.speed.to_u32() as u8);
+        put_rules(bytes, &self.rules);
+        bytes.push(self.players.len() as u8);
+        for player in &self.players {
+            put_vec2(bytes, player.pos);
+            bytes.extend([player.facing_left as u8, player.state as u8]);
+        }
+        put_vec2(bytes, self.ball_pos);
+        bytes.extend(self.ball_rotation.to_le_bytes());
+
+        let state = &self.state;
+        bytes.extend((state.player1_score as u16).to_le_bytes());
+        bytes.extend((state.player2_score as u16).to_le_bytes());
+        bytes.extend([state.player1_sets as u8, state.player2_sets as u8, state.set_scores.len() as u8]);
+        for (p1, p2) in &state.set_scores {
+            bytes.extend((*p1 as u16).to_le_bytes());
+            bytes.extend((*p2 as u16).to_le_bytes());
+        }
+        bytes.extend([
+            state.serving_player as u8,
+            state.is_ball_active as u8,
+            state.game_over as u8,
+            state.winner.unwrap_or(0) as u8,
+            self.touch_side.map_or(0, |side| side as u8 + 1),
+            self.touch_count as u8,
+        ]);
+
+        let stats = &self.stats;
+        bytes.push(stats.hits.len() as u8);
+        let counts = stats.hits.iter()
+            .chain(&stats.aces)
+            .chain(&stats.serve_points)
+            .chain([&stats.pole_hits, &stats.wall_bounces, &stats.longest_rally, &stats.rallies, &stats.rally_hits]);
+        for count in counts {
+            bytes.extend((*count as u16).to_le_bytes());
+        }
+        for count in [&self.keep_up_hits].into_iter().chain(&self.drill_counts) {
+            bytes.extend((*count as u16).to_le_bytes());
+        }
+
+        let events = &self.events[..self.events.len().min(u8::MAX as usize)];
+        bytes.push(events.len() as u8);
+        for event in events {
+            match *event {
+                SimEvent::Served { player } => bytes.extend([0, player as u8]),
+                SimEvent::PlayerHit { player, speed } => {
+                    bytes.extend([1, player as u8]);
+                    bytes.extend(speed.to_le_bytes());
+                }
+                SimEvent::PoleHit { speed } => {
+                    bytes.push(2);
+                    bytes.extend(speed.to_le_bytes());
+                }
+                SimEvent::WallBounce { speed } => {
+                    bytes.push(3);
+                    bytes.extend(speed.to_le_bytes());
+                }
+                SimEvent::Fault { side, fault } => bytes.extend([4, side as u8, fault as u8]),
+                SimEvent::PointScored { scorer } => bytes.extend([5, scorer as u8]),
+                SimEvent::SetWon { winner } => bytes.extend([6, winner as u8]),
+                SimEvent::MatchWon { winner } => bytes.extend([7, winner as u8]),
+                SimEvent::Dropped { hits } => {
+                    bytes.push(8);
+                    bytes.extend(hits.to_le_bytes());
+                }
+                SimEvent::Launched => bytes.push(9),
+                SimEvent::Returned { on_target } => bytes.extend([10, on_target as u8]),
+                SimEvent::Missed => bytes.push(11),
+            }
+        }
+    }
+
+    fn decode(r: &mut Reader) -> Option<Self> {
+        let tick = r.u32()?;
+        let waiting = r.bool()?;
+        let spectators = r.u16()?;
+        let arena = r.arena()?;
+        let speed = r.speed()?;
+        let rules = r.rules()?;
+        let players = (0..r.u8()?)
+            .map(|_| Some(PlayerSnapshot { pos: r.vec2()?, facing_left: r.bool()?, state: r.player_state()? }))
+            .collect::<Option<Vec<_>>>()?;
+        let ball_pos = r.vec2()?;
+        let ball_rotation = r.f32()?;
+
+        let player1_score = r.u16()? as u32;
+        let player2_score = r.u16()? as u32;
+        let player1_sets = r.u8()? as u32;
+        let player2_sets = r.u8()? as u32;
+        let set_scores = (0..r.u8()?)
+            .map(|_| Some((r.u16()? as u32, r.u16()? as u32)))
+            .collect::<Option<Vec<_>>>()?;
+        let state = GameState {
+            player1_score,
+            player2_score,
+            player1_sets,
+            player2_sets,
+            set_scores,
+            serving_player: if r.bool()? { ServingPlayer::Player2 } else { ServingPlayer::Player1 },
+            is_ball_active: r.bool()?,
+            game_over: r.bool()?,
+            winner: Some(r.u8()? as u32).filter(|winner| *winner != 0),
+        };
+        let touch_side = match r.u8()? {
+            0 => None,
+            side => Some(side_from(side - 1)?),
+        };
+        let touch_count = r.u8()? as u32;
+
+        let mut stats = MatchStats::default();
+        stats.hits = (0..r.u8()?).map(|_| r.u16().map(u32::from)).collect::<Option<Vec<_>>>()?;
+        for count in stats.aces.iter_mut().chain(&mut stats.serve_points) {
+            *count = r.u16()? as u32;
+        }
+        stats.pole_hits = r.u16()? as u32;
+        stats.wall_bounces = r.u16()? as u32;
+        stats.longest_rally = r.u16()? as u32;
+        stats.rallies = r.u16()? as u32;
+        stats.rally_hits = r.u16()? as u32;
+        let keep_up_hits = r.u16()? as u32;
+        let mut drill_counts = [0; 4];
+        for count in &mut drill_counts {
+            *count = r.u16()? as u32;
+        }
+
+        let events = (0..r.u8()?)
+            .map(|_| {
+                Some(match r.u8()? {
+                    0 => SimEvent::Served { player: r.u8()? as usize },
+                    1 => SimEvent::PlayerHit { player: r.u8()? as usize, speed: r.f32()? },
+                    2 => SimEvent::PoleHit { speed: r.f32()? },
+                    3 => SimEvent::WallBounce { speed: r.f32()? },
+                    4 => SimEvent::Fault {
+                        side: side_from(r.u8()?)?,
+                        fault: match r.u8()? {
+                            0 => Fault::TooManyTouches,
+                            1 => Fault::DoubleTouch,
+                            _ => return None,
+                        },
+                    },
+                    5 => SimEvent::PointScored { scorer: side_from(r.u8()?)? },
+                    6 => SimEvent::SetWon { winner: r.u8()? as u32 },
+                    7 => SimEvent::MatchWon { winner: r.u8()? as u32 },
+                    8 => SimEvent::Dropped { hits: r.u32()? },
+                    9 => SimEvent::Launched,
+                    10 => SimEvent::Returned { on_target: r.bool()? },
+                    11 => SimEvent::Missed,
+                    _ => return None,
+                })
+            })
+            .collect::<Option<Vec<_>>>()?;
+
+        Some(Self {
+            tick,
+            waiting,
+            spectators,
+            arena,
+            speed,
+            rules,
+            players,
+            ball_pos,
+            ball_rotation,
+            state,
+            touch_side,
+            touch_count,
+            stats,
+            keep_up_hits,
+            drill_counts,
+            events,
+        })
+    }
+}
+
+fn put_rules(bytes: &mut Vec<u8>, rules: &MatchRules) {
+    bytes.extend([
+        rules.winning_score as u8,
+        rules.win_by_two as u8,
+        rules.touch_rules as u8,
+        rules.sets as u8,
+        rules.team_size as u8,
+    ]);
+    match rules.mode {
+        GameMode::Versus => bytes.push(0),
+        GameMode::KeepUp { over_net } => bytes.push(1 + over_net as u8),
+        GameMode::Training(drill) => {
+            bytes.extend([
+                3,
+                drill.kind as u8,
+                drill.power as u8,
+                drill.angle as i8 as u8,
+                drill.spin as u8,
+                drill.random as u8,
+                drill.interval as u8,
+                drill.target as u8,
+            ]);
+            bytes.extend(drill.seed.to_le_bytes());
+        }
+    }
+}
+
+fn put_arena(bytes: &mut Vec<u8>, arena: &Arena) {
+    bytes.extend(arena.width.to_le_bytes());
+    bytes.extend(arena.height.to_le_bytes());
+}
+
+fn put_vec2(bytes: &mut Vec<u8>, v: Vec2) {
+    bytes.extend(v.x.to_le_bytes());
+    bytes.extend(v.y.to_le_bytes());
+}
+
+fn side_from(value: u8) -> Option<Side> {
+    match value {
+        0 => Some(Side::Left),
+        1 => Some(Side::Right),
+        _ => None,
+    }
+}
+
+/// Reads little-endian fields off the front of a datagram; `None` once it runs out.
+struct Reader<'a>(&'a [u8]);
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, len: usize) -> Option<&'a [u8]> {
+        let (head, rest) = self.0.split_at_checked(len)?;
+        self.0 = rest;
+        Some(head)
+    }
+    fn u8(&mut self) -> Option<u8> {
+        Some(self.take(1)?[0])
+    }
+    fn u16(&mut self) -> Option<u16> {
+        Some(u16::from_le_bytes(self.take(2)?.try_into().ok()?))
+    }
+    fn u32(&mut self) -> Option<u32> {
+        Some(u32::from_le_bytes(self.take(4)?.try_into().ok()?))
+    }
+    fn f32(&mut self) -> Option<f32> {
+        Some(f32::from_le_bytes(self.take(4)?.try_into().ok()?)).filter(|value| value.is_finite())
+    }
+    fn bool(&mut self) -> Option<bool> {
+        match self.u8()? {
+            0 => Some(false),
+            1 => Some(true),
+            _ => None,
+        }
+    }
+    fn vec2(&mut self) -> Option<Vec2> {
+        Some(Vec2::new(self.f32()?, self.f32()?))
+    }
+    fn arena(&mut self) -> Option<Arena> {
+        Some(Arena { width: self.f32()?, height: self.f32()? }).filter(|arena| arena.width > 0.0 && arena.height > 0.0)
+    }
+    fn speed(&mut self) -> Option<SpeedLevel> {
+        SpeedLevel::from_u32(self.u8()? as u32)
+    }
+    /// Rules a match could actually be set up with: winnable, an odd set count and at most two a side.
+    fn rules(&mut self) -> Option<MatchRules> {
+        let rules = MatchRules {
+            winning_score: self.u8()? as u32,
+            win_by_two: self.bool()?,
+            touch_rules: self.bool()?,
+            sets: self.u8()? as u32,
+            team_size: self.u8()? as u32,
+            mode: match self.u8()? {
+                0 => GameMode::Versus,
+                1 => GameMode::KeepUp { over_net: false },
+                2 => GameMode::KeepUp { over_net: true },
+                3 => GameMode::Training(self.drill()?),
+                _ => return None,
+            },
+        };
+        let valid = (1..=config::MAX_WINNING_SCORE).contains(&rules.winning_score)
+            && (1..=config::MAX_SETS).contains(&rules.sets)
+            && !rules.sets.is_multiple_of(2)
+            && (1..=(config::MAX_PLAYERS / 2) as u32).contains(&rules.team_size);
+        valid.then_some(rules)
+    }
+    fn drill(&mut self) -> Option<Drill> {
+        Some(Drill {
+            kind: *DrillKind::ALL.get(self.u8()? as usize)?,
+            power: self.u8()? as u32,
+            angle: self.u8()? as i8 as i32,
+            spin: match self.u8()? {
+                0 => LaunchSpin::None,
+                1 => LaunchSpin::Top,
+                2 => LaunchSpin::Back,
+                3 => LaunchSpin::Mixed,
+                _ => return None,
+            },
+            random: self.bool()?,
+            interval: self.u8()? as u32,
+            target: match self.u8()? {
+                0 => TargetZone::Anywhere,
+                1 => TargetZone::Short,
+                2 => TargetZone::Deep,
+                _ => return None,
+            },
+            seed: self.u32()?,
+        })
+    }
+    /// Only the four known buttons; anything else marks a forged or corrupt packet.
+    fn input(&mut self) -> Option<PlayerInput> {
+        let bits = self.u8()?;
+        (bits & !0b1111 == 0).then(|| PlayerInput::from_bits(bits))
+    }
+    fn player_state(&mut self) -> Option<PlayerState> {
+        match self.u8()? {
+            0 => Some(PlayerState::Idle),
+            1 => Some(PlayerState::Running),
+            2 => Some(PlayerState::Jumping),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn doubles() -> MatchRules {
+        MatchRules { winning_score: 15, win_by_two: true, touch_rules: true, sets: 3, team_size: 2, mode: GameMode::Versus }
+    }
+
+    fn training() -> MatchRules {
+        let drill = Drill {
+            kind: DrillKind::Pole,
+            power: 115,
+            angle: -10,
+            spin: LaunchSpin::Mixed,
+            random: true,
+            interval: 3,
+            target: TargetZone::Deep,
+            seed: 0xdead_beef,
+        };
+        MatchRules { mode: GameMode::Training(drill), ..MatchRules::default() }
+    }
+
+    fn snapshot() -> Snapshot {
+        let mut stats = MatchStats::default();
+        stats.hits = vec![4, 2, 7, 1];
+        stats.aces = [1, 0];
+        stats.serve_points = [3, 2];
+        stats.pole_hits = 5;
+        stats.wall_bounces = 6;
+        stats.longest_rally = 9;
+        stats.rallies = 8;
+        stats.rally_hits = 30;
+        Snapshot {
+            tick: 1234,
+            waiting: false,
+            spectators: 3,
+            arena: Arena { width: 1600.0, height: 900.0 },
+            speed: SpeedLevel::Slow,
+            rules: doubles(),
+            players: vec![
+                PlayerSnapshot { pos: Vec2::new(-300.0, -200.0), facing_left: false, state: PlayerState::Running },
+                PlayerSnapshot { pos: Vec2::new(250.5, -120.25), facing_left: true, state: PlayerState::Jumping },
+            ],
+            ball_pos: Vec2::new(12.5, 80.0),
+            ball_rotation: -1.5,
+            state: GameState {
+                player1_score: 4,
+                player2_score: 6,
+                player1_sets: 1,
+                player2_sets: 0,
+                set_scores: vec![(15, 12)],
+                serving_player: ServingPlayer::Player2,
+                is_ball_active: true,
+                game_over: false,
+                winner: None,
+            },
+            touch_side: Some(Side::Right),
+            touch_count: 2,
+            stats,
+            keep_up_hits: 0,
+            drill_counts: [10, 6, 4, 3],
+            events: vec![
+                SimEvent::Served { player: 1 },
+                SimEvent::PlayerHit { player: 2, speed: 900.0 },
+                SimEvent::PoleHit { speed: 450.0 },
+                SimEvent::WallBounce { speed: 120.0 },
+                SimEvent::Fault { side: Side::Left, fault: Fault::DoubleTouch },
+                SimEvent::PointScored { scorer: Side::Right },
+                SimEvent::SetWon { winner: 2 },
+                SimEvent::MatchWon { winner: 2 },
+                SimEvent::Dropped { hits: 17 },
+                SimEvent::Launched,
+                SimEvent::Returned { on_target: true },
+                SimEvent::Missed,
+            ],
+        }
+    }
+
+    /// One of every packet, with fields away from their defaults.
+    fn packets() -> Vec<Packet> {
+        let input = PlayerInput { left: true, jump: true, ..PlayerInput::default() };
+        vec![
+            Packet::Hello { version: PROTOCOL_VERSION },
+            Packet::Welcome { version: PROTOCOL_VERSION, speed: SpeedLevel::Fast, rules: training(), input_delay: 3 },
+            Packet::Inputs { ack: 40, start: 37, inputs: vec![input, PlayerInput::default(), PlayerInput { serve: true, right: true, ..input }] },
+            Packet::Announce {
+                version: PROTOCOL_VERSION,
+                id: 99,
+                port: 7878,
+                speed: SpeedLevel::Normal,
+                rules: MatchRules { mode: GameMode::KeepUp { over_net: true }, ..MatchRules::default() },
+                name: "REX".to_string(),
+            },
+            Packet::Connect { version: PROTOCOL_VERSION, spectate: true, token: 0x1234_5678 },
+            Packet::Accepted {
+                version: PROTOCOL_VERSION,
+                slot: Some(1),
+                arena: Arena { width: 1024.0, height: 600.0 },
+                speed: SpeedLevel::Slow,
+                rules: doubles(),
+                token: 0x1234_5678,
+            },
+            Packet::Accepted { version: PROTOCOL_VERSION, slot: None, arena: Arena::default(), speed: SpeedLevel::Normal, rules: training(), token: 1 },
+            Packet::Input { seq: 77, input },
+            Packet::Snapshot(Box::new(snapshot())),
+            Packet::Leave,
+        ]
+    }
+
+    #[test]
+    fn every_packet_survives_a_round_trip() {
+        for packet in packets() {
+            assert_eq!(Packet::decode(&packet.encode()), Some(packet.clone()), "{packet:?}");
+        }
+    }
+
+    #[test]
+    fn truncated_packets_are_rejected() {
+        for packet in packets() {
+            let bytes = packet.encode();
+            for len in 0..bytes.len() {
+                assert_eq!(Packet::decode(&bytes[..len]), None, "{packet:?} cut to {len} bytes");
+            }
+        }
+    }
+
+    #[test]
+    fn trailing_bytes_are_rejected() {
+        for packet in packets() {
+            let mut bytes = packet.encode();
+            bytes.push(0);
+            assert_eq!(Packet::decode(&bytes), None, "{packet:?}");
+        }
+    }
+
+    #[test]
+    fn rules_no_menu_could_set_up_are_rejected() {
+        let broken = [
+            MatchRules { team_size: 255, ..doubles() },
+            MatchRules { team_size: 0, ..doubles() },
+            MatchRules { winning_score: 0, ..doubles() },
+            MatchRules { sets: 0, ..doubles() },
+            MatchRules { sets: 4, ..doubles() },
+            MatchRules { sets: 11, ..doubles() },
+        ];
+        for rules in broken {
+            let packet = Packet::Accepted { version: PROTOCOL_VERSION, slot: Some(0), arena: Arena::default(), speed: SpeedLevel::default(), rules, token: 1 };
+            assert_eq!(Packet::decode(&packet.encode()), None, "{rules:?}");
+        }
+    }
+
+    #[test]
+    fn foreign_datagrams_are_rejected() {
+        let mut bytes = Packet::Leave.encode();
+        bytes[0] = b'X';
+        assert_eq!(Packet::decode(&bytes), None);
+        assert_eq!(Packet::decode(&[MAGIC[0], MAGIC[1], 200]), None);
+    }
+
+    #[test]
+    fn applying_a_snapshot_of_another_match_rebuilds_the_sim() {
+        let snapshot = snapshot();
+        let mut sim = MatchSim::default();
+        snapshot.apply(&snapshot, 0.0, &mut sim);
+        assert_eq!(sim.arena, snapshot.arena);
+        assert_eq!(sim.rules, snapshot.rules);
+        assert_eq!(sim.players.len(), 4);
+        assert_eq!(sim.players[1].pos, snapshot.players[1].pos);
+        assert_eq!(sim.state, snapshot.state);
+    }
+}
@@ -0,0 +1,205 @@
+use bevy::prelude::*;
+use crate::history::{format_date, MatchHistory};
+use crate::sim::Side;
+use crate::state;
+use crate::types::*;
+
+const RECENT_MATCHES: usize = 8;
+const PLAYER_ROWS: usize = 6;
+
+pub fn setup_records_menu(mut commands: Commands, history: Res<MatchHistory>) {
+    let totals = history.totals();
+    let label_color = Color::srgb(0.7, 0.7, 0.7);
+
+    commands.spawn((
+        Node {
+            width: Val::Percent(100.0),
+            height: Val::Percent(100.0),
+            align_items: AlignItems::Center,
+            justify_content: JustifyContent::Center,
+            flex_direction: FlexDirection::Column,
+            ..default()
+        },
+        BackgroundColor(Color::srgba(0.0, 0.0, 0.0, 0.85)),
+        RecordsUI,
+    )).with_children(|parent| {
+        parent.spawn((
+            Text::new("RECORDS"),
+            TextFont {
+                font_size: 48.0,
+                ..default()
+            },
+            TextColor(Color::WHITE),
+            Node {
+                margin: UiRect::bottom(Val::Px(12.0)),
+                ..default()
+            },
+        ));
+
+        if history.matches.is_empty() {
+            parent.spawn(text("NO MATCHES PLAYED YET", 22.0, label_color));
+        } else {
+            let lifetime = [
+                ("MATCHES", totals.matches.to_string()),
+                ("POINTS", totals.points.to_string()),
+                ("HITS", totals.hits.to_string()),
+                ("ACES", totals.aces.to_string()),
+                ("LONGEST RALLY", totals.longest_rally.to_string()),
+                ("AVERAGE RALLY", format!("{:.1}", totals.average_rally())),
+                ("POLE HITS", totals.pole_hits.to_string()),
+                ("WALL BOUNCES", totals.wall_bounces.to_string()),
+            ];
+            parent.spawn(Node {
+                flex_direction: FlexDirection::Row,
+                margin: UiRect::bottom(Val::Px(16.0)),
+                ..default()
+            }).with_children(|row| {
+                for (label, value) in lifetime {
+                    row.spawn(Node {
+                        flex_direction: FlexDirection::Column,
+                        align_items: AlignItems::Center,
+                        width: Val::Px(140.0),
+                        ..default()
+                    }).with_children(|column| {
+                        column.spawn(text(&value, 26.0, Color::WHITE));
+                        column.spawn(text(label, 16.0, label_color));
+                    });
+                }
+            });
+
+            parent.spawn(Node {
+                flex_direction: FlexDirection::Row,
+                align_items: AlignItems::FlexStart,
+                ..default()
+            }).with_children(|columns| {
+                columns.spawn(Node {
+                    flex_direction: FlexDirection::Column,
+                    width: Val::Px(360.0),
+                    margin: UiRect::horizontal(Val::Px(20.0)),
+                    ..default()
+                }).with_children(|column| {
+                    column.spawn(text("WIN / LOSS", 22.0, Color::WHITE));
+                    for (name, won, lost) in history.win_loss().into_iter().take(PLAYER_ROWS) {
+                        column.spawn(text(&format!("{name}  {won}-{lost}"), 20.0, label_color));
+                    }
+                });
+                columns.spawn(Node {
+                    flex_direction: FlexDirection::Column,
+                    width: Val::Px(620.0),
+                    margin: UiRect::horizontal(Val::Px(20.0)),
+                    ..default()
+                }).with_children(|column| {
+                    column.spawn(text("RECENT MATCHES", 22.0, Color::WHITE));
+                    for record in history.matches.iter().rev().take(RECENT_MATCHES) {
+                        let (left, right) = record.sets_won();
+                        let line = format!(
+                            "{}  {} VS {}  {}-{} ({})",
+                            format_date(record.finished_at),
+                            record.side_names(Side::Left),
+                            record.side_names(Side::Right),
+                            left,
+                            right,
+                            record.score_line(),
+                        );
+                        column.spawn(text(&line, 18.0, label_color));
+                    }
+                });
+            });
+        }
+
+        parent.spawn((
+            text("", 20.0, Color::srgb(1.0, 0.85, 0.2)),
+            Node {
+                margin: UiRect::vertical(Val::Px(16.0)),
+                ..default()
+            },
+            RecordsStatus,
+        ));
+
+        parent.spawn(Node {
+            flex_direction: FlexDirection::Row,
+            ..default()
+        }).with_children(|row| {
+            let buttons = [
+                ("EXPORT CSV", RecordsButton::ExportCsv),
+                ("EXPORT JSON", RecordsButton::ExportJson),
+                ("BACK", RecordsButton::Back),
+            ];
+            for (label, action) in buttons {
+                row.spawn((
+                    Button,
+                    Node {
+                        width: Val::Px(200.0),
+                        height: Val::Px(60.0),
+                        margin: UiRect::all(Val::Px(10.0)),
+                        justify_content: JustifyContent::Center,
+                        align_items: AlignItems::Center,
+                        ..default()
+                    },
+                    BackgroundColor(Color::srgb(0.2, 0.2, 0.2)),
+                    action,
+                )).with_children(|btn| {
+                    btn.spawn(text(label, 26.0, Color::WHITE));
+                });
+            }
+        });
+    });
+}
+
+fn text(value: &str, size: f32, color: Color) -> (Text, TextFont, TextColor) {
+    (
+        Text::new(value),
+        TextFont {
+            font_size: size,
+            ..default()
+        },
+        TextColor(color),
+    )
+}
+
+pub fn cleanup_records_menu(mut commands: Commands, records_ui: Query<Entity, With<RecordsUI>>) {
+    for entity in records_ui.iter() {
+        commands.entity(entity).despawn();
+    }
+}
+
+pub fn records_button_system(
+    mut interaction_query: Query<(&Interaction, &RecordsButton, &mut BackgroundColor), Changed<Interaction>>,
+    history: Res<MatchHistory>,
+    mut status_query: Query<&mut Text, With<RecordsStatus>>,
+    mut app_state: ResMut<NextState<state::AppMode>>,
+    menu_ui: Query<Entity, With<MenuUI>>,
+    mut commands: Commands,
+) {
+    for (interaction, action, mut color) in interaction_query.iter_mut() {
+        *color = match interaction {
+            Interaction::Pressed => BackgroundColor(Color::srgb(0.5, 0.5, 0.5)),
+            Interaction::Hovered => BackgroundColor(Color::srgb(0.4, 0.4, 0.4)),
+            Interaction::None => BackgroundColor(Color::srgb(0.2, 0.2, 0.2)),
+        };
+        if *interaction != Interaction::Pressed {
+            continue;
+        }
+        let exported = match action {
+            RecordsButton::ExportCsv => history.export_csv(),
+            RecordsButton::ExportJson => history.export_json(),
+            RecordsButton::Back => {
+                app_state.set(state::AppMode::Menu);
+                for entity in menu_ui.iter() {
+                    commands.entity(entity).insert(Visibility::Visible);
+                }
+                continue;
+            }
+        };
+        let message = match exported {
+            Ok(path) => format!("SAVED TO {}", path.display()),
+            Err(e) => {
+                eprintln!("Failed to export match history: {}", e);
+                "EXPORT FAILED".to_string()
+            }
+        };
+        for mut text in status_query.iter_mut() {
+            text.0 = message.clone();
+        }
+    }
+}
@@ -0,0 +1,225 @@
+use bevy::prelude::*;
+use std::collections::VecDeque;
+use std::net::{SocketAddr, ToSocketAddrs};
+use std::time::{Duration, Instant};
+use crate::config;
+use crate::netplay::Link;
+use crate::protocol::{Packet, Snapshot, PROTOCOL_VERSION};
+use crate::settings::NetSettings;
+use crate::sim::MatchSim;
+use crate::state;
+use crate::types::*;
+
+/// A match simulated by a dedicated server. Nothing is simulated locally: we send
+/// our buttons and draw the server's snapshots slightly in the past, blending between them.
+#[derive(Resource)]
+pub struct RemoteSession {
+    link: Link,
+    pub server: SocketAddr,
+    pub spectate: bool,
+    pub accepted: bool,
+    /// Player slot the server gave us, or `None` when watching.
+    pub slot: Option<usize>,
+    snapshots: VecDeque<Snapshot>,
+    /// Server tick currently on screen, fractional while between snapshots.
+    render_tick: Option<f64>,
+    /// Snapshots up to this tick have had their events played.
+    events_until: u32,
+    seq: u32,
+    last_heard: Instant,
+    last_connect: Option<Instant>,
+    /// Echoed back to the server so it knows snapshots reach us; 0 until it has answered.
+    token: u32,
+    pub waiting: bool,
+    pub spectators: u16,
+}
+
+impl RemoteSession {
+    pub fn connect(net: &NetSettings, spectate: bool) -> std::io::Result<Self> {
+        // A bare host name to watch means the usual spectator port.
+        let address = if spectate && !net.join_address.contains(':') {
+            format!("{}:{}", net.join_address, net.spectator_port)
+        } else {
+            net.join_address.clone()
+        };
+        let server = address.to_socket_addrs()?
+            .next()
+            .ok_or_else(|| std::io::Error::other("address did not resolve"))?;
+        Ok(Self {
+            link: Link::bind(0, net)?,
+            server,
+            spectate,
+            accepted: false,
+            slot: None,
+            snapshots: VecDeque::new(),
+            render_tick: None,
+            events_until: 0,
+            seq: 0,
+            last_heard: Instant::now(),
+            last_connect: None,
+            token: 0,
+            waiting: true,
+            spectators: 0,
+        })
+    }
+
+    /// Asks to join, and afterwards keeps reminding the server we're still here.
+    fn knock(&mut self) {
+        if self.last_connect.is_none_or(|sent| sent.elapsed() > Duration::from_secs_f64(config::NET_KEEPALIVE_SECONDS)) {
+            self.link.send(self.server, &Packet::Connect { version: PROTOCOL_VERSION, spectate: self.spectate, token: self.token });
+            self.last_connect = Some(Instant::now());
+        }
+        self.link.flush();
+    }
+}
+
+/// Waits on the online screen for the server to take us in.
+pub fn connect_system(
+    mut session: ResMut<RemoteSession>,
+    mut game: ResMut<state::Match>,
+    mut cpu_players: ResMut<state::CpuPlayers>,
+    mut status: ResMut<crate::online_menu::OnlineStatus>,
+    mut app_state: ResMut<NextState<state::AppMode>>,
+) {
+    session.knock();
+    while let Some((from, packet)) = session.link.recv() {
+        match packet {
+            _ if from != session.server => {}
+            Packet::Accepted { version, .. } if version != PROTOCOL_VERSION => {
+                status.0 = "SERVER USES AN INCOMPATIBLE VERSION".to_string();
+            }
+            Packet::Accepted { slot, arena, speed, rules, token, .. } => {
+                // Drawn on the server's court, whatever size our window is.
+                game.0 = MatchSim::new(arena, speed, rules);
+                cpu_players.controllers.clear();
+                session.accepted = true;
+                session.slot = slot.map(|slot| slot as usize);
+                session.last_heard = Instant::now();
+                // Snapshots only start once the server hears its token back, so knock again right away.
+                session.token = token;
+                session.last_connect = None;
+                app_state.set(state::AppMode::Playing);
+                return;
+            }
+            _ => {}
+        }
+    }
+}
+
+pub fn input_system(mut session: ResMut<RemoteSession>, inputs: Res<state::PlayerInputs>) {
+    session.knock();
+    if session.slot.is_none() {
+        return;
+    }
+    // Local player always uses the first player's bindings and gamepad.
+    let input = inputs.0.first().copied().unwrap_or_default();
+    session.seq += 1;
+    let packet = Packet::Input { seq: session.seq, input };
+    let server = session.server;
+    session.link.send(server, &packet);
+}
+
+/// Buffers incoming snapshots and puts the interpolated state into the match for drawing.
+#[allow(clippy::too_many_arguments)]
+pub fn snapshot_system(
+    time: Res<Time>,
+    mut session: ResMut<RemoteSession>,
+    mut game: ResMut<state::Match>,
+    mut game_events: MessageWriter<state::GameEvent>,
+    mut app_state: ResMut<NextState<state::AppMode>>,
+    menu_ui: Query<Entity, With<MenuUI>>,
+    win_ui: Query<Entity, With<WinUI>>,
+    mut commands: Commands,
+) {
+    let session = &mut *session;
+    while let Some((from, packet)) = session.link.recv() {
+        if from != session.server {
+            continue;
+        }
+        session.last_heard = Instant::now();
+        let Packet::Snapshot(snapshot) = packet else { continue };
+        session.waiting = snapshot.waiting;
+        session.spectators = snapshot.spectators;
+        // Keep the buffer ordered; late packets slot in, duplicates and stale ones are dropped.
+        let index = session.snapshots.partition_point(|known| known.tick < snapshot.tick);
+        if session.snapshots.get(index).is_some_and(|known| known.tick == snapshot.tick)
+            || snapshot.tick <= session.events_until
+        {
+            continue;
+        }
+        session.snapshots.insert(index, *snapshot);
+    }
+
+    if session.last_heard.elapsed().as_secs_f64() > config::NET_TIMEOUT_SECONDS {
+        eprintln!("Lost connection to server {}", session.server);
+        for entity in win_ui.iter() {
+            commands.entity(entity).despawn();
+        }
+        app_state.set(state::AppMode::Menu);
+        for entity in menu_ui.iter() {
+            commands.entity(entity).insert(Visibility::Visible);
+        }
+        return;
+    }
+
+    let Some(latest) = session.snapshots.back() else { return };
+    let delay = if session.spectate { config::SPECTATOR_DELAY } else { config::SNAPSHOT_INTERPOLATION_DELAY };
+    let target = latest.tick as f64 - delay * config::SIMULATION_HZ;
+    let advanced = session.render_tick.map_or(target, |tick| tick + time.delta_secs_f64() * config::SIMULATION_HZ);
+    // Ease towards the target so jitter doesn't show, but jump if we're way off.
+    let render_tick = if (advanced - target).abs() > config::SIMULATION_HZ * 0.25 {
+        target
+    } else {
+        advanced + (target - advanced) * 0.02
+    };
+    session.render_tick = Some(render_tick);
+
+    game.events.clear();
+    for snapshot in session.snapshots.iter().filter(|snapshot| snapshot.tick as f64 <= render_tick) {
+        if snapshot.tick > session.events_until {
+            game.events.extend_from_slice(&snapshot.events);
+            session.events_until = snapshot.tick;
+        }
+    }
+    game_events.write_batch(game.events.iter().copied().map(state::GameEvent));
+
+    while session.snapshots.len() >= 2 && session.snapshots[1].tick as f64 <= render_tick {
+        session.snapshots.pop_front();
+    }
+    let from = &session.snapshots[0];
+    let to = session.snapshots.get(1).unwrap_or(from);
+    let t = if to.tick > from.tick {
+        ((render_tick - from.tick as f64) / (to.tick - from.tick) as f64).clamp(0.0, 1.0) as f32
+    } else {
+        0.0
+    };
+    from.apply(to, t, &mut game);
+
+    // The players started another match; spectators follow along.
+    if !game.state.game_over {
+        for entity in win_ui.iter() {
+            commands.entity(entity).despawn();
+        }
+    }
+}
+
+/// Explains why nothing is moving while the server waits for a second player.
+pub fn status_system(
+    session: Res<RemoteSession>,
+    game: Res<state::Match>,
+    mut status_query: Query<&mut Text2d, With<StatusText>>,
+) {
+    if !session.waiting || game.state.game_over {
+        return;
+    }
+    for mut text in status_query.iter_mut() {
+        text.0 = "WAITING FOR PLAYERS".to_string();
+    }
+}
+
+pub fn end_session(mut commands: Commands, session: Option<Res<RemoteSession>>) {
+    let Some(session) = session else { return };
+    // The session is gone before a delayed packet would be flushed.
+    session.link.send_now(session.server, &Packet::Leave);
+    commands.remove_resource::<RemoteSession>();
+}
@@ -0,0 +1,412 @@
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use crate::config::{self, SpeedLevel};
+use crate::sim::{Arena, MatchRules, MatchSim, PlayerInput};
+use crate::state;
+use crate::types::*;
+use crate::util;
+
+const REPLAY_DIR: &str = "replays";
+
+/// Bump when the file layout changes; older builds refuse newer files.
+pub const REPLAY_VERSION: u32 = 1;
+
+/// Consecutive ticks that share the same inputs and arena.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct InputRun {
+    pub ticks: u32,
+    /// One `PlayerInput::to_bits` value per player slot.
+    pub inputs: Vec<u8>,
+    pub arena: Arena,
+}
+
+/// Everything needed to re-simulate a match tick by tick.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Replay {
+    pub version: u32,
+    /// Seed the match's CPU players were created with.
+    pub seed: u32,
+    pub speed: SpeedLevel,
+    pub rules: MatchRules,
+    /// Arena the match started in; later resizes are stored per run.
+    pub arena: Arena,
+    pub runs: Vec<InputRun>,
+}
+
+impl Replay {
+    pub fn new(sim: &MatchSim, seed: u32) -> Self {
+        Self {
+            version: REPLAY_VERSION,
+            seed,
+            speed: sim.speed,
+            rules: sim.rules,
+            arena: sim.arena,
+            runs: Vec::new(),
+        }
+    }
+
+    pub fn push(&mut self, inputs: &[PlayerInput], arena: Arena) {
+        let inputs: Vec<u8> = inputs.iter().map(|input| input.to_bits()).collect();
+        match self.runs.last_mut() {
+            Some(run) if run.inputs == inputs && run.arena == arena => run.ticks += 1,
+            _ => self.runs.push(InputRun { ticks: 1, inputs, arena }),
+        }
+    }
+
+    pub fn tick_count(&self) -> usize {
+        self.runs.iter().map(|run| run.ticks as usize).sum()
+    }
+
+    /// A fresh simulation in the state the recorded match started from.
+    pub fn start_sim(&self) -> MatchSim {
+        MatchSim::new(self.arena, self.speed, self.rules)
+    }
+
+    pub fn save(&self) -> std::io::Result<PathBuf> {
+        let dir = util::data_path(REPLAY_DIR).ok_or_else(|| std::io::Error::other("no data directory"))?;
+        std::fs::create_dir_all(&dir)?;
+        let stamp = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.as_millis())
+            .unwrap_or(0);
+        let path = dir.join(format!("replay-{stamp}.ron"));
+        let text = ron::ser::to_string(self).map_err(std::io::Error::other)?;
+        std::fs::write(&path, text)?;
+        Ok(path)
+    }
+
+    pub fn load(path: &Path) -> std::io::Result<Self> {
+        let text = std::fs::read_to_string(path)?;
+        let replay: Self = ron::from_str(&text).map_err(std::io::Error::other)?;
+        if replay.version > REPLAY_VERSION {
+            return Err(std::io::Error::other(format!("unsupported replay version {}", replay.version)));
+        }
+        Ok(replay)
+    }
+
+    /// Most recently saved replay file, if any.
+    pub fn latest() -> Option<PathBuf> {
+        let dir = util::data_path(REPLAY_DIR)?;
+        std::fs::read_dir(dir).ok()?
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .filter(|path| path.extension().is_some_and(|ext| ext == "ron"))
+            .max()
+    }
+}
+
+/// Replay of the match in progress, written out when it ends.
+#[derive(Resource, Default)]
+pub struct Recorder {
+    replay: Option<Replay>,
+    seed: u32,
+}
+
+impl Recorder {
+    pub fn start(&mut self, sim: &MatchSim, seed: u32) {
+        self.seed = seed;
+        self.replay = Some(Replay::new(sim, seed));
+    }
+
+    /// Saves the current recording and starts a new one for a restarted match.
+    pub fn restart(&mut self, sim: &MatchSim) {
+        self.finish();
+        self.start(sim, self.seed);
+    }
+
+    pub fn finish(&mut self) {
+        let Some(replay) = self.replay.take() else { return };
+        if replay.runs.is_empty() {
+            return;
+        }
+        if let Err(e) = replay.save() {
+            eprintln!("Failed to save replay: {}", e);
+        }
+    }
+}
+
+/// Runs after the simulation step so the stored arena is the one that step used.
+pub fn record_tick(
+    game: Res<state::Match>,
+    inputs: Res<state::PlayerInputs>,
+    mut recorder: ResMut<Recorder>,
+) {
+    let Some(replay) = recorder.replay.as_mut() else { return };
+    replay.push(&inputs.0, game.arena);
+    if game.state.game_over {
+        recorder.finish();
+    }
+}
+
+pub fn finish_recording(mut recorder: ResMut<Recorder>) {
+    recorder.finish();
+}
+
+/// A loaded replay being watched, expanded to one entry per tick for seeking.
+#[derive(Resource)]
+pub struct Playback {
+    replay: Replay,
+    frames: Vec<(Arena, Vec<PlayerInput>)>,
+    pub tick: usize,
+    pub paused: bool,
+    pub speed_index: usize,
+}
+
+impl Playback {
+    pub fn new(replay: Replay) -> Self {
+        let mut frames = Vec::with_capacity(replay.tick_count());
+        for run in &replay.runs {
+            let inputs: Vec<PlayerInput> = run.inputs.iter().map(|bits| PlayerInput::from_bits(*bits)).collect();
+            for _ in 0..run.ticks {
+                frames.push((run.arena, inputs.clone()));
+            }
+        }
+        Self {
+            replay,
+            frames,
+            tick: 0,
+            paused: false,
+            speed_index: config::REPLAY_SPEEDS.iter().position(|speed| *speed == 1.0).unwrap_or(0),
+        }
+    }
+
+    fn step(&mut self, sim: &mut MatchSim, dt: f32) -> bool {
+        let Some((arena, inputs)) = self.frames.get(self.tick) else { return false };
+        sim.arena = *arena;
+        sim.step(inputs, dt);
+        self.tick += 1;
+        true
+    }
+
+    /// Re-simulates from the closest point to land exactly on `target`.
+    fn seek(&mut self, sim: &mut MatchSim, target: usize, dt: f32) {
+        let target = target.min(self.frames.len());
+        if target < self.tick {
+            *sim = self.replay.start_sim();
+            self.tick = 0;
+        }
+        while self.tick < target {
+            self.step(sim, dt);
+        }
+    }
+}
+
+pub fn setup_playback(
+    mut commands: Commands,
+    playback: Res<Playback>,
+    mut game: ResMut<state::Match>,
+) {
+    game.0 = playback.replay.start_sim();
+
+    commands.spawn((
+        Node {
+            width: Val::Percent(100.0),
+            position_type: PositionType::Absolute,
+            bottom: Val::Px(20.0),
+            align_items: AlignItems::Center,
+            justify_content: JustifyContent::Center,
+            flex_direction: FlexDirection::Column,
+            ..default()
+        },
+        GlobalZIndex(10),
+        ReplayUI,
+    )).with_children(|parent| {
+        parent.spawn((
+            Text::new(""),
+            TextFont {
+                font_size: 28.0,
+                ..default()
+            },
+            TextColor(Color::WHITE),
+            Node {
+                margin: UiRect::bottom(Val::Px(8.0)),
+                ..default()
+            },
+            ReplayText,
+        ));
+
+        parent.spawn(Node {
+            flex_direction: FlexDirection::Row,
+            justify_content: JustifyContent::Center,
+            align_items: AlignItems::Center,
+            ..default()
+        }).with_children(|row| {
+            let buttons = [
+                ("<<", ReplayButton::SeekBack),
+                ("PLAY", ReplayButton::TogglePause),
+                (">>", ReplayButton::SeekForward),
+                ("SLOWER", ReplayButton::Slower),
+                ("FASTER", ReplayButton::Faster),
+                ("EXIT", ReplayButton::Exit),
+            ];
+            for (label, action) in buttons {
+                row.spawn((
+                    Button,
+                    Node {
+                        width: Val::Px(110.0),
+                        height: Val::Px(50.0),
+                        margin: UiRect::all(Val::Px(6.0)),
+                        justify_content: JustifyContent::Center,
+                        align_items: AlignItems::Center,
+                        ..default()
+                    },
+                    BackgroundColor(Color::srgb(0.2, 0.2, 0.2)),
+                    action,
+                )).with_children(|btn| {
+                    btn.spawn((
+                        Text::new(label),
+                        TextFont {
+                            font_size: 22.0,
+                            ..default()
+                        },
+                        TextColor(Color::WHITE),
+                    ));
+                });
+            }
+        });
+    });
+}
+
+pub fn cleanup_playback(
+    mut commands: Commands,
+    replay_ui: Query<Entity, With<ReplayUI>>,
+    mut game: ResMut<state::Match>,
+    mut time: ResMut<Time<Virtual>>,
+) {
+    for entity in replay_ui.iter() {
+        commands.entity(entity).despawn();
+    }
+    commands.remove_resource::<Playback>();
+    game.reset();
+    time.unpause();
+    time.set_relative_speed(1.0);
+}
+
+/// Steps the replayed match through the same `MatchSim::step` the live game uses.
+pub fn playback_tick(
+    time: Res<Time>,
+    mut playback: ResMut<Playback>,
+    mut game: ResMut<state::Match>,
+    mut game_events: MessageWriter<state::GameEvent>,
+) {
+    if playback.paused {
+        return;
+    }
+    if playback.step(&mut game, time.delta_secs()) {
+        game_events.write_batch(game.events.iter().copied().map(state::GameEvent));
+    } else {
+        playback.paused = true;
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn playback_controls(
+    keyboard: Res<ButtonInput<KeyCode>>,
+    mut interaction_query: Query<(&Interaction, &mut BackgroundColor, &ReplayButton), Changed<Interaction>>,
+    mut playback: ResMut<Playback>,
+    mut game: ResMut<state::Match>,
+    fixed_time: Res<Time<Fixed>>,
+    mut time: ResMut<Time<Virtual>>,
+    mut app_state: ResMut<NextState<state::AppMode>>,
+    menu_ui: Query<Entity, With<MenuUI>>,
+    mut commands: Commands,
+) {
+    let mut action = None;
+    for (interaction, mut color, button) in interaction_query.iter_mut() {
+        match *interaction {
+            Interaction::Pressed => action = Some(*button),
+            Interaction::Hovered => *color = BackgroundColor(Color::srgb(0.4, 0.4, 0.4)),
+            Interaction::None => *color = BackgroundColor(Color::srgb(0.2, 0.2, 0.2)),
+        }
+    }
+    let shortcuts = [
+        (KeyCode::ArrowLeft, ReplayButton::SeekBack),
+        (KeyCode::Space, ReplayButton::TogglePause),
+        (KeyCode::ArrowRight, ReplayButton::SeekForward),
+        (KeyCode::ArrowDown, ReplayButton::Slower),
+        (KeyCode::ArrowUp, ReplayButton::Faster),
+        (KeyCode::Escape, ReplayButton::Exit),
+    ];
+    for (key, button) in shortcuts {
+        if keyboard.just_pressed(key) {
+            action = Some(button);
+        }
+    }
+
+    let dt = fixed_time.timestep().as_secs_f32();
+    let seek_ticks = (config::REPLAY_SEEK_SECONDS * config::SIMULATION_HZ) as usize;
+    match action {
+        Some(ReplayButton::SeekBack) => {
+            let target = playback.tick.saturating_sub(seek_ticks);
+            playback.seek(&mut game, target, dt);
+        }
+        Some(ReplayButton::TogglePause) => {
+            if playback.tick >= playback.frames.len() {
+                playback.seek(&mut game, 0, dt);
+                playback.paused = false;
+            } else {
+                playback.paused = !playback.paused;
+            }
+        }
+        Some(ReplayButton::SeekForward) => {
+            let target = playback.tick + seek_ticks;
+            playback.seek(&mut game, target, dt);
+        }
+        Some(ReplayButton::Slower) => playback.speed_index = playback.speed_index.saturating_sub(1),
+        Some(ReplayButton::Faster) => {
+            playback.speed_index = (playback.speed_index + 1).min(config::REPLAY_SPEEDS.len() - 1);
+        }
+        Some(ReplayButton::Exit) => {
+            app_state.set(state::AppMode::Menu);
+            for entity in menu_ui.iter() {
+                commands.entity(entity).insert(Visibility::Visible);
+            }
+        }
+        None => {}
+    }
+
+    // Virtual time drives the fixed loop, so scaling it keeps rendering interpolation intact.
+    if playback.paused {
+        time.pause();
+    } else {
+        time.unpause();
+    }
+    time.set_relative_speed(config::REPLAY_SPEEDS[playback.speed_index]);
+}
+
+pub fn replay_text_system(
+    playback: Res<Playback>,
+    mut text_query: Query<&mut Text, With<ReplayText>>,
+    button_text: Query<(&ReplayButton, &Children)>,
+    mut texts: Query<&mut Text, Without<ReplayText>>,
+) {
+    let seconds = |ticks: usize| {
+        let total = (ticks as f64 / config::SIMULATION_HZ) as u32;
+        format!("{}:{:02}", total / 60, total % 60)
+    };
+    let label = format!(
+        "REPLAY  {}x  {} / {}",
+        config::REPLAY_SPEEDS[playback.speed_index],
+        seconds(playback.tick),
+        seconds(playback.frames.len()),
+    );
+    for mut text in text_query.iter_mut() {
+        if text.0 != label {
+            text.0 = label.clone();
+        }
+    }
+
+    let toggle_label = if playback.paused { "PLAY" } else { "PAUSE" };
+    for (button, children) in button_text.iter() {
+        if *button != ReplayButton::TogglePause {
+            continue;
+        }
+        for child in children.iter() {
+            if let Ok(mut text) = texts.get_mut(child)
+                && text.0 != toggle_label
+            {
+                text.0 = toggle_label.to_string();
+            }
+        }
+    }
+}
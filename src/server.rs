@@ -0,0 +1,283 @@
+use std::hash::BuildHasher;
+use std::net::{SocketAddr, UdpSocket};
+use std::time::Instant;
+use crate::config;
+use crate::protocol::{Packet, Snapshot, PROTOCOL_VERSION};
+use crate::sim::{MatchSim, PlayerInput, SimEvent};
+
+struct Client {
+    addr: SocketAddr,
+    /// Player slot, or `None` for a spectator.
+    slot: Option<usize>,
+    last_heard: Instant,
+    last_seq: Option<u32>,
+    /// Sent our token back, so the address is really theirs and can be sent snapshots.
+    confirmed: bool,
+}
+
+/// Keeps track of who is connected to a match and streams snapshots to them.
+///
+/// The server owns the only copy of the truth: clients just send the buttons they
+/// hold and draw what comes back, so there is nothing on their side to tamper with.
+pub struct Server {
+    socket: UdpSocket,
+    clients: Vec<Client>,
+    inputs: Vec<PlayerInput>,
+    tick: u32,
+    events: Vec<SimEvent>,
+    last_broadcast: Option<Instant>,
+    /// Randomly keyed per run, so tokens can't be guessed without seeing our answers.
+    tokens: std::hash::RandomState,
+}
+
+impl Server {
+    /// `player_slots` is how many clients may play; everyone else watches.
+    pub fn bind(port: u16, player_slots: usize) -> std::io::Result<Self> {
+        let socket = UdpSocket::bind(("0.0.0.0", port))?;
+        socket.set_nonblocking(true)?;
+        Ok(Self {
+            socket,
+            clients: Vec::new(),
+            inputs: vec![PlayerInput::default(); player_slots],
+            tick: 0,
+            events: Vec::new(),
+            last_broadcast: None,
+            tokens: std::hash::RandomState::new(),
+        })
+    }
+
+    pub fn port(&self) -> Option<u16> {
+        self.socket.local_addr().ok().map(|addr| addr.port())
+    }
+
+    /// Latest buttons from every player slot; empty slots hold nothing.
+    pub fn inputs(&self) -> &[PlayerInput] {
+        &self.inputs
+    }
+
+    pub fn players_connected(&self) -> usize {
+        self.clients.iter().filter(|client| client.slot.is_some()).count()
+    }
+
+    pub fn spectators(&self) -> usize {
+        self.clients.iter().filter(|client| client.slot.is_none()).count()
+    }
+
+    /// Every player slot is taken, so the match can run.
+    pub fn is_full(&self) -> bool {
+        self.players_connected() == self.inputs.len()
+    }
+
+    /// Handles everything clients sent since the last call and forgets the silent ones.
+    pub fn poll(&mut self, sim: &MatchSim) {
+        let mut buf = [0u8; 1500];
+        loop {
+            let (len, from) = match self.socket.recv_from(&mut buf) {
+                Ok(received) => received,
+                Err(e) if e.kind() == std::io::ErrorKind::WouldBlock => break,
+                // Windows reports an unreachable client on the next receive.
+                Err(e) if e.kind() == std::io::ErrorKind::ConnectionReset => continue,
+                Err(e) => {
+                    eprintln!("Server receive failed: {}", e);
+                    break;
+                }
+            };
+            let Some(packet) = Packet::decode(&buf[..len]) else { continue };
+            self.handle(from, packet, sim);
+        }
+
+        let now = Instant::now();
+        let (alive, gone): (Vec<_>, Vec<_>) = std::mem::take(&mut self.clients)
+            .into_iter()
+            .partition(|client| now.duration_since(client.last_heard).as_secs_f64() < config::NET_TIMEOUT_SECONDS);
+        self.clients = alive;
+        for client in gone {
+            println!("{} timed out", client.addr);
+            self.release(client.slot);
+        }
+    }
+
+    fn handle(&mut self, from: SocketAddr, packet: Packet, sim: &MatchSim) {
+        let known = self.clients.iter().position(|client| client.addr == from);
+        match packet {
+            Packet::Connect { version, .. } if version != PROTOCOL_VERSION => {
+                self.send(from, &self.accepted(from, None, sim).encode());
+            }
+            Packet::Connect { spectate, token, .. } => {
+                let index = match known {
+                    Some(index) => index,
+                    None => {
+                        let free = (0..self.inputs.len()).find(|slot| self.clients.iter().all(|client| client.slot != Some(*slot)));
+                        let slot = if spectate { None } else { free };
+                        if slot.is_none() && self.spectators() >= config::MAX_SPECTATORS {
+                            return;
+                        }
+                        match slot {
+                            Some(slot) => println!("{} joined as player {}", from, slot + 1),
+                            None => println!("{} is watching", from),
+                        }
+                        self.clients.push(Client { addr: from, slot, last_heard: Instant::now(), last_seq: None, confirmed: false });
+                        self.clients.len() - 1
+                    }
+                };
+                let confirmed = token == self.token(from);
+                let client = &mut self.clients[index];
+                client.last_heard = Instant::now();
+                client.confirmed |= confirmed;
+                // Answer every knock; the previous answer may have been lost.
+                self.send(from, &self.accepted(from, self.clients[index].slot, sim).encode());
+            }
+            Packet::Input { seq, input } => {
+                let Some(client) = known.map(|index| &mut self.clients[index]) else { return };
+                client.last_heard = Instant::now();
+                // Spectators don't get to steer, and reordered packets are older than what we have.
+                let Some(slot) = client.slot else { return };
+                if client.last_seq.is_some_and(|last| seq <= last) {
+                    return;
+                }
+                client.last_seq = Some(seq);
+                self.inputs[slot] = input;
+            }
+            Packet::Leave => {
+                if let Some(index) = known {
+                    let client = self.clients.remove(index);
+                    println!("{} left", client.addr);
+                    self.release(client.slot);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn release(&mut self, slot: Option<usize>) {
+        if let Some(slot) = slot {
+            self.inputs[slot] = PlayerInput::default();
+        }
+    }
+
+    /// Token `addr` has to echo before it gets snapshots. Never 0, which clients send before they have one.
+    fn token(&self, addr: SocketAddr) -> u32 {
+        self.tokens.hash_one(addr) as u32 | 1
+    }
+
+    fn accepted(&self, to: SocketAddr, slot: Option<usize>, sim: &MatchSim) -> Packet {
+        Packet::Accepted {
+            version: PROTOCOL_VERSION,
+            slot: slot.map(|slot| slot as u8),
+            arena: sim.arena,
+            speed: sim.speed,
+            rules: sim.rules,
+            token: self.token(to),
+        }
+    }
+
+    fn send(&self, to: SocketAddr, bytes: &[u8]) {
+        if let Err(e) = self.socket.send_to(bytes, to)
+            && e.kind() != std::io::ErrorKind::WouldBlock
+        {
+            eprintln!("Failed to send packet to {}: {}", to, e);
+        }
+    }
+
+    /// Call once per simulation tick, after stepping `sim` (or not, while waiting for players).
+    /// Every few ticks the state goes out to all clients along with the events since the last one.
+    pub fn end_tick(&mut self, sim: &MatchSim, stepped: bool) {
+        if stepped {
+            self.events.extend_from_slice(&sim.events);
+        }
+        self.tick = self.tick.wrapping_add(1);
+        if self.clients.is_empty() {
+            self.events.clear();
+            return;
+        }
+        if self.tick.is_multiple_of(config::SNAPSHOT_INTERVAL_TICKS) {
+            let events = std::mem::take(&mut self.events);
+            self.broadcast(sim, events);
+        }
+    }
+
+    /// Resends the current state if no tick has gone out for a while, e.g. while the
+    /// match is paused, so clients don't think we've disappeared.
+    pub fn keep_alive(&mut self, sim: &MatchSim) {
+        let idle = self.last_broadcast.is_none_or(|sent| sent.elapsed().as_secs_f64() > config::NET_KEEPALIVE_SECONDS);
+        if idle && !self.clients.is_empty() {
+            self.broadcast(sim, Vec::new());
+        }
+    }
+
+    fn broadcast(&mut self, sim: &MatchSim, events: Vec<SimEvent>) {
+        let mut snapshot = Snapshot::capture(sim, self.tick, events);
+        snapshot.waiting = !self.is_full();
+        snapshot.spectators = self.spectators().min(u16::MAX as usize) as u16;
+        let bytes = Packet::Snapshot(Box::new(snapshot)).encode();
+        // A spoofed `Connect` never sees its token, so it can't turn us into an amplifier.
+        for client in self.clients.iter().filter(|client| client.confirmed) {
+            self.send(client.addr, &bytes);
+        }
+        self.last_broadcast = Some(Instant::now());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    fn client() -> UdpSocket {
+        let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+        socket.set_read_timeout(Some(Duration::from_millis(100))).unwrap();
+        socket
+    }
+
+    fn connect(socket: &UdpSocket, server: &mut Server, sim: &MatchSim, token: u32) {
+        let port = server.port().unwrap();
+        let packet = Packet::Connect { version: PROTOCOL_VERSION, spectate: true, token };
+        socket.send_to(&packet.encode(), ("127.0.0.1", port)).unwrap();
+        std::thread::sleep(Duration::from_millis(20));
+        server.poll(sim);
+    }
+
+    /// Everything that arrived until the socket went quiet.
+    fn received(socket: &UdpSocket) -> Vec<Packet> {
+        let mut buf = [0u8; 1500];
+        let mut packets = Vec::new();
+        while let Ok(len) = socket.recv(&mut buf) {
+            packets.extend(Packet::decode(&buf[..len]));
+        }
+        packets
+    }
+
+    fn broadcast_tick(server: &mut Server, sim: &MatchSim) {
+        for _ in 0..config::SNAPSHOT_INTERVAL_TICKS {
+            server.end_tick(sim, false);
+        }
+    }
+
+    #[test]
+    fn snapshots_wait_for_the_token_to_come_back() {
+        let sim = MatchSim::default();
+        let mut server = Server::bind(0, 2).unwrap();
+        let socket = client();
+
+        connect(&socket, &mut server, &sim, 0);
+        broadcast_tick(&mut server, &sim);
+        let packets = received(&socket);
+        let [Packet::Accepted { token, .. }] = packets.as_slice() else { panic!("expected only an answer, got {packets:?}") };
+
+        connect(&socket, &mut server, &sim, *token);
+        broadcast_tick(&mut server, &sim);
+        assert!(received(&socket).iter().any(|packet| matches!(packet, Packet::Snapshot(_))));
+    }
+
+    #[test]
+    fn spectators_are_capped() {
+        let sim = MatchSim::default();
+        let mut server = Server::bind(0, 0).unwrap();
+        let sockets: Vec<UdpSocket> = (0..=config::MAX_SPECTATORS).map(|_| client()).collect();
+        for socket in &sockets {
+            connect(socket, &mut server, &sim, 0);
+        }
+        assert_eq!(server.spectators(), config::MAX_SPECTATORS);
+        assert!(received(&sockets[config::MAX_SPECTATORS]).is_empty());
+    }
+}
@@ -0,0 +1,280 @@
+use bevy::prelude::*;
+use bevy::window::{MonitorSelection, PrimaryWindow, WindowMode};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use crate::bindings::Bindings;
+use crate::config;
+use crate::sim::{Drill, MatchRules};
+use crate::util;
+
+const SETTINGS_FILE: &str = "settings.toml";
+
+/// Bump when the file layout changes and add a step to `migrate`.
+pub const SETTINGS_VERSION: u32 = 1;
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum WindowModeSetting {
+    #[default]
+    Windowed,
+    Fullscreen,
+}
+
+impl WindowModeSetting {
+    pub fn label(self) -> &'static str {
+        match self {
+            WindowModeSetting::Windowed => "WINDOWED",
+            WindowModeSetting::Fullscreen => "FULLSCREEN",
+        }
+    }
+
+    pub fn toggled(self) -> Self {
+        match self {
+            WindowModeSetting::Windowed => WindowModeSetting::Fullscreen,
+            WindowModeSetting::Fullscreen => WindowModeSetting::Windowed,
+        }
+    }
+
+    pub fn window_mode(self) -> WindowMode {
+        match self {
+            WindowModeSetting::Windowed => WindowMode::Windowed,
+            WindowModeSetting::Fullscreen => WindowMode::BorderlessFullscreen(MonitorSelection::Current),
+        }
+    }
+}
+
+/// Online play options. The simulated latency and loss apply to outgoing packets for testing on localhost.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(default)]
+pub struct NetSettings {
+    /// Shown to other players when hosting on the local network, unless player 1 has a named profile.
+    pub player_name: String,
+    pub port: u16,
+    pub join_address: String,
+    /// Frames between pressing a button and it taking effect, hiding network latency.
+    pub input_delay: u32,
+    pub sim_latency_ms: u32,
+    pub sim_loss_percent: u32,
+    /// Let other instances watch matches played here.
+    pub spectators: bool,
+    pub spectator_port: u16,
+}
+
+impl Default for NetSettings {
+    fn default() -> Self {
+        let user = std::env::var("USER").or_else(|_| std::env::var("USERNAME"));
+        Self {
+            player_name: user.map(|name| name.to_uppercase()).unwrap_or_else(|_| "PLAYER".to_string()),
+            port: config::NET_DEFAULT_PORT,
+            join_address: format!("127.0.0.1:{}", config::NET_DEFAULT_PORT),
+            input_delay: 2,
+            sim_latency_ms: 0,
+            sim_loss_percent: 0,
+            spectators: false,
+            spectator_port: config::SPECTATOR_DEFAULT_PORT,
+        }
+    }
+}
+
+/// Everything the player can configure, saved to `settings.toml` whenever it changes.
+#[derive(Resource, Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(default)]
+pub struct Settings {
+    pub version: u32,
+    pub speed: config::SpeedLevel,
+    pub winning_score: u32,
+    pub win_by_two: bool,
+    pub touch_rules: bool,
+    pub sets: u32,
+    /// Keep-up practice makes every hit cross the net.
+    pub keep_up_over_net: bool,
+    /// Launcher setup last used on the training screen.
+    pub training: Drill,
+    /// Hue shift each player slot's dino is drawn with, unless their profile picks one.
+    pub dino_hues: Vec<f32>,
+    pub master_volume: f32,
+    pub sfx_volume: f32,
+    pub window_mode: WindowModeSetting,
+    pub controls: Bindings,
+    pub net: NetSettings,
+    /// The file on disk couldn't be read as-is, so saving would lose something in it.
+    #[serde(skip)]
+    keep_file: bool,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            version: SETTINGS_VERSION,
+            speed: config::SpeedLevel::default(),
+            winning_score: config::WINNING_SCORE,
+            win_by_two: false,
+            touch_rules: false,
+            sets: 1,
+            keep_up_over_net: false,
+            training: Drill::default(),
+            dino_hues: config::PLAYER_HUE_SHIFTS.to_vec(),
+            master_volume: 0.8,
+            sfx_volume: 1.0,
+            window_mode: WindowModeSetting::default(),
+            controls: Bindings::default(),
+            net: NetSettings::default(),
+            keep_file: false,
+        }
+    }
+}
+
+impl Settings {
+    pub fn match_rules(&self) -> MatchRules {
+        MatchRules {
+            winning_score: self.winning_score,
+            win_by_two: self.win_by_two,
+            touch_rules: self.touch_rules,
+            sets: self.sets,
+            ..MatchRules::default()
+        }
+    }
+
+    pub fn load() -> Self {
+        util::config_path(SETTINGS_FILE).map_or_else(Self::default, |path| Self::load_from(&path))
+    }
+
+    fn load_from(path: &Path) -> Self {
+        let Ok(text) = std::fs::read_to_string(path) else { return Self::default() };
+        let loaded = text.parse::<toml::Table>()
+            .map_err(|e| e.to_string())
+            .and_then(|table| migrate(table).try_into::<Settings>().map_err(|e| e.to_string()));
+        match loaded {
+            Ok(mut settings) => {
+                if settings.version > SETTINGS_VERSION {
+                    eprintln!("Settings file {:?} is from a newer version ({}), so changes won't be saved over it", path, settings.version);
+                    settings.keep_file = true;
+                }
+                settings.controls.fill_missing();
+                let start = settings.dino_hues.len().min(config::MAX_PLAYERS);
+                settings.dino_hues.extend_from_slice(&config::PLAYER_HUE_SHIFTS[start..]);
+                settings
+            }
+            Err(e) => {
+                // Start from the defaults, but keep the player's file somewhere the next save won't touch.
+                let backup = path.with_extension("toml.bak");
+                let keep_file = match std::fs::copy(path, &backup) {
+                    Ok(_) => {
+                        eprintln!("Failed to read settings from {:?}: {}; kept a copy at {:?}", path, e, backup);
+                        false
+                    }
+                    Err(copy_error) => {
+                        eprintln!("Failed to read settings from {:?}: {}; couldn't back it up ({}), so it won't be saved over", path, e, copy_error);
+                        true
+                    }
+                };
+                Self { keep_file, ..Self::default() }
+            }
+        }
+    }
+
+    pub fn save(&self) {
+        if let Some(path) = util::config_path(SETTINGS_FILE) {
+            self.save_to(&path);
+        }
+    }
+
+    fn save_to(&self, path: &Path) {
+        if self.keep_file {
+            return;
+        }
+        let result = toml::to_string_pretty(self)
+            .map_err(std::io::Error::other)
+            .and_then(|text| {
+                if let Some(dir) = path.parent() {
+                    std::fs::create_dir_all(dir)?;
+                }
+                std::fs::write(path, text)
+            });
+        if let Err(e) = result {
+            eprintln!("Failed to save settings to {:?}: {}", path, e);
+        }
+    }
+}
+
+/// Upgrades a settings file written by an older release to the current layout.
+/// Files from newer releases keep their version number.
+fn migrate(mut table: toml::Table) -> toml::Table {
+    let version = table.get("version").and_then(|v| v.as_integer()).unwrap_or(0);
+    // Files without a version number predate versioning but share the version 1 layout.
+    // Later layout changes add steps here, e.g. `if version < 2 { ... }`.
+    if version < SETTINGS_VERSION as i64 {
+        table.insert("version".to_string(), toml::Value::Integer(SETTINGS_VERSION as i64));
+    }
+    table
+}
+
+pub fn save_settings(settings: Res<Settings>) {
+    if settings.is_changed() && !settings.is_added() {
+        settings.save();
+    }
+}
+
+pub fn apply_window_mode(
+    settings: Res<Settings>,
+    mut windows: Query<&mut Window, With<PrimaryWindow>>,
+) {
+    if !settings.is_changed() {
+        return;
+    }
+    for mut window in windows.iter_mut() {
+        let mode = settings.window_mode.window_mode();
+        if window.mode != mode {
+            window.mode = mode;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    /// A fresh settings path in its own temporary directory.
+    fn scratch(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("dino-ball-settings-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        dir.join(SETTINGS_FILE)
+    }
+
+    #[test]
+    fn a_broken_file_is_backed_up_before_it_is_replaced() {
+        let path = scratch("broken");
+        std::fs::write(&path, "speed = \"Warp\"\n").unwrap();
+        let settings = Settings::load_from(&path);
+        assert_eq!(settings, Settings::default());
+        assert_eq!(std::fs::read_to_string(path.with_extension("toml.bak")).unwrap(), "speed = \"Warp\"\n");
+
+        settings.save_to(&path);
+        assert_eq!(Settings::load_from(&path), settings);
+    }
+
+    #[test]
+    fn a_newer_file_keeps_its_version_and_is_not_saved_over() {
+        let path = scratch("newer");
+        let text = format!("version = {}\nsets = 3\nreplay_quality = \"high\"\n", SETTINGS_VERSION + 1);
+        std::fs::write(&path, &text).unwrap();
+        let mut settings = Settings::load_from(&path);
+        assert_eq!(settings.version, SETTINGS_VERSION + 1);
+        assert_eq!(settings.sets, 3);
+
+        settings.sets = 5;
+        settings.save_to(&path);
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), text);
+    }
+
+    #[test]
+    fn an_unversioned_file_is_upgraded() {
+        let path = scratch("unversioned");
+        std::fs::write(&path, "win_by_two = true\n").unwrap();
+        let settings = Settings::load_from(&path);
+        assert_eq!(settings.version, SETTINGS_VERSION);
+        assert!(settings.win_by_two);
+        assert_eq!(settings.controls, Bindings::default());
+    }
+}
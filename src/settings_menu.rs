@@ -0,0 +1,410 @@
+use bevy::prelude::*;
+use crate::bindings::{self, Action, Bindings};
+use crate::profiles::Profiles;
+use crate::settings::Settings;
+use crate::state;
+use crate::types::*;
+
+const VOLUME_STEP: f32 = 0.1;
+
+/// Binding waiting for a key press, if any.
+#[derive(Resource, Default)]
+pub struct RebindTarget(pub Option<(usize, Action)>);
+
+pub fn setup_settings_menu(mut commands: Commands, settings: Res<Settings>) {
+    let bindings = &settings.controls;
+    commands.spawn((
+        Node {
+            width: Val::Percent(100.0),
+            height: Val::Percent(100.0),
+            align_items: AlignItems::Center,
+            justify_content: JustifyContent::Center,
+            flex_direction: FlexDirection::Column,
+            ..default()
+        },
+        BackgroundColor(Color::srgba(0.0, 0.0, 0.0, 0.85)),
+        SettingsUI,
+    )).with_children(|parent| {
+        parent.spawn((
+            Text::new("SETTINGS"),
+            TextFont {
+                font_size: 48.0,
+                ..default()
+            },
+            TextColor(Color::WHITE),
+            Node {
+                margin: UiRect::bottom(Val::Px(16.0)),
+                ..default()
+            },
+        ));
+
+        let option_rows = [
+            ("WINDOW", None, SettingValue::WindowMode),
+            ("SPECTATORS", None, SettingValue::Spectators),
+            ("MASTER VOLUME", Some((SettingOption::MasterVolumeDown, SettingOption::MasterVolumeUp)), SettingValue::MasterVolume),
+            ("EFFECTS VOLUME", Some((SettingOption::SfxVolumeDown, SettingOption::SfxVolumeUp)), SettingValue::SfxVolume),
+        ];
+        for (label, steppers, value) in option_rows {
+            parent.spawn(Node {
+                flex_direction: FlexDirection::Row,
+                align_items: AlignItems::Center,
+                ..default()
+            }).with_children(|row| {
+                row.spawn((
+                    Text::new(label),
+                    TextFont {
+                        font_size: 22.0,
+                        ..default()
+                    },
+                    TextColor(Color::WHITE),
+                    Node {
+                        width: Val::Px(180.0),
+                        margin: UiRect::all(Val::Px(4.0)),
+                        ..default()
+                    },
+                ));
+
+                let value_text = (
+                    Text::new(value.display(&settings)),
+                    TextFont {
+                        font_size: 22.0,
+                        ..default()
+                    },
+                    TextColor(Color::WHITE),
+                    value,
+                );
+                match steppers {
+                    None => {
+                        row.spawn((
+                            Button,
+                            Node {
+                                width: Val::Px(180.0),
+                                height: Val::Px(36.0),
+                                margin: UiRect::all(Val::Px(4.0)),
+                                justify_content: JustifyContent::Center,
+                                align_items: AlignItems::Center,
+                                ..default()
+                            },
+                            BackgroundColor(Color::srgb(0.2, 0.2, 0.2)),
+                            match value {
+                                SettingValue::Spectators => SettingOption::Spectators,
+                                _ => SettingOption::WindowMode,
+                            },
+                        )).with_children(|btn| {
+                            btn.spawn(value_text);
+                        });
+                    }
+                    Some((down, up)) => {
+                        spawn_stepper(row, "-", down);
+                        row.spawn(Node {
+                            width: Val::Px(84.0),
+                            justify_content: JustifyContent::Center,
+                            ..default()
+                        }).with_children(|cell| {
+                            cell.spawn(value_text);
+                        });
+                        spawn_stepper(row, "+", up);
+                    }
+                }
+            });
+        }
+
+        let header = std::iter::once(String::new()).chain((1..=bindings.players.len()).map(|n| format!("PLAYER {}", n)));
+        parent.spawn(Node {
+            flex_direction: FlexDirection::Row,
+            ..default()
+        }).with_children(|row| {
+            for label in header {
+                row.spawn((
+                    Text::new(label),
+                    TextFont {
+                        font_size: 24.0,
+                        ..default()
+                    },
+                    TextColor(Color::WHITE),
+                    Node {
+                        width: Val::Px(180.0),
+                        margin: UiRect::all(Val::Px(6.0)),
+                        ..default()
+                    },
+                ));
+            }
+        });
+
+        for action in Action::ALL {
+            parent.spawn(Node {
+                flex_direction: FlexDirection::Row,
+                align_items: AlignItems::Center,
+                ..default()
+            }).with_children(|row| {
+                row.spawn((
+                    Text::new(action.label()),
+                    TextFont {
+                        font_size: 24.0,
+                        ..default()
+                    },
+                    TextColor(Color::WHITE),
+                    Node {
+                        width: Val::Px(180.0),
+                        margin: UiRect::all(Val::Px(6.0)),
+                        ..default()
+                    },
+                ));
+
+                for (player, player_bindings) in bindings.players.iter().enumerate() {
+                    row.spawn((
+                        Button,
+                        Node {
+                            width: Val::Px(180.0),
+                            height: Val::Px(36.0),
+                            margin: UiRect::all(Val::Px(4.0)),
+                            justify_content: JustifyContent::Center,
+                            align_items: AlignItems::Center,
+                            ..default()
+                        },
+                        BackgroundColor(Color::srgb(0.2, 0.2, 0.2)),
+                        BindingButton { player, action },
+                    )).with_children(|btn| {
+                        btn.spawn((
+                            Text::new(bindings::key_label(player_bindings.key(action))),
+                            TextFont {
+                                font_size: 22.0,
+                                ..default()
+                            },
+                            TextColor(Color::WHITE),
+                            BindingText { player, action },
+                        ));
+                    });
+                }
+            });
+        }
+
+        parent.spawn((
+            Text::new(""),
+            TextFont {
+                font_size: 20.0,
+                ..default()
+            },
+            TextColor(Color::srgb(1.0, 0.4, 0.4)),
+            Node {
+                margin: UiRect::vertical(Val::Px(20.0)),
+                ..default()
+            },
+            ConflictText,
+        ));
+
+        parent.spawn(Node {
+            flex_direction: FlexDirection::Row,
+            ..default()
+        }).with_children(|row| {
+            for (label, reset) in [("DEFAULTS", true), ("BACK", false)] {
+                let mut button = row.spawn((
+                    Button,
+                    Node {
+                        width: Val::Px(200.0),
+                        height: Val::Px(60.0),
+                        margin: UiRect::all(Val::Px(10.0)),
+                        justify_content: JustifyContent::Center,
+                        align_items: AlignItems::Center,
+                        ..default()
+                    },
+                    BackgroundColor(Color::srgb(0.2, 0.2, 0.2)),
+                ));
+                if reset {
+                    button.insert(ResetBindingsButton);
+                } else {
+                    button.insert(SettingsBackButton);
+                }
+                button.with_children(|btn| {
+                    btn.spawn((
+                        Text::new(label),
+                        TextFont {
+                            font_size: 28.0,
+                            ..default()
+                        },
+                        TextColor(Color::WHITE),
+                    ));
+                });
+            }
+        });
+    });
+}
+
+fn spawn_stepper(row: &mut ChildSpawnerCommands, symbol: &str, option: SettingOption) {
+    row.spawn((
+        Button,
+        Node {
+            width: Val::Px(40.0),
+            height: Val::Px(36.0),
+            margin: UiRect::all(Val::Px(4.0)),
+            justify_content: JustifyContent::Center,
+            align_items: AlignItems::Center,
+            ..default()
+        },
+        BackgroundColor(Color::srgb(0.2, 0.2, 0.2)),
+        option,
+    )).with_children(|btn| {
+        btn.spawn((
+            Text::new(symbol),
+            TextFont {
+                font_size: 22.0,
+                ..default()
+            },
+            TextColor(Color::WHITE),
+        ));
+    });
+}
+
+pub fn cleanup_settings_menu(
+    mut commands: Commands,
+    settings_ui: Query<Entity, With<SettingsUI>>,
+    mut target: ResMut<RebindTarget>,
+) {
+    for entity in settings_ui.iter() {
+        commands.entity(entity).despawn();
+    }
+    target.0 = None;
+}
+
+#[allow(clippy::type_complexity)]
+pub fn settings_button_system(
+    interaction_query: Query<
+        (&Interaction, Option<&BindingButton>, Option<&SettingOption>, Has<ResetBindingsButton>, Has<SettingsBackButton>),
+        (Changed<Interaction>, With<Button>),
+    >,
+    mut target: ResMut<RebindTarget>,
+    mut settings: ResMut<Settings>,
+    settings_origin: Res<state::SettingsOrigin>,
+    mut app_state: ResMut<NextState<state::AppMode>>,
+    menu_ui: Query<Entity, With<MenuUI>>,
+    mut commands: Commands,
+) {
+    for (interaction, binding_btn, option, reset, back) in interaction_query.iter() {
+        if *interaction != Interaction::Pressed {
+            continue;
+        }
+        if let Some(binding) = binding_btn {
+            target.0 = Some((binding.player, binding.action));
+        } else if let Some(option) = option {
+            match option {
+                SettingOption::WindowMode => settings.window_mode = settings.window_mode.toggled(),
+                SettingOption::MasterVolumeDown => settings.master_volume = step_volume(settings.master_volume, -VOLUME_STEP),
+                SettingOption::MasterVolumeUp => settings.master_volume = step_volume(settings.master_volume, VOLUME_STEP),
+                SettingOption::SfxVolumeDown => settings.sfx_volume = step_volume(settings.sfx_volume, -VOLUME_STEP),
+                SettingOption::SfxVolumeUp => settings.sfx_volume = step_volume(settings.sfx_volume, VOLUME_STEP),
+                SettingOption::Spectators => settings.net.spectators = !settings.net.spectators,
+            }
+        } else if reset {
+            settings.controls = Bindings::default();
+            target.0 = None;
+        } else if back {
+            app_state.set(settings_origin.0.clone());
+            if settings_origin.0 == state::AppMode::Menu {
+                for entity in menu_ui.iter() {
+                    commands.entity(entity).insert(Visibility::Visible);
+                }
+            }
+        }
+    }
+}
+
+/// Assigns the next pressed key to the binding picked on the settings screen.
+pub fn capture_rebind_system(
+    keyboard: Res<ButtonInput<KeyCode>>,
+    mut target: ResMut<RebindTarget>,
+    mut settings: ResMut<Settings>,
+    mut profiles: ResMut<Profiles>,
+) {
+    let Some((player, action)) = target.0 else { return };
+    let Some(key) = keyboard.get_just_pressed().next() else { return };
+    if let Some(player_bindings) = settings.controls.players.get_mut(player) {
+        player_bindings.set(action, *key);
+        profiles.remember_controls(player, player_bindings);
+    }
+    target.0 = None;
+}
+
+fn step_volume(volume: f32, step: f32) -> f32 {
+    ((volume + step) * 10.0).round().clamp(0.0, 10.0) / 10.0
+}
+
+#[allow(clippy::type_complexity)]
+pub fn settings_display_system(
+    settings: Res<Settings>,
+    target: Res<RebindTarget>,
+    mut binding_texts: Query<(&BindingText, &mut Text), (Without<ConflictText>, Without<SettingValue>)>,
+    mut value_texts: Query<(&SettingValue, &mut Text), (Without<ConflictText>, Without<BindingText>)>,
+    mut binding_buttons: Query<(&BindingButton, &Interaction, &mut BackgroundColor)>,
+    mut other_buttons: Query<(&Interaction, &mut BackgroundColor), (Or<(With<ResetBindingsButton>, With<SettingsBackButton>, With<SettingOption>)>, Without<BindingButton>)>,
+    mut conflict_text: Query<&mut Text, (With<ConflictText>, Without<BindingText>, Without<SettingValue>)>,
+) {
+    let bindings = &settings.controls;
+    for (binding, interaction, mut color) in binding_buttons.iter_mut() {
+        *color = binding_color(*interaction, bindings, &target, binding);
+    }
+    for (interaction, mut color) in other_buttons.iter_mut() {
+        *color = plain_color(*interaction);
+    }
+
+    if !settings.is_changed() && !target.is_changed() {
+        return;
+    }
+
+    for (value, mut text) in value_texts.iter_mut() {
+        text.0 = value.display(&settings);
+    }
+
+    for (binding, mut text) in binding_texts.iter_mut() {
+        text.0 = if target.0 == Some((binding.player, binding.action)) {
+            "PRESS A KEY".to_string()
+        } else {
+            bindings.players.get(binding.player)
+                .map(|b| bindings::key_label(b.key(binding.action)))
+                .unwrap_or_default()
+        };
+    }
+
+    if let Some(mut text) = conflict_text.iter_mut().next() {
+        text.0 = bindings.conflicts().iter()
+            .map(|c| format!(
+                "{} is bound to P{} {} and P{} {}",
+                bindings::key_label(c.key),
+                c.first.0 + 1,
+                c.first.1.label(),
+                c.second.0 + 1,
+                c.second.1.label(),
+            ))
+            .collect::<Vec<_>>()
+            .join("\n");
+    }
+}
+
+fn binding_color(interaction: Interaction, bindings: &Bindings, target: &RebindTarget, binding: &BindingButton) -> BackgroundColor {
+    if target.0 == Some((binding.player, binding.action)) {
+        BackgroundColor(Color::srgb(0.6, 0.6, 0.2))
+    } else if bindings.is_conflicting(binding.player, binding.action) {
+        BackgroundColor(Color::srgb(0.6, 0.15, 0.15))
+    } else {
+        plain_color(interaction)
+    }
+}
+
+fn plain_color(interaction: Interaction) -> BackgroundColor {
+    match interaction {
+        Interaction::Pressed => BackgroundColor(Color::srgb(0.5, 0.5, 0.5)),
+        Interaction::Hovered => BackgroundColor(Color::srgb(0.4, 0.4, 0.4)),
+        Interaction::None => BackgroundColor(Color::srgb(0.2, 0.2, 0.2)),
+    }
+}
+
+impl SettingValue {
+    fn display(&self, settings: &Settings) -> String {
+        match self {
+            SettingValue::WindowMode => settings.window_mode.label().to_string(),
+            SettingValue::MasterVolume => format!("{:.0}%", settings.master_volume * 100.0),
+            SettingValue::SfxVolume => format!("{:.0}%", settings.sfx_volume * 100.0),
+            SettingValue::Spectators if settings.net.spectators => format!("PORT {}", settings.net.spectator_port),
+            SettingValue::Spectators => "OFF".to_string(),
+        }
+    }
+}
@@ -0,0 +1,1305 @@
+use glam::Vec2;
+use serde::{Deserialize, Serialize};
+use crate::config;
+
+#[derive(Default, PartialEq, Eq, Clone, Copy, Debug)]
+pub enum ServingPlayer {
+    #[default]
+    Player1,
+    Player2,
+}
+
+impl ServingPlayer {
+    pub fn side(&self) -> Side {
+        match self {
+            ServingPlayer::Player1 => Side::Left,
+            ServingPlayer::Player2 => Side::Right,
+        }
+    }
+}
+
+#[derive(Default, Clone, PartialEq, Debug)]
+pub struct GameState {
+    pub player1_score: u32,
+    pub player2_score: u32,
+    pub player1_sets: u32,
+    pub player2_sets: u32,
+    /// Final points of every finished set, as (player 1, player 2).
+    pub set_scores: Vec<(u32, u32)>,
+    pub serving_player: ServingPlayer,
+    pub is_ball_active: bool,
+    pub game_over: bool,
+    pub winner: Option<u32>,
+}
+
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub enum Side {
+    Left,
+    Right,
+}
+
+impl Side {
+    pub fn opponent(self) -> Side {
+        match self {
+            Side::Left => Side::Right,
+            Side::Right => Side::Left,
+        }
+    }
+}
+
+#[derive(PartialEq, Eq, Default, Clone, Copy, Debug)]
+pub enum PlayerState {
+    #[default]
+    Idle,
+    Running,
+    Jumping,
+}
+
+/// Something that happened during a step, for sound, stats and rules to react to.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum SimEvent {
+    Served { player: usize },
+    PlayerHit { player: usize, speed: f32 },
+    PoleHit { speed: f32 },
+    WallBounce { speed: f32 },
+    Fault { side: Side, fault: Fault },
+    PointScored { scorer: Side },
+    SetWon { winner: u32 },
+    MatchWon { winner: u32 },
+    /// The ball came down in keep-up, ending an attempt of `hits` hits.
+    Dropped { hits: u32 },
+    /// The training launcher fired a ball.
+    Launched,
+    /// A launched ball was hit back over the net.
+    Returned { on_target: bool },
+    /// A launched ball landed on the receiving side.
+    Missed,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Fault {
+    /// More than `config::MAX_TOUCHES` touches before the ball crossed the net.
+    TooManyTouches,
+    /// The same player touched the ball twice in a row.
+    DoubleTouch,
+}
+
+/// Buttons held by one player during a simulation step.
+#[derive(Default, Clone, Copy, PartialEq, Eq, Debug)]
+pub struct PlayerInput {
+    pub left: bool,
+    pub right: bool,
+    pub jump: bool,
+    pub serve: bool,
+}
+
+impl PlayerInput {
+    pub fn any(&self) -> bool {
+        self.left || self.right || self.jump || self.serve
+    }
+
+    /// Packs the buttons into one byte for replay files.
+    pub fn to_bits(self) -> u8 {
+        self.left as u8 | (self.right as u8) << 1 | (self.jump as u8) << 2 | (self.serve as u8) << 3
+    }
+
+    pub fn from_bits(bits: u8) -> Self {
+        Self {
+            left: bits & 1 != 0,
+            right: bits & 2 != 0,
+            jump: bits & 4 != 0,
+            serve: bits & 8 != 0,
+        }
+    }
+}
+
+/// Court dimensions in world units, centred on the origin like the camera.
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Debug)]
+pub struct Arena {
+    pub width: f32,
+    pub height: f32,
+}
+
+impl Default for Arena {
+    fn default() -> Self {
+        Self { width: 1280.0, height: 720.0 }
+    }
+}
+
+impl Arena {
+    pub fn half_width(&self) -> f32 {
+        self.width / 2.0
+    }
+    pub fn ground_y(&self) -> f32 {
+        -self.height / 2.0 + self.height * config::GROUND_OFFSET_RATIO
+    }
+    pub fn net_height(&self) -> f32 {
+        self.height * config::NET_HEIGHT_RATIO
+    }
+    pub fn net_top_y(&self) -> f32 {
+        -self.height / 2.0 + self.net_height()
+    }
+    pub fn player_size(&self) -> Vec2 {
+        let player_height = self.height * config::PLAYER_HEIGHT_RATIO;
+        Vec2::new(player_height * config::PLAYER_ASPECT_RATIO, player_height)
+    }
+    pub fn player_radius(&self) -> f32 {
+        let size = self.player_size();
+        (size.x + size.y) / 4.0
+    }
+    pub fn ball_radius(&self) -> f32 {
+        (self.height * config::BALL_SIZE_RATIO) / 2.0
+    }
+    pub fn player_speed(&self) -> f32 {
+        self.width * config::SPEED_RATIO
+    }
+    pub fn jump_velocity(&self) -> f32 {
+        let target_jump_height = self.net_height() * config::JUMP_HEIGHT_RATIO_OF_POLE;
+        (2.0 * config::GRAVITY * target_jump_height).sqrt()
+    }
+    pub fn serve_y(&self) -> f32 {
+        self.ground_y() + 150.0
+    }
+    /// Where the training launcher holds and fires the ball from.
+    pub fn launcher_pos(&self) -> Vec2 {
+        Vec2::new(self.half_width() / 2.0, self.net_top_y() + self.height * config::LAUNCHER_HEIGHT_RATIO)
+    }
+    /// Stretches a launch tuned on the default arena so the ball flies the same path across this one.
+    /// Gravity doesn't scale, so flight time grows with the square root of the height.
+    pub fn scale_launch(&self, vel: Vec2) -> Vec2 {
+        let reference = Arena::default();
+        let (x, y) = (self.width / reference.width, self.height / reference.height);
+        Vec2::new(vel.x * x / y.sqrt(), vel.y * y.sqrt())
+    }
+    /// Horizontal range a player's centre may occupy when free to pass under the net.
+    pub fn court_bounds(&self) -> (f32, f32) {
+        let half_player_width = self.player_size().x / 2.0;
+        (-self.half_width() + half_player_width, self.half_width() - half_player_width)
+    }
+    /// Horizontal range a player's centre may occupy on the given side of the net.
+    pub fn side_bounds(&self, side: Side) -> (f32, f32) {
+        let half_player_width = self.player_size().x / 2.0;
+        let net_half_thickness = config::NET_COLLISION_WIDTH;
+        match side {
+            Side::Left => (-self.half_width() + half_player_width, -net_half_thickness - half_player_width),
+            Side::Right => (net_half_thickness + half_player_width, self.half_width() - half_player_width),
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct PlayerSim {
+    pub side: Side,
+    pub pos: Vec2,
+    pub prev_pos: Vec2,
+    pub vel: Vec2,
+    pub facing_left: bool,
+    pub state: PlayerState,
+    jump_held: bool,
+}
+
+impl PlayerSim {
+    /// `depth` is how far back from the net the player starts, as a fraction of the half.
+    pub fn new(side: Side, arena: &Arena, depth: f32) -> Self {
+        let x = match side {
+            Side::Left => -arena.half_width() * depth,
+            Side::Right => arena.half_width() * depth,
+        };
+        let pos = Vec2::new(x, arena.ground_y());
+        Self {
+            side,
+            pos,
+            prev_pos: pos,
+            vel: Vec2::ZERO,
+            facing_left: side == Side::Right,
+            state: PlayerState::Idle,
+            jump_held: false,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct BallSim {
+    pub pos: Vec2,
+    pub prev_pos: Vec2,
+    pub vel: Vec2,
+    pub angular_velocity: f32,
+    pub rotation: f32,
+    pub prev_rotation: f32,
+}
+
+/// What the dinos on court are playing for.
+#[derive(Serialize, Deserialize, Default, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum GameMode {
+    #[default]
+    Versus,
+    /// One dino alone keeps the ball in the air for as many hits as possible. With
+    /// `over_net` it has the whole court and every hit must send the ball across the net.
+    KeepUp { over_net: bool },
+    /// One dino receives balls fired by a launcher on the other side of the net.
+    Training(Drill),
+}
+
+#[derive(Serialize, Deserialize, Default, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum DrillKind {
+    /// Flat, fast balls to dig out.
+    #[default]
+    Receive,
+    /// Balls aimed at the top of the pole that deflect unpredictably.
+    Pole,
+    /// High, slow balls dropping deep into the court.
+    Lobs,
+    /// A bit of everything.
+    Mixed,
+}
+
+impl DrillKind {
+    pub const ALL: [DrillKind; 4] = [DrillKind::Receive, DrillKind::Pole, DrillKind::Lobs, DrillKind::Mixed];
+
+    /// Launch speed (world units per second), angle above horizontal (degrees) for each ball
+    /// of the drill, as fired on the default arena.
+    fn launches(self) -> &'static [(f32, f32)] {
+        const RECEIVE: [(f32, f32); 4] = [(880.0, 15.0), (940.0, 18.0), (1000.0, 12.0), (860.0, 22.0)];
+        const POLE: [(f32, f32); 5] = [(695.0, 16.0), (620.0, 30.0), (615.0, 40.0), (645.0, 50.0), (735.0, 60.0)];
+        const LOBS: [(f32, f32); 3] = [(1000.0, 56.0), (1060.0, 52.0), (940.0, 60.0)];
+        const MIXED: [(f32, f32); 8] = [
+            (880.0, 15.0), (680.0, 12.0), (1000.0, 56.0), (940.0, 18.0),
+            (650.0, 16.0), (1060.0, 52.0), (1000.0, 12.0), (940.0, 60.0),
+        ];
+        match self {
+            DrillKind::Receive => &RECEIVE,
+            DrillKind::Pole => &POLE,
+            DrillKind::Lobs => &LOBS,
+            DrillKind::Mixed => &MIXED,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Default, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum LaunchSpin {
+    #[default]
+    None,
+    Top,
+    Back,
+    /// Top or back spin at random.
+    Mixed,
+}
+
+/// Where a returned ball has to land on the launcher's side to count as on target.
+#[derive(Serialize, Deserialize, Default, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum TargetZone {
+    #[default]
+    Anywhere,
+    /// Close to the net.
+    Short,
+    /// Back half, towards the wall.
+    Deep,
+}
+
+impl TargetZone {
+    /// Horizontal range of the zone on the right side of `arena`.
+    pub fn range(self, arena: &Arena) -> (f32, f32) {
+        let half = arena.half_width();
+        match self {
+            TargetZone::Anywhere => (0.0, half),
+            TargetZone::Short => (0.0, half / 2.0),
+            TargetZone::Deep => (half / 2.0, half),
+        }
+    }
+}
+
+/// Launcher setup for a training session.
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(default)]
+pub struct Drill {
+    pub kind: DrillKind,
+    /// Launch speed as a percentage of the drill's own.
+    pub power: u32,
+    /// Degrees added to the angle of every launch.
+    pub angle: i32,
+    pub spin: LaunchSpin,
+    /// Pick the drill's balls at random instead of in order.
+    pub random: bool,
+    /// Seconds between a ball coming down and the next launch.
+    pub interval: u32,
+    pub target: TargetZone,
+    /// Seeds the random order and spin so replays fire the same balls.
+    pub seed: u32,
+}
+
+impl Default for Drill {
+    fn default() -> Self {
+        Self {
+            kind: DrillKind::default(),
+            power: 100,
+            angle: 0,
+            spin: LaunchSpin::default(),
+            random: false,
+            interval: 2,
+            target: TargetZone::default(),
+            seed: 1,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Debug)]
+#[serde(default)]
+pub struct MatchRules {
+    pub winning_score: u32,
+    /// Keep playing past `winning_score` until one player leads by two.
+    pub win_by_two: bool,
+    /// Volleyball-style touch limit and double-touch faults.
+    pub touch_rules: bool,
+    /// Best-of-N sets, each played to `winning_score`.
+    pub sets: u32,
+    /// Dinos on each side of the net; 2 for doubles.
+    pub team_size: u32,
+    pub mode: GameMode,
+}
+
+impl Default for MatchRules {
+    fn default() -> Self {
+        Self {
+            winning_score: config::WINNING_SCORE,
+            win_by_two: false,
+            touch_rules: false,
+            sets: 1,
+            team_size: 1,
+            mode: GameMode::Versus,
+        }
+    }
+}
+
+impl MatchRules {
+    pub fn sets_to_win(&self) -> u32 {
+        self.sets / 2 + 1
+    }
+
+    /// Player number (1 or 2) who has won the set with this score, if any.
+    pub fn winner(&self, player1_score: u32, player2_score: u32) -> Option<u32> {
+        let margin = if self.win_by_two { 2 } else { 1 };
+        if player1_score >= self.winning_score && player1_score >= player2_score + margin {
+            Some(1)
+        } else if player2_score >= self.winning_score && player2_score >= player1_score + margin {
+            Some(2)
+        } else {
+            None
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum MatchStatus {
+    Playing,
+    /// The given side wins with the next point.
+    MatchPoint(Side),
+    /// The given side wins the current set, but not yet the match, with the next point.
+    SetPoint(Side),
+    /// Tied at or past match point; someone needs a two point lead.
+    Deuce,
+    Finished,
+}
+
+/// Touches by the side currently playing the ball.
+#[derive(Default, Clone, Copy, Debug)]
+pub struct Touches {
+    pub side: Option<Side>,
+    pub count: u32,
+    pub last_player: Option<usize>,
+    since_last: f32,
+}
+
+/// Small xorshift generator so CPU decisions and drills don't need an external crate.
+#[derive(Clone, Debug)]
+pub struct Rng(u32);
+
+impl Rng {
+    pub fn new(seed: u32) -> Self {
+        Self(seed.max(1))
+    }
+    pub fn next_u32(&mut self) -> u32 {
+        let mut x = self.0;
+        x ^= x << 13;
+        x ^= x >> 17;
+        x ^= x << 5;
+        self.0 = x;
+        x
+    }
+    /// Uniform value in `[0, 1)`.
+    pub fn next_f32(&mut self) -> f32 {
+        (self.next_u32() >> 8) as f32 / (1u32 << 24) as f32
+    }
+    pub fn range(&mut self, min: f32, max: f32) -> f32 {
+        min + (max - min) * self.next_f32()
+    }
+}
+
+/// Progress of the current keep-up attempt.
+#[derive(Default, Clone, Copy, Debug)]
+pub struct KeepUp {
+    pub hits: u32,
+    /// Side of the net the last counted hit was made on.
+    last_side: Option<Side>,
+    /// The ball was hit twice without crossing the net.
+    failed: bool,
+}
+
+/// Running totals for the end-of-match summary. Per-side counts are indexed by `Side as usize`.
+#[derive(Serialize, Deserialize, Default, Clone, PartialEq, Debug)]
+#[serde(default)]
+pub struct MatchStats {
+    /// Hits per player slot; the server's first contact is the serve.
+    pub hits: Vec<u32>,
+    /// Points won off a serve the other side never touched.
+    pub aces: [u32; 2],
+    /// Points won by the side that served them.
+    pub serve_points: [u32; 2],
+    pub pole_hits: u32,
+    pub wall_bounces: u32,
+    pub longest_rally: u32,
+    pub rallies: u32,
+    /// Hits over all finished rallies, for the average.
+    pub rally_hits: u32,
+    /// Hits in the point being played.
+    #[serde(skip)]
+    rally: u32,
+    #[serde(skip)]
+    receiver_touched: bool,
+}
+
+impl MatchStats {
+    fn new(players: usize) -> Self {
+        Self { hits: vec![0; players], ..Self::default() }
+    }
+
+    pub fn average_rally(&self) -> f32 {
+        if self.rallies == 0 { 0.0 } else { self.rally_hits as f32 / self.rallies as f32 }
+    }
+}
+
+/// Training launcher state and how the session has gone so far.
+#[derive(Clone, Debug)]
+pub struct Launcher {
+    pub launched: u32,
+    pub returned: u32,
+    pub on_target: u32,
+    pub missed: u32,
+    /// Seconds until the next launch.
+    pub countdown: f32,
+    next: usize,
+    rng: Rng,
+    /// The current ball has been touched by the receiver.
+    touched: bool,
+}
+
+impl Launcher {
+    fn new(drill: &Drill) -> Self {
+        Self {
+            launched: 0,
+            returned: 0,
+            on_target: 0,
+            missed: 0,
+            countdown: drill.interval as f32,
+            next: 0,
+            rng: Rng::new(drill.seed),
+            touched: false,
+        }
+    }
+}
+
+/// A full match of dino ball, independent of any rendering or input backend.
+#[derive(Clone, Debug)]
+pub struct MatchSim {
+    pub arena: Arena,
+    pub speed: config::SpeedLevel,
+    pub rules: MatchRules,
+    pub players: Vec<PlayerSim>,
+    pub ball: BallSim,
+    pub state: GameState,
+    pub touches: Touches,
+    pub keep_up: KeepUp,
+    /// Present in training mode.
+    pub launcher: Option<Launcher>,
+    pub stats: MatchStats,
+    /// Events from the most recent `step`.
+    pub events: Vec<SimEvent>,
+    /// Side that broke a touch rule during this step; loses the point.
+    fault: Option<Side>,
+}
+
+impl Default for MatchSim {
+    fn default() -> Self {
+        Self::new(Arena::default(), config::SpeedLevel::default(), MatchRules::default())
+    }
+}
+
+impl MatchSim {
+    pub fn new(arena: Arena, speed: config::SpeedLevel, rules: MatchRules) -> Self {
+        // Sides alternate so slots 0 and 1 are always the first player of each team,
+        // and teammates spread across their half with the first (serving) one at the back.
+        let team_size = rules.team_size.max(1) as usize;
+        let player_count = if rules.mode == GameMode::Versus { team_size * 2 } else { 1 };
+        let players = (0..player_count)
+            .map(|i| {
+                let side = if i % 2 == 0 { Side::Left } else { Side::Right };
+                let depth = 1.0 - (i / 2 + 1) as f32 / (team_size + 1) as f32;
+                PlayerSim::new(side, &arena, depth)
+            })
+            .collect();
+        let mut sim = Self {
+            arena,
+            speed,
+            rules,
+            stats: MatchStats::new(player_count),
+            players,
+            ball: BallSim::default(),
+            state: GameState::default(),
+            touches: Touches::default(),
+            keep_up: KeepUp::default(),
+            launcher: match rules.mode {
+                GameMode::Training(drill) => Some(Launcher::new(&drill)),
+                _ => None,
+            },
+            events: Vec::new(),
+            fault: None,
+        };
+        if sim.launcher.is_some() {
+            sim.ball.pos = arena.launcher_pos();
+        } else {
+            sim.place_ball_on_server();
+        }
+        sim.ball.prev_pos = sim.ball.pos;
+        sim
+    }
+
+    /// Clears scores and puts everyone back in their starting positions.
+    pub fn reset(&mut self) {
+        *self = Self::new(self.arena, self.speed, self.rules);
+    }
+
+    /// Advances the match by `dt` seconds. `inputs` is indexed like `players`;
+    /// missing entries count as no buttons held. Meant to be called with a
+    /// fixed `dt`; positions from before the call are kept in `prev_pos` so
+    /// renderers can interpolate between steps.
+    pub fn step(&mut self, inputs: &[PlayerInput], dt: f32) {
+        self.events.clear();
+        for player in &mut self.players {
+            player.prev_pos = player.pos;
+        }
+        self.ball.prev_pos = self.ball.pos;
+        self.ball.prev_rotation = self.ball.rotation;
+
+        if self.state.game_over {
+            self.ball.vel = Vec2::ZERO;
+            return;
+        }
+
+        self.player_movement(inputs, dt);
+        match self.rules.mode {
+            GameMode::Training(drill) if !self.state.is_ball_active => self.launch_system(&drill, dt * self.speed.factor()),
+            _ => self.ball_system(inputs, dt * self.speed.factor()),
+        }
+        match self.rules.mode {
+            GameMode::Versus => self.score_and_reset(),
+            GameMode::KeepUp { .. } => self.drop_and_reset(),
+            GameMode::Training(drill) => self.judge_launch(&drill),
+        }
+    }
+
+    pub fn match_status(&self) -> MatchStatus {
+        let (p1, p2) = (self.state.player1_score, self.state.player2_score);
+        if self.state.game_over {
+            return MatchStatus::Finished;
+        }
+        let point_for = if self.rules.winner(p1 + 1, p2).is_some() {
+            Some(Side::Left)
+        } else if self.rules.winner(p1, p2 + 1).is_some() {
+            Some(Side::Right)
+        } else {
+            None
+        };
+        match point_for {
+            _ if self.rules.win_by_two && p1 == p2 && p1 + 1 >= self.rules.winning_score => MatchStatus::Deuce,
+            Some(side) if self.sets_won(side) + 1 >= self.rules.sets_to_win() => MatchStatus::MatchPoint(side),
+            Some(side) => MatchStatus::SetPoint(side),
+            None => MatchStatus::Playing,
+        }
+    }
+
+    pub fn sets_won(&self, side: Side) -> u32 {
+        match side {
+            Side::Left => self.state.player1_sets,
+            Side::Right => self.state.player2_sets,
+        }
+    }
+
+    fn player_movement(&mut self, inputs: &[PlayerInput], dt: f32) {
+        let arena = self.arena;
+        let ground_y = arena.ground_y();
+        let jump_velocity = arena.jump_velocity();
+        let roams = self.rules.mode == GameMode::KeepUp { over_net: true };
+
+        for (i, player) in self.players.iter_mut().enumerate() {
+            let input = inputs.get(i).copied().unwrap_or_default();
+            let mut horizontal_direction = 0.0;
+            if input.left {
+                horizontal_direction -= 1.0;
+            }
+            if input.right {
+                horizontal_direction += 1.0;
+            }
+
+            let jump_pressed = input.jump && !player.jump_held;
+            player.jump_held = input.jump;
+            if jump_pressed && player.pos.y <= ground_y + 1.0 {
+                player.vel.y = jump_velocity;
+            }
+
+            player.pos.x += horizontal_direction * arena.player_speed() * dt;
+
+            if horizontal_direction < 0.0 {
+                player.facing_left = true;
+            } else if horizontal_direction > 0.0 {
+                player.facing_left = false;
+            }
+
+            player.vel.y -= config::GRAVITY * dt;
+            player.pos.y += player.vel.y * dt;
+
+            if player.pos.y < ground_y {
+                player.pos.y = ground_y;
+                player.vel.y = 0.0;
+            }
+
+            player.state = if player.pos.y > ground_y + 1.0 {
+                PlayerState::Jumping
+            } else if horizontal_direction != 0.0 {
+                PlayerState::Running
+            } else {
+                PlayerState::Idle
+            };
+
+            let (min_x, max_x) = if roams { arena.court_bounds() } else { arena.side_bounds(player.side) };
+            player.pos.x = player.pos.x.clamp(min_x, max_x);
+        }
+        self.separate_teammates();
+    }
+
+    /// Teammates can't walk through each other; overlapping pairs are set side by side
+    /// around their midpoint. Someone jumping clear over a teammate passes freely.
+    fn separate_teammates(&mut self) {
+        let size = self.arena.player_size();
+        let spacing = size.x * config::TEAMMATE_SPACING_RATIO;
+        for b in 1..self.players.len() {
+            for a in 0..b {
+                let (first, rest) = self.players.split_at_mut(b);
+                let (p, q) = (&mut first[a], &mut rest[0]);
+                let dx = q.pos.x - p.pos.x;
+                if p.side != q.side || dx.abs() >= spacing || (q.pos.y - p.pos.y).abs() >= size.y {
+                    continue;
+                }
+                // Keep whoever was on the left on the left.
+                let dir = if dx < 0.0 || (dx == 0.0 && q.prev_pos.x < p.prev_pos.x) { -1.0 } else { 1.0 };
+                let (min_x, max_x) = self.arena.side_bounds(p.side);
+                let mid = ((p.pos.x + q.pos.x) / 2.0).clamp(min_x + spacing / 2.0, max_x - spacing / 2.0);
+                p.pos.x = mid - dir * spacing / 2.0;
+                q.pos.x = mid + dir * spacing / 2.0;
+            }
+        }
+    }
+
+    fn place_ball_on_server(&mut self) {
+        let serving_side = self.state.serving_player.side();
+        let target_x = self.players.iter()
+            .find(|p| p.side == serving_side)
+            .map(|p| p.pos.x)
+            .unwrap_or(0.0);
+        self.ball.pos = Vec2::new(target_x, self.arena.serve_y());
+        self.ball.rotation = 0.0;
+        self.ball.vel = Vec2::ZERO;
+        self.ball.angular_velocity = 0.0;
+    }
+
+    fn ball_system(&mut self, inputs: &[PlayerInput], delta: f32) {
+        if !self.state.is_ball_active {
+            self.serve(inputs);
+            return;
+        }
+
+        // Split fast movement into sub-steps so the ball cannot skip over the pole or a player.
+        let max_step = self.arena.ball_radius() * config::BALL_MAX_STEP_RATIO;
+        let substeps = ((self.ball.vel.length() * delta) / max_step).ceil().clamp(1.0, 16.0) as u32;
+        let sub_delta = delta / substeps as f32;
+        for _ in 0..substeps {
+            self.ball_substep(sub_delta);
+            if self.fault.is_some() {
+                break;
+            }
+        }
+    }
+
+    fn serve(&mut self, inputs: &[PlayerInput]) {
+        let arena = self.arena;
+        let ball_radius = arena.ball_radius();
+        let player_size = arena.player_size();
+
+        self.place_ball_on_server();
+        let ball_pos = self.ball.pos;
+        let serving_side = self.state.serving_player.side();
+
+        for (i, player) in self.players.iter().enumerate() {
+            if player.side != serving_side {
+                continue;
+            }
+            let serve_pressed = inputs.get(i).is_some_and(|input| input.any());
+            let is_moving_up = player.vel.y > 10.0;
+
+            if (serve_pressed || is_moving_up) && ball_pos.x + ball_radius > player.pos.x - player_size.x / 2.0 &&
+               ball_pos.x - ball_radius < player.pos.x + player_size.x / 2.0 &&
+               ball_pos.y + ball_radius > player.pos.y - player_size.y / 2.0 &&
+               ball_pos.y - ball_radius < player.pos.y + player_size.y / 2.0
+            {
+                self.state.is_ball_active = true;
+                self.touches = Touches::default();
+                self.ball.vel.y = arena.jump_velocity();
+                self.ball.vel.x = (ball_pos.x - player.pos.x) * 15.0;
+
+                let hit_dir = (ball_pos.x - player.pos.x) / (player_size.x / 2.0);
+                self.ball.angular_velocity = (-hit_dir * 30.0).clamp(-config::BALL_MAX_SPIN, config::BALL_MAX_SPIN);
+                self.events.push(SimEvent::Served { player: i });
+                self.stats.rally = 0;
+                self.stats.receiver_touched = false;
+            }
+        }
+    }
+
+    fn ball_substep(&mut self, delta: f32) {
+        let arena = self.arena;
+        let ball_radius = arena.ball_radius();
+        let player_size = arena.player_size();
+
+        let ball = &mut self.ball;
+        ball.vel.y -= config::BALL_GRAVITY * delta;
+        ball.vel.x *= config::BALL_HORIZONTAL_DAMPING.powf(delta);
+
+        ball.pos += ball.vel * delta;
+
+        ball.rotation += ball.angular_velocity * delta;
+        ball.rotation -= ball.vel.x * config::BALL_ROTATION_FACTOR * delta;
+        ball.angular_velocity *= config::BALL_ANGULAR_DAMPING.powf(delta);
+
+        let screen_half_width = arena.half_width();
+        if ball.pos.x - ball_radius < -screen_half_width {
+            ball.pos.x = -screen_half_width + ball_radius;
+            self.events.push(SimEvent::WallBounce { speed: ball.vel.x.abs() });
+            self.stats.wall_bounces += 1;
+            ball.vel.x *= -config::BALL_BOUNCE;
+        }
+        if ball.pos.x + ball_radius > screen_half_width {
+            ball.pos.x = screen_half_width - ball_radius;
+            self.events.push(SimEvent::WallBounce { speed: ball.vel.x.abs() });
+            self.stats.wall_bounces += 1;
+            ball.vel.x *= -config::BALL_BOUNCE;
+        }
+        // No roof boundary: ball can go off screen at the top
+
+        let net_half_thickness = config::NET_COLLISION_WIDTH;
+        let net_top_y = arena.net_top_y();
+
+        if ball.pos.y < net_top_y + ball_radius && ball.pos.x.abs() < net_half_thickness + ball_radius {
+            let pole_top_center = Vec2::new(0.0, net_top_y);
+            let on_top = ball.pos.y > net_top_y - 10.0;
+            let normal = if on_top { (ball.pos - pole_top_center).normalize_or_zero() } else { Vec2::new(ball.pos.x.signum(), 0.0) };
+            // The ball can still overlap on the substeps after a bounce; only a ball coming in is a new contact.
+            let contact = ball.vel.dot(normal) < 0.0;
+            if contact {
+                self.events.push(SimEvent::PoleHit { speed: ball.vel.length() });
+                self.stats.pole_hits += 1;
+            }
+            if on_top {
+                let dist_vec = ball.pos - pole_top_center;
+                if contact {
+                    let speed = ball.vel.length().max(300.0);
+                    ball.vel = normal * (speed + 50.0);
+                }
+
+                let overlap = ball_radius - dist_vec.length();
+                if overlap > 0.0 {
+                    ball.pos += normal * overlap;
+                }
+            } else {
+                ball.vel.x *= -config::BALL_BOUNCE;
+                ball.pos.x = ball.pos.x.signum() * (net_half_thickness + ball_radius + 1.0);
+            }
+        }
+
+        // Crossing the net hands the ball to the other side's touch count.
+        self.touches.since_last += delta;
+        if let Some(side) = self.touches.side
+            && (side == Side::Left) != (ball.pos.x < 0.0)
+        {
+            self.touches = Touches::default();
+        }
+
+        let p_radius = arena.player_radius();
+        for (i, player) in self.players.iter().enumerate() {
+            let dist_vec = ball.pos - player.pos;
+            let distance = dist_vec.length();
+
+            if distance < p_radius + ball_radius {
+                self.events.push(SimEvent::PlayerHit { player: i, speed: (ball.vel - player.vel).length() });
+                let repeat = self.touches.last_player == Some(i) && self.touches.since_last < config::TOUCH_MERGE_TIME;
+                if !repeat {
+                    self.stats.hits[i] += 1;
+                    self.stats.rally += 1;
+                    self.stats.receiver_touched |= player.side != self.state.serving_player.side();
+                }
+                if let GameMode::KeepUp { over_net } = self.rules.mode
+                    && !repeat
+                {
+                    let side = if ball.pos.x < 0.0 { Side::Left } else { Side::Right };
+                    if over_net && self.keep_up.last_side == Some(side) {
+                        self.keep_up.failed = true;
+                    } else {
+                        self.keep_up.hits += 1;
+                        self.keep_up.last_side = Some(side);
+                    }
+                }
+                if let Some(fault) = count_touch(&mut self.touches, &self.players, i)
+                    && self.rules.touch_rules
+                    && self.fault.is_none()
+                {
+                    self.fault = Some(player.side);
+                    self.events.push(SimEvent::Fault { side: player.side, fault });
+                }
+                let normal = dist_vec.normalize_or_zero();
+                let speed = ball.vel.length().max(900.0);
+                ball.vel = (normal * (speed + 600.0)).clamp_length_max(config::BALL_MAX_SPEED);
+
+                // spin on hit
+                let hit_dir = (ball.pos.x - player.pos.x) / (player_size.x / 2.0);
+                ball.angular_velocity = (-hit_dir * 50.0).clamp(-config::BALL_MAX_SPIN, config::BALL_MAX_SPIN);
+
+                let overlap = (p_radius + ball_radius) - distance;
+                ball.pos += normal * overlap;
+            }
+        }
+    }
+
+    /// Counts down between balls and fires the next one of the drill from the launcher.
+    fn launch_system(&mut self, drill: &Drill, delta: f32) {
+        let Some(launcher) = self.launcher.as_mut() else { return };
+        self.ball.pos = self.arena.launcher_pos();
+        self.ball.vel = Vec2::ZERO;
+        launcher.countdown -= delta;
+        if launcher.countdown > 0.0 {
+            return;
+        }
+
+        let launches = drill.kind.launches();
+        let index = if drill.random {
+            launcher.rng.next_u32() as usize % launches.len()
+        } else {
+            launcher.next % launches.len()
+        };
+        launcher.next = index + 1;
+        let (speed, angle) = launches[index];
+        let speed = speed * drill.power as f32 / 100.0;
+        let angle = (angle + drill.angle as f32).to_radians();
+        // Fired towards the left side, so top spin turns the ball counter-clockwise.
+        let spin = match drill.spin {
+            LaunchSpin::None => 0.0,
+            LaunchSpin::Top => config::BALL_MAX_SPIN,
+            LaunchSpin::Back => -config::BALL_MAX_SPIN,
+            LaunchSpin::Mixed if launcher.rng.next_u32() % 2 == 0 => config::BALL_MAX_SPIN,
+            LaunchSpin::Mixed => -config::BALL_MAX_SPIN,
+        };
+        self.ball.vel = self.arena.scale_launch(Vec2::new(-speed * angle.cos(), speed * angle.sin()));
+        self.ball.angular_velocity = spin;
+        launcher.launched += 1;
+        launcher.touched = false;
+        self.state.is_ball_active = true;
+        self.touches = Touches::default();
+        self.events.push(SimEvent::Launched);
+    }
+
+    /// Training's end condition: once the ball lands, a touched ball on the launcher's
+    /// side is a return and one on the receiver's side a miss. Balls that bounce back
+    /// untouched, e.g. off the pole, don't count either way.
+    fn judge_launch(&mut self, drill: &Drill) {
+        let Some(launcher) = self.launcher.as_mut() else { return };
+        if self.events.iter().any(|event| matches!(event, SimEvent::PlayerHit { .. })) {
+            launcher.touched = true;
+        }
+        if !self.state.is_ball_active || self.ball.pos.y >= self.arena.ground_y() {
+            return;
+        }
+        let x = self.ball.pos.x;
+        if x < 0.0 {
+            launcher.missed += 1;
+            self.events.push(SimEvent::Missed);
+        } else if launcher.touched {
+            let (min_x, max_x) = drill.target.range(&self.arena);
+            let on_target = (min_x..=max_x).contains(&x);
+            launcher.returned += 1;
+            launcher.on_target += on_target as u32;
+            self.events.push(SimEvent::Returned { on_target });
+        }
+        launcher.countdown = drill.interval as f32;
+        self.state.is_ball_active = false;
+        self.touches = Touches::default();
+        self.ball.vel = Vec2::ZERO;
+    }
+
+    /// Keep-up's end condition: the attempt is over once the ball lands, or once it's
+    /// hit twice without crossing when it has to go over the net.
+    fn drop_and_reset(&mut self) {
+        if !self.state.is_ball_active || (self.ball.pos.y >= self.arena.ground_y() && !self.keep_up.failed) {
+            return;
+        }
+        self.events.push(SimEvent::Dropped { hits: self.keep_up.hits });
+        self.keep_up = KeepUp::default();
+        self.state.is_ball_active = false;
+        self.touches = Touches::default();
+        self.ball.vel = Vec2::ZERO;
+    }
+
+    fn score_and_reset(&mut self) {
+        if !self.state.is_ball_active {
+            return;
+        }
+        let scorer = if let Some(side) = self.fault.take() {
+            side.opponent()
+        } else if self.ball.pos.y < self.arena.ground_y() {
+            if self.ball.pos.x < 0.0 { Side::Right } else { Side::Left }
+        } else {
+            return;
+        };
+
+        let stats = &mut self.stats;
+        let serving_side = self.state.serving_player.side();
+        if scorer == serving_side {
+            stats.serve_points[scorer as usize] += 1;
+            if !stats.receiver_touched {
+                stats.aces[scorer as usize] += 1;
+            }
+        }
+        stats.longest_rally = stats.longest_rally.max(stats.rally);
+        stats.rally_hits += stats.rally;
+        stats.rallies += 1;
+        stats.rally = 0;
+
+        let state = &mut self.state;
+        self.events.push(SimEvent::PointScored { scorer });
+        if scorer == Side::Right {
+            state.player2_score += 1;
+            state.serving_player = ServingPlayer::Player2;
+        } else {
+            state.player1_score += 1;
+            state.serving_player = ServingPlayer::Player1;
+        }
+        if let Some(winner) = self.rules.winner(state.player1_score, state.player2_score) {
+            state.set_scores.push((state.player1_score, state.player2_score));
+            let sets_won = if winner == 1 { &mut state.player1_sets } else { &mut state.player2_sets };
+            *sets_won += 1;
+            self.events.push(SimEvent::SetWon { winner });
+            if *sets_won >= self.rules.sets_to_win() {
+                state.game_over = true;
+                state.winner = Some(winner);
+                self.events.push(SimEvent::MatchWon { winner });
+            } else {
+                // Serve alternates between sets, starting with player 1.
+                state.player1_score = 0;
+                state.player2_score = 0;
+                state.serving_player = if state.set_scores.len().is_multiple_of(2) {
+                    ServingPlayer::Player1
+                } else {
+                    ServingPlayer::Player2
+                };
+            }
+        }
+        state.is_ball_active = false;
+        self.touches = Touches::default();
+        self.ball.vel = Vec2::ZERO;
+    }
+}
+
+/// Records a touch by `player` and returns the fault it commits, if any.
+/// Contacts by the same player within `config::TOUCH_MERGE_TIME` are one touch.
+/// No player may touch twice in a row, so in singles the ball has to go straight back.
+fn count_touch(touches: &mut Touches, players: &[PlayerSim], player: usize) -> Option<Fault> {
+    let side = players[player].side;
+    let same_player = touches.last_player == Some(player);
+    if same_player && touches.since_last < config::TOUCH_MERGE_TIME {
+        touches.since_last = 0.0;
+        return None;
+    }
+    touches.since_last = 0.0;
+    if touches.side != Some(side) {
+        *touches = Touches { side: Some(side), count: 1, last_player: Some(player), since_last: 0.0 };
+        return None;
+    }
+
+    touches.count += 1;
+    touches.last_player = Some(player);
+    if same_player {
+        Some(Fault::DoubleTouch)
+    } else if touches.count > config::MAX_TOUCHES {
+        Some(Fault::TooManyTouches)
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    const DT: f32 = 1.0 / config::SIMULATION_HZ as f32;
+
+    fn jump() -> PlayerInput {
+        PlayerInput { jump: true, ..PlayerInput::default() }
+    }
+
+    /// Puts a live ball on the ground of `side`'s half, so the other side scores on the next step.
+    fn land_on(sim: &mut MatchSim, side: Side) {
+        let x = sim.arena.half_width() - sim.arena.ball_radius() * 2.0;
+        sim.state.is_ball_active = true;
+        sim.ball.pos = Vec2::new(if side == Side::Left { -x } else { x }, sim.arena.ground_y() - 1.0);
+        sim.ball.vel = Vec2::ZERO;
+        sim.step(&[], DT);
+    }
+
+    fn score(sim: &MatchSim) -> (u32, u32) {
+        (sim.state.player1_score, sim.state.player2_score)
+    }
+
+    #[test]
+    fn serving_takes_a_jump_into_the_ball() {
+        let mut sim = MatchSim::default();
+        let serve = PlayerInput { serve: true, ..PlayerInput::default() };
+        for _ in 0..30 {
+            sim.step(&[serve], DT);
+        }
+        assert!(!sim.state.is_ball_active, "the ball hangs above a dino standing still");
+
+        let mut served = false;
+        for _ in 0..60 {
+            sim.step(&[jump()], DT);
+            served |= sim.events.contains(&SimEvent::Served { player: 0 });
+        }
+        assert!(served);
+        assert!(sim.state.is_ball_active);
+    }
+
+    #[test]
+    fn the_side_that_scores_serves_next() {
+        let mut sim = MatchSim::default();
+        land_on(&mut sim, Side::Left);
+        assert!(sim.events.contains(&SimEvent::PointScored { scorer: Side::Right }));
+        assert_eq!(score(&sim), (0, 1));
+        assert_eq!(sim.state.serving_player, ServingPlayer::Player2);
+        assert!(!sim.state.is_ball_active);
+
+        land_on(&mut sim, Side::Right);
+        assert_eq!(score(&sim), (1, 1));
+        assert_eq!(sim.state.serving_player, ServingPlayer::Player1);
+    }
+
+    #[test]
+    fn first_to_the_winning_score_wins() {
+        let mut sim = MatchSim::default();
+        for _ in 1..config::WINNING_SCORE {
+            land_on(&mut sim, Side::Right);
+        }
+        assert_eq!(sim.match_status(), MatchStatus::MatchPoint(Side::Left));
+        assert!(!sim.state.game_over);
+
+        land_on(&mut sim, Side::Right);
+        assert!(sim.events.contains(&SimEvent::MatchWon { winner: 1 }));
+        assert_eq!(sim.match_status(), MatchStatus::Finished);
+        assert_eq!(sim.state.winner, Some(1));
+
+        // Nothing moves once the match is over.
+        land_on(&mut sim, Side::Right);
+        assert_eq!(score(&sim), (config::WINNING_SCORE, 0));
+    }
+
+    #[test]
+    fn win_by_two_plays_on_until_someone_leads_by_two() {
+        let rules = MatchRules { winning_score: 3, win_by_two: true, ..MatchRules::default() };
+        let mut sim = MatchSim::new(Arena::default(), config::SpeedLevel::default(), rules);
+        for side in [Side::Right, Side::Left, Side::Right, Side::Left] {
+            land_on(&mut sim, side);
+        }
+        assert_eq!(score(&sim), (2, 2));
+        assert_eq!(sim.match_status(), MatchStatus::Deuce);
+
+        land_on(&mut sim, Side::Right);
+        assert_eq!(sim.match_status(), MatchStatus::MatchPoint(Side::Left));
+        land_on(&mut sim, Side::Left);
+        assert_eq!(score(&sim), (3, 3));
+        assert!(!sim.state.game_over, "3-3 isn't a win at 3 when you need two clear");
+        assert_eq!(sim.match_status(), MatchStatus::Deuce);
+
+        land_on(&mut sim, Side::Right);
+        land_on(&mut sim, Side::Right);
+        assert_eq!(sim.state.winner, Some(1));
+        assert_eq!(sim.state.set_scores, vec![(5, 3)]);
+    }
+
+    /// Inputs for a short rally, chosen by tick so every frame rate sees the same ones.
+    fn scripted_inputs(tick: u32) -> [PlayerInput; 2] {
+        let mut inputs = [PlayerInput::default(); 2];
+        inputs[0].jump = tick < 40 || (150..170).contains(&tick);
+        inputs[0].right = (40..90).contains(&tick);
+        inputs[1].left = (20..120).contains(&tick);
+        inputs[1].jump = (60..80).contains(&tick);
+        inputs
+    }
+
+    /// Steps the sim the way a fixed timestep does at `fps` frames a second, until `ticks` steps have run.
+    fn run_at_fps(fps: u32, ticks: u32) -> MatchSim {
+        let step = Duration::from_secs_f64(1.0 / config::SIMULATION_HZ);
+        let frame = Duration::from_secs_f64(1.0 / fps as f64);
+        let mut sim = MatchSim::default();
+        let mut overstep = Duration::ZERO;
+        let mut tick = 0;
+        while tick < ticks {
+            overstep += frame;
+            while overstep >= step && tick < ticks {
+                overstep -= step;
+                sim.step(&scripted_inputs(tick), step.as_secs_f32());
+                tick += 1;
+            }
+        }
+        sim
+    }
+
+    #[test]
+    fn fixed_steps_give_the_same_match_at_any_frame_rate() {
+        let ticks = 3 * config::SIMULATION_HZ as u32;
+        let expected = run_at_fps(60, ticks);
+        assert!(expected.stats.rallies > 0 || expected.state.is_ball_active, "the script should at least serve");
+        for fps in [24, 30, 75, 144, 240] {
+            let sim = run_at_fps(fps, ticks);
+            assert_eq!(sim.ball.pos, expected.ball.pos, "ball at {fps} fps");
+            assert_eq!(sim.ball.vel, expected.ball.vel, "ball speed at {fps} fps");
+            for (player, other) in sim.players.iter().zip(&expected.players) {
+                assert_eq!(player.pos, other.pos, "dino at {fps} fps");
+            }
+            assert_eq!(sim.state, expected.state);
+            assert_eq!(sim.stats, expected.stats);
+        }
+    }
+
+    /// Fires every ball of a drill in order with nobody touching them, and counts those that hit the pole.
+    fn pole_hits(arena: Arena, kind: DrillKind) -> (usize, usize) {
+        let drill = Drill { kind, interval: 1, ..Drill::default() };
+        let rules = MatchRules { mode: GameMode::Training(drill), ..MatchRules::default() };
+        let mut sim = MatchSim::new(arena, config::SpeedLevel::default(), rules);
+        // Out of the way at the back of the court.
+        sim.players[0].pos.x = -arena.half_width();
+        let balls = kind.launches().len();
+        let mut hit = vec![false; balls];
+        let mut launched = 0;
+        while launched < balls || sim.state.is_ball_active {
+            sim.step(&[], DT);
+            launched += sim.events.iter().filter(|event| **event == SimEvent::Launched).count();
+            if sim.events.iter().any(|event| matches!(event, SimEvent::PoleHit { .. })) {
+                hit[launched - 1] = true;
+            }
+        }
+        (hit.iter().filter(|hit| **hit).count(), balls)
+    }
+
+    #[test]
+    fn pole_drill_hits_the_pole_on_any_window() {
+        for (width, height) in [(1280.0, 720.0), (1920.0, 1080.0), (800.0, 600.0), (1600.0, 700.0), (1024.0, 768.0)] {
+            let (hits, balls) = pole_hits(Arena { width, height }, DrillKind::Pole);
+            assert_eq!(hits, balls, "{width}x{height}");
+        }
+    }
+
+    #[test]
+    fn each_pole_drill_ball_counts_one_pole_hit() {
+        let drill = Drill { kind: DrillKind::Pole, interval: 1, ..Drill::default() };
+        let rules = MatchRules { mode: GameMode::Training(drill), ..MatchRules::default() };
+        let mut sim = MatchSim::new(Arena::default(), config::SpeedLevel::default(), rules);
+        sim.players[0].pos.x = -sim.arena.half_width();
+        let balls = DrillKind::Pole.launches().len();
+        let mut per_ball = vec![0; balls];
+        let mut launched = 0;
+        while launched < balls || sim.state.is_ball_active {
+            let before = sim.stats.pole_hits;
+            sim.step(&[], DT);
+            launched += sim.events.iter().filter(|event| **event == SimEvent::Launched).count();
+            if launched > 0 {
+                per_ball[launched - 1] += sim.stats.pole_hits - before;
+            }
+        }
+        assert_eq!(per_ball, vec![1; balls]);
+    }
+
+    #[test]
+    fn a_ball_still_overlapping_the_pole_is_not_a_new_hit() {
+        let mut sim = MatchSim::default();
+        let radius = sim.arena.ball_radius();
+        let pole_top = Vec2::new(0.0, sim.arena.net_top_y());
+        let offset = Vec2::new(-0.6, 0.8) * radius * 0.9;
+        sim.state.is_ball_active = true;
+        sim.ball.pos = pole_top + offset;
+        sim.ball.vel = offset.normalize() * 400.0;
+        sim.step(&[], DT);
+        assert_eq!(sim.stats.pole_hits, 0);
+        assert!(!sim.events.iter().any(|event| matches!(event, SimEvent::PoleHit { .. })));
+
+        sim.ball.pos = pole_top + offset;
+        sim.ball.vel = -offset.normalize() * 400.0;
+        sim.step(&[], DT);
+        assert_eq!(sim.stats.pole_hits, 1);
+    }
+
+    #[test]
+    fn winner_needs_the_margin() {
+        let rules = MatchRules { winning_score: 11, win_by_two: true, ..MatchRules::default() };
+        assert_eq!(rules.winner(11, 10), None);
+        assert_eq!(rules.winner(12, 10), Some(1));
+        assert_eq!(rules.winner(9, 11), Some(2));
+        let rules = MatchRules { win_by_two: false, ..rules };
+        assert_eq!(rules.winner(11, 10), Some(1));
+    }
+
+    /// Touches by `players` in order, each one after the previous contact has ended.
+    fn touch_faults(team_size: u32, players: &[usize]) -> Vec<Option<Fault>> {
+        let rules = MatchRules { touch_rules: true, team_size, ..MatchRules::default() };
+        let sim = MatchSim::new(Arena::default(), config::SpeedLevel::default(), rules);
+        let mut touches = Touches::default();
+        players.iter().map(|player| {
+            touches.since_last = config::TOUCH_MERGE_TIME;
+            count_touch(&mut touches, &sim.players, *player)
+        }).collect()
+    }
+
+    #[test]
+    fn a_lone_player_cannot_touch_twice() {
+        assert_eq!(touch_faults(1, &[0, 0]), [None, Some(Fault::DoubleTouch)]);
+    }
+
+    #[test]
+    fn doubles_partners_have_to_take_turns() {
+        assert_eq!(touch_faults(2, &[0, 0]), [None, Some(Fault::DoubleTouch)]);
+        assert_eq!(touch_faults(2, &[0, 2, 0, 2]), [None, None, None, Some(Fault::TooManyTouches)]);
+    }
+
+    #[test]
+    fn one_contact_is_one_touch() {
+        let sim = MatchSim::default();
+        let mut touches = Touches::default();
+        for _ in 0..5 {
+            assert_eq!(count_touch(&mut touches, &sim.players, 0), None);
+        }
+        assert_eq!(touches.count, 1);
+    }
+}
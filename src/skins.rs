@@ -0,0 +1,179 @@
+use bevy::asset::io::memory::{Dir, MemoryAssetReader};
+use bevy::asset::io::{AssetSourceBuilder, Reader};
+use bevy::asset::{AssetLoader, AssetPath, LoadContext};
+use bevy::prelude::*;
+use bevy::render::render_resource::TextureFormat;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use crate::config;
+use crate::profiles::Players;
+use crate::types::*;
+
+/// Asset source recoloured dino textures are loaded from; it only holds [`HueShift`] recipes.
+pub const SKIN_SOURCE: &str = "dino-skin";
+
+/// A recoloured texture: the image it starts from and how far its hue is turned.
+#[derive(Serialize, Deserialize)]
+struct HueShift {
+    image: String,
+    hue: f32,
+}
+
+/// Builds a texture from a [`HueShift`] recipe. The base image goes through the asset server
+/// like any other, so it's found inside app bundles and editing it redraws every colour.
+#[derive(Default, TypePath)]
+pub struct HueShiftLoader;
+
+impl AssetLoader for HueShiftLoader {
+    type Asset = Image;
+    type Settings = ();
+    type Error = BevyError;
+
+    async fn load(&self, reader: &mut dyn Reader, _settings: &(), load_context: &mut LoadContext<'_>) -> Result<Image, BevyError> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes).await?;
+        let recipe: HueShift = ron::de::from_bytes(&bytes)?;
+        let base = load_context.loader().immediate().load::<Image>(recipe.image.clone()).await?;
+        let base = base.get();
+        let mut image = base.convert(TextureFormat::Rgba8UnormSrgb)
+            .ok_or_else(|| format!("{} can't be recoloured", recipe.image))?;
+        image.sampler = base.sampler.clone();
+        for pixel in image.data.iter_mut().flat_map(|data| data.chunks_exact_mut(4)) {
+            if pixel[3] > 0 {
+                let (h, s, v) = rgb_to_hsv(pixel[0] as f32 / 255.0, pixel[1] as f32 / 255.0, pixel[2] as f32 / 255.0);
+                // Greys and outlines keep their colour.
+                let nh = if s > 0.15 { (h + recipe.hue).fract() } else { h };
+                let (nr, ng, nb) = hsv_to_rgb(nh, s, v);
+                pixel[..3].copy_from_slice(&[(nr * 255.0).round() as u8, (ng * 255.0).round() as u8, (nb * 255.0).round() as u8]);
+            }
+        }
+        Ok(image)
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["hue"]
+    }
+}
+
+fn rgb_to_hsv(r: f32, g: f32, b: f32) -> (f32, f32, f32) {
+    let max = r.max(g).max(b);
+    let min = r.min(g).min(b);
+    let d = max - min;
+    let mut h = 0.0;
+    if d != 0.0 {
+        if max == r {
+            h = (g - b) / d + (if g < b { 6.0 } else { 0.0 });
+        } else if max == g {
+            h = (b - r) / d + 2.0;
+        } else {
+            h = (r - g) / d + 4.0;
+        }
+        h /= 6.0;
+    }
+    let s = if max == 0.0 { 0.0 } else { d / max };
+    let v = max;
+    (h, s, v)
+}
+
+fn hsv_to_rgb(h: f32, s: f32, v: f32) -> (f32, f32, f32) {
+    let i = (h * 6.0).floor();
+    let f = h * 6.0 - i;
+    let p = v * (1.0 - s);
+    let q = v * (1.0 - f * s);
+    let t = v * (1.0 - (1.0 - f) * s);
+    match i as i32 % 6 {
+        0 => (v, t, p),
+        1 => (q, v, p),
+        2 => (p, v, t),
+        3 => (p, q, v),
+        4 => (t, p, v),
+        _ => (v, p, q),
+    }
+}
+
+/// Dino textures for each hue used so far, so switching back is free.
+#[derive(Resource, Default)]
+pub struct DinoSkins {
+    /// Backs [`SKIN_SOURCE`]; a recipe is written the first time its colour is asked for.
+    recipes: Dir,
+    skins: HashMap<u32, PlayerAnimations>,
+}
+
+impl DinoSkins {
+    /// The asset source serving this cache's recipes. Has to be registered before `AssetPlugin`.
+    pub fn source(&self) -> AssetSourceBuilder {
+        let root = self.recipes.clone();
+        AssetSourceBuilder::new(move || Box::new(MemoryAssetReader { root: root.clone() }))
+    }
+
+    /// Textures for a dino drawn with `hue`. Recoloured ones load in the background.
+    pub fn get(&mut self, hue: f32, asset_server: &AssetServer) -> PlayerAnimations {
+        // Hues a fraction of a degree apart look the same; don't recolour for them.
+        let key = (hue.rem_euclid(1.0) * 360.0).round() as u32 % 360;
+        let recipes = &self.recipes;
+        let load = |image: &'static str| -> Handle<Image> {
+            if key == 0 {
+                return asset_server.load(image);
+            }
+            let file = PathBuf::from(key.to_string()).join(image).with_extension("hue");
+            let recipe = HueShift { image: image.to_string(), hue: key as f32 / 360.0 };
+            match ron::to_string(&recipe) {
+                Ok(text) => recipes.insert_asset_text(&file, &text),
+                Err(e) => eprintln!("Failed to write dino skin {:?}: {}", file, e),
+            }
+            asset_server.load(AssetPath::from_path_buf(file).with_source(SKIN_SOURCE))
+        };
+        self.skins.entry(key)
+            .or_insert_with(|| PlayerAnimations {
+                stationary: load(config::ASSET_PLAYER_STATIONARY),
+                jump: load(config::ASSET_PLAYER_JUMP),
+                run1: load(config::ASSET_PLAYER_RUN1),
+                run2: load(config::ASSET_PLAYER_RUN2),
+            })
+            .clone()
+    }
+}
+
+/// How far apart two hue shifts are around the colour wheel, from 0 to 0.5.
+pub fn hue_distance(a: f32, b: f32) -> f32 {
+    let d = (a - b).rem_euclid(1.0);
+    d.min(1.0 - d)
+}
+
+/// Whether a dino drawn with `hue` can be told apart from all of `others`.
+pub fn is_distinct(hue: f32, others: impl IntoIterator<Item = f32>) -> bool {
+    others.into_iter().all(|other| hue_distance(hue, other) >= config::MIN_HUE_DISTANCE)
+}
+
+/// Preset name for `hue`, or its angle on the colour wheel.
+pub fn hue_label(hue: f32) -> String {
+    match config::DINO_COLORS.iter().find(|(_, preset)| hue_distance(hue, *preset) < 0.001) {
+        Some((name, _)) => name.to_string(),
+        None => format!("HUE {:.0}°", (config::DINO_BASE_HUE + hue).rem_euclid(1.0) * 360.0),
+    }
+}
+
+/// Roughly the main body colour of a dino drawn with `hue`, for swatches.
+pub fn swatch_color(hue: f32) -> Color {
+    Color::hsv((config::DINO_BASE_HUE + hue).rem_euclid(1.0) * 360.0, 0.7, 0.85)
+}
+
+/// Redraws dinos whose player wants a different colour, e.g. at the start of a match.
+pub fn apply_dino_colors(
+    players: Res<Players>,
+    asset_server: Res<AssetServer>,
+    mut skins: ResMut<DinoSkins>,
+    mut dinos: Query<(&PlayerSlot, &mut DinoHue, &mut PlayerAnimations, &mut Sprite)>,
+) {
+    for (slot, mut hue, mut anims, mut sprite) in dinos.iter_mut() {
+        let wanted = players.hue(slot.0);
+        if hue.0 == wanted {
+            continue;
+        }
+        hue.0 = wanted;
+        *anims = skins.get(wanted, &asset_server);
+        sprite.image = anims.stationary.clone();
+    }
+}
+
@@ -0,0 +1,79 @@
+use bevy::prelude::*;
+use crate::server::Server;
+use crate::settings::Settings;
+use crate::state;
+use crate::types::*;
+
+/// Streams matches played on this machine to spectators while enabled in the settings.
+#[derive(Resource)]
+pub struct Publisher(Server);
+
+/// Opens or closes the spectator port to follow the setting.
+pub fn sync_publisher(
+    settings: Res<Settings>,
+    publisher: Option<Res<Publisher>>,
+    mut failed_port: Local<Option<u16>>,
+    mut commands: Commands,
+) {
+    let net = &settings.net;
+    if !net.spectators {
+        if publisher.is_some() {
+            commands.remove_resource::<Publisher>();
+        }
+        *failed_port = None;
+        return;
+    }
+    if publisher.is_some() || *failed_port == Some(net.spectator_port) {
+        return;
+    }
+    // Players stay local; every connection is a spectator.
+    match Server::bind(net.spectator_port, 0) {
+        Ok(server) => commands.insert_resource(Publisher(server)),
+        Err(e) => {
+            eprintln!("Failed to open spectator port {}: {}", net.spectator_port, e);
+            *failed_port = Some(net.spectator_port);
+        }
+    }
+}
+
+/// Runs after every simulation step.
+pub fn publish_tick(mut publisher: ResMut<Publisher>, game: Res<state::Match>) {
+    publisher.0.end_tick(&game, true);
+}
+
+/// Answers spectators every frame, even while the match is paused or over.
+pub fn publish_idle(mut publisher: ResMut<Publisher>, game: Res<state::Match>) {
+    publisher.0.poll(&game);
+    publisher.0.keep_alive(&game);
+}
+
+pub fn setup_spectator_text(mut commands: Commands) {
+    commands.spawn((
+        Text::new(""),
+        TextFont {
+            font_size: 20.0,
+            ..default()
+        },
+        TextColor(Color::srgb(0.8, 0.8, 0.8)),
+        Node {
+            position_type: PositionType::Absolute,
+            right: Val::Px(16.0),
+            bottom: Val::Px(12.0),
+            ..default()
+        },
+        SpectatorText,
+    ));
+}
+
+pub fn spectator_text_system(publisher: Option<Res<Publisher>>, mut texts: Query<&mut Text, With<SpectatorText>>) {
+    let label = match publisher.map(|publisher| publisher.0.spectators()) {
+        Some(1) => "1 SPECTATOR".to_string(),
+        Some(count) if count > 1 => format!("{} SPECTATORS", count),
+        _ => String::new(),
+    };
+    for mut text in texts.iter_mut() {
+        if text.0 != label {
+            text.0 = label.clone();
+        }
+    }
+}
@@ -1,39 +1,58 @@
 use bevy::prelude::*;
 use crate::config;
-
-#[derive(Default, PartialEq, Clone, Copy)]
-pub enum ServingPlayer {
-    #[default]
-    Player1,
-    Player2,
-}
+use crate::cpu;
+use crate::sim;
 
 #[derive(States, Default, Debug, Clone, PartialEq, Eq, Hash)]
 pub enum AppMode {
     #[default]
     Menu,
+    Settings,
     Playing,
+    Paused,
+    Replay,
+    InstantReplay,
+    Online,
+    Training,
+    Records,
+    Profiles,
+    Dinos,
 }
 
+/// Screen the settings menu goes back to.
 #[derive(Resource)]
-pub struct GameSpeed {
-    pub level: config::SpeedLevel,
+pub struct SettingsOrigin(pub AppMode);
+
+impl Default for SettingsOrigin {
+    fn default() -> Self {
+        Self(AppMode::Menu)
+    }
 }
 
-impl Default for GameSpeed {
+#[derive(Resource, Default, Deref, DerefMut)]
+pub struct Match(pub sim::MatchSim);
+
+/// A simulation event forwarded to Bevy systems such as audio.
+#[derive(Message, Clone, Copy, Debug)]
+pub struct GameEvent(pub sim::SimEvent);
+
+/// Inputs for the next simulation step, indexed by player slot.
+#[derive(Resource, Default)]
+pub struct PlayerInputs(pub Vec<sim::PlayerInput>);
+
+/// Gamepad entity assigned to each player slot.
+#[derive(Resource)]
+pub struct GamepadSlots(pub Vec<Option<Entity>>);
+
+impl Default for GamepadSlots {
     fn default() -> Self {
-        Self {
-            level: config::SpeedLevel::Normal,
-        }
+        Self(vec![None; config::MAX_PLAYERS])
     }
 }
 
+/// Difficulty picked in the menu and the CPU controllers driving player slots this match.
 #[derive(Resource, Default)]
-pub struct GameState {
-    pub player1_score: u32,
-    pub player2_score: u32,
-    pub serving_player: ServingPlayer,
-    pub is_ball_active: bool,
-    pub game_over: bool,
-    pub winner: Option<u32>,
+pub struct CpuPlayers {
+    pub difficulty: cpu::Difficulty,
+    pub controllers: Vec<(usize, cpu::CpuController)>,
 }
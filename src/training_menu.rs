@@ -0,0 +1,311 @@
+use bevy::prelude::*;
+use crate::practice::DrillRecords;
+use crate::replay;
+use crate::settings::Settings;
+use crate::sim::{Drill, DrillKind, GameMode, LaunchSpin, MatchRules, TargetZone};
+use crate::state;
+use crate::types::*;
+
+const MIN_INTERVAL: u32 = 1;
+const MAX_INTERVAL: u32 = 6;
+/// Launch speed range, as a percentage of the drill's own.
+const MIN_POWER: u32 = 70;
+const MAX_POWER: u32 = 130;
+const POWER_STEP: u32 = 5;
+/// How far every launch angle can be raised or lowered, in degrees.
+const MAX_ANGLE: i32 = 15;
+const ANGLE_STEP: i32 = 5;
+
+pub fn setup_training_menu(mut commands: Commands) {
+    commands.spawn((
+        Node {
+            width: Val::Percent(100.0),
+            height: Val::Percent(100.0),
+            align_items: AlignItems::Center,
+            justify_content: JustifyContent::Center,
+            flex_direction: FlexDirection::Column,
+            ..default()
+        },
+        BackgroundColor(Color::srgba(0.0, 0.0, 0.0, 0.85)),
+        TrainingUI,
+    )).with_children(|parent| {
+        parent.spawn((
+            Text::new("TRAINING"),
+            TextFont {
+                font_size: 48.0,
+                ..default()
+            },
+            TextColor(Color::WHITE),
+            Node {
+                margin: UiRect::bottom(Val::Px(16.0)),
+                ..default()
+            },
+        ));
+
+        let rows = [
+            ("DRILL", None, TrainingButton::Drill, TrainingValue::Drill),
+            ("BALL SPEED", Some(TrainingButton::SpeedDown), TrainingButton::SpeedUp, TrainingValue::Speed),
+            ("ANGLE", Some(TrainingButton::AngleDown), TrainingButton::AngleUp, TrainingValue::Angle),
+            ("SPIN", None, TrainingButton::Spin, TrainingValue::Spin),
+            ("ORDER", None, TrainingButton::Order, TrainingValue::Order),
+            ("INTERVAL", Some(TrainingButton::IntervalDown), TrainingButton::IntervalUp, TrainingValue::Interval),
+            ("TARGET ZONE", None, TrainingButton::Target, TrainingValue::Target),
+        ];
+        for (label, down, action, value) in rows {
+            parent.spawn(Node {
+                flex_direction: FlexDirection::Row,
+                align_items: AlignItems::Center,
+                ..default()
+            }).with_children(|row| {
+                row.spawn((
+                    Text::new(label),
+                    TextFont {
+                        font_size: 22.0,
+                        ..default()
+                    },
+                    TextColor(Color::WHITE),
+                    Node {
+                        width: Val::Px(180.0),
+                        margin: UiRect::all(Val::Px(4.0)),
+                        ..default()
+                    },
+                ));
+                let value_text = (
+                    Text::new(""),
+                    TextFont {
+                        font_size: 22.0,
+                        ..default()
+                    },
+                    TextColor(Color::WHITE),
+                    value,
+                );
+                // Steppers for numbers, a button that cycles through the choices otherwise.
+                if let Some(down) = down {
+                    spawn_button(row, 40.0, down, |btn| {
+                        btn.spawn(label_text("-"));
+                    });
+                    row.spawn(Node {
+                        width: Val::Px(84.0),
+                        justify_content: JustifyContent::Center,
+                        ..default()
+                    }).with_children(|cell| {
+                        cell.spawn(value_text);
+                    });
+                    spawn_button(row, 40.0, action, |btn| {
+                        btn.spawn(label_text("+"));
+                    });
+                } else {
+                    spawn_button(row, 180.0, action, |btn| {
+                        btn.spawn(value_text);
+                    });
+                }
+            });
+        }
+
+        parent.spawn((
+            Text::new(""),
+            TextFont {
+                font_size: 20.0,
+                ..default()
+            },
+            TextColor(Color::srgb(1.0, 0.85, 0.2)),
+            TextLayout::new_with_justify(Justify::Center),
+            Node {
+                margin: UiRect::vertical(Val::Px(20.0)),
+                ..default()
+            },
+            TrainingValue::Stats,
+        ));
+
+        parent.spawn(Node {
+            flex_direction: FlexDirection::Row,
+            ..default()
+        }).with_children(|row| {
+            for (label, action) in [("START", TrainingButton::Start), ("BACK", TrainingButton::Back)] {
+                row.spawn((
+                    Button,
+                    Node {
+                        width: Val::Px(200.0),
+                        height: Val::Px(60.0),
+                        margin: UiRect::all(Val::Px(10.0)),
+                        justify_content: JustifyContent::Center,
+                        align_items: AlignItems::Center,
+                        ..default()
+                    },
+                    BackgroundColor(Color::srgb(0.2, 0.2, 0.2)),
+                    action,
+                )).with_children(|btn| {
+                    btn.spawn((
+                        Text::new(label),
+                        TextFont {
+                            font_size: 28.0,
+                            ..default()
+                        },
+                        TextColor(Color::WHITE),
+                    ));
+                });
+            }
+        });
+    });
+}
+
+fn spawn_button(row: &mut ChildSpawnerCommands, width: f32, action: TrainingButton, children: impl FnOnce(&mut ChildSpawnerCommands)) {
+    row.spawn((
+        Button,
+        Node {
+            width: Val::Px(width),
+            height: Val::Px(36.0),
+            margin: UiRect::all(Val::Px(4.0)),
+            justify_content: JustifyContent::Center,
+            align_items: AlignItems::Center,
+            ..default()
+        },
+        BackgroundColor(Color::srgb(0.2, 0.2, 0.2)),
+        action,
+    )).with_children(children);
+}
+
+fn label_text(text: &str) -> (Text, TextFont, TextColor) {
+    (
+        Text::new(text),
+        TextFont {
+            font_size: 22.0,
+            ..default()
+        },
+        TextColor(Color::WHITE),
+    )
+}
+
+pub fn cleanup_training_menu(mut commands: Commands, training_ui: Query<Entity, With<TrainingUI>>) {
+    for entity in training_ui.iter() {
+        commands.entity(entity).despawn();
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn training_button_system(
+    interaction_query: Query<(&Interaction, &TrainingButton), Changed<Interaction>>,
+    mut settings: ResMut<Settings>,
+    mut game: ResMut<state::Match>,
+    mut cpu_players: ResMut<state::CpuPlayers>,
+    mut recorder: ResMut<replay::Recorder>,
+    mut app_state: ResMut<NextState<state::AppMode>>,
+    menu_ui: Query<Entity, With<MenuUI>>,
+    mut commands: Commands,
+) {
+    for (interaction, action) in interaction_query.iter() {
+        if *interaction != Interaction::Pressed {
+            continue;
+        }
+        let speed = settings.speed;
+        let drill = &mut settings.training;
+        match action {
+            TrainingButton::Drill => drill.kind = next(&DrillKind::ALL, drill.kind),
+            TrainingButton::SpeedDown => drill.power = drill.power.saturating_sub(POWER_STEP).max(MIN_POWER),
+            TrainingButton::SpeedUp => drill.power = (drill.power + POWER_STEP).min(MAX_POWER),
+            TrainingButton::AngleDown => drill.angle = (drill.angle - ANGLE_STEP).max(-MAX_ANGLE),
+            TrainingButton::AngleUp => drill.angle = (drill.angle + ANGLE_STEP).min(MAX_ANGLE),
+            TrainingButton::Spin => {
+                drill.spin = next(&[LaunchSpin::None, LaunchSpin::Top, LaunchSpin::Back, LaunchSpin::Mixed], drill.spin);
+            }
+            TrainingButton::Order => drill.random = !drill.random,
+            TrainingButton::IntervalDown => drill.interval = drill.interval.saturating_sub(1).max(MIN_INTERVAL),
+            TrainingButton::IntervalUp => drill.interval = (drill.interval + 1).min(MAX_INTERVAL),
+            TrainingButton::Target => {
+                drill.target = next(&[TargetZone::Anywhere, TargetZone::Short, TargetZone::Deep], drill.target);
+            }
+            TrainingButton::Start => {
+                let seed = std::time::SystemTime::now()
+                    .duration_since(std::time::UNIX_EPOCH)
+                    .map(|d| d.subsec_nanos())
+                    .unwrap_or(1);
+                game.speed = speed;
+                game.rules = MatchRules {
+                    mode: GameMode::Training(Drill { seed, ..*drill }),
+                    ..MatchRules::default()
+                };
+                game.reset();
+                cpu_players.controllers.clear();
+                recorder.start(&game, seed);
+                app_state.set(state::AppMode::Playing);
+            }
+            TrainingButton::Back => {
+                app_state.set(state::AppMode::Menu);
+                for entity in menu_ui.iter() {
+                    commands.entity(entity).insert(Visibility::Visible);
+                }
+            }
+        }
+    }
+}
+
+fn next<T: Copy + PartialEq>(choices: &[T], current: T) -> T {
+    let index = choices.iter().position(|choice| *choice == current).map_or(0, |i| i + 1);
+    choices[index % choices.len()]
+}
+
+pub fn training_display_system(
+    settings: Res<Settings>,
+    records: Res<DrillRecords>,
+    mut texts: Query<(&TrainingValue, &mut Text)>,
+    mut buttons: Query<(&Interaction, &mut BackgroundColor), With<TrainingButton>>,
+) {
+    let drill = &settings.training;
+    for (value, mut text) in texts.iter_mut() {
+        let label = match value {
+            TrainingValue::Drill => drill_label(drill.kind).to_string(),
+            TrainingValue::Speed => format!("{}%", drill.power),
+            TrainingValue::Angle => format!("{:+} DEG", drill.angle),
+            TrainingValue::Spin => match drill.spin {
+                LaunchSpin::None => "NONE",
+                LaunchSpin::Top => "TOP SPIN",
+                LaunchSpin::Back => "BACK SPIN",
+                LaunchSpin::Mixed => "MIXED",
+            }
+            .to_string(),
+            TrainingValue::Order => if drill.random { "RANDOM" } else { "IN ORDER" }.to_string(),
+            TrainingValue::Interval => format!("{} S", drill.interval),
+            TrainingValue::Target => match drill.target {
+                TargetZone::Anywhere => "ANYWHERE",
+                TargetZone::Short => "SHORT",
+                TargetZone::Deep => "DEEP",
+            }
+            .to_string(),
+            TrainingValue::Stats => {
+                let record = records.get(drill.kind);
+                if record.launched == 0 {
+                    format!("NO {} DRILLS YET", drill_label(drill.kind))
+                } else {
+                    format!(
+                        "{}: {} BALLS, {} RETURNED ({}%), {} ON TARGET",
+                        drill_label(drill.kind),
+                        record.launched,
+                        record.returned,
+                        record.success_rate(),
+                        record.on_target,
+                    )
+                }
+            }
+        };
+        if text.0 != label {
+            text.0 = label;
+        }
+    }
+
+    for (interaction, mut color) in buttons.iter_mut() {
+        *color = match interaction {
+            Interaction::Pressed => BackgroundColor(Color::srgb(0.5, 0.5, 0.5)),
+            Interaction::Hovered => BackgroundColor(Color::srgb(0.4, 0.4, 0.4)),
+            Interaction::None => BackgroundColor(Color::srgb(0.2, 0.2, 0.2)),
+        };
+    }
+}
+
+pub fn drill_label(kind: DrillKind) -> &'static str {
+    match kind {
+        DrillKind::Receive => "RECEIVE",
+        DrillKind::Pole => "POLE",
+        DrillKind::Lobs => "LOBS",
+        DrillKind::Mixed => "MIXED",
+    }
+}
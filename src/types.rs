@@ -1,17 +1,16 @@
 use bevy::prelude::*;
+use crate::bindings;
 use crate::config;
+use crate::cpu;
+use crate::sim;
 
+/// Side of the net a dino plays on; teammates share it.
 #[derive(Component)]
-pub struct Player1;
+pub struct Team(pub sim::Side);
 
+/// Index of the player in `MatchSim::players` that this entity renders.
 #[derive(Component)]
-pub struct Player2;
-
-#[derive(Component, Default)]
-pub struct Velocity(pub Vec2);
-
-#[derive(Component, Default)]
-pub struct AngularVelocity(pub f32);
+pub struct PlayerSlot(pub usize);
 
 #[derive(Component)]
 pub struct Background;
@@ -28,6 +27,17 @@ pub struct ScoreText1;
 #[derive(Component)]
 pub struct ScoreText2;
 
+#[derive(Component)]
+pub struct StatusText;
+
+/// Touch count of one side, shown under its score when touch rules are on.
+#[derive(Component)]
+pub struct TouchText(pub sim::Side);
+
+/// Sets won by one side, shown beside its score in best-of-N matches.
+#[derive(Component)]
+pub struct SetText(pub sim::Side);
+
 #[derive(Component, Clone)]
 pub struct PlayerAnimations {
     pub stationary: Handle<Image>,
@@ -36,19 +46,16 @@ pub struct PlayerAnimations {
     pub run2: Handle<Image>,
 }
 
-#[derive(Component, PartialEq, Default, Clone, Copy)]
-pub enum PlayerState {
-    #[default]
-    Idle,
-    Running,
-    Jumping,
-}
-
 #[derive(Component)]
 pub struct AnimationTimer(pub Timer);
 
-#[derive(Component, Default)]
-pub struct FacingLeft(pub bool);
+/// Hue shift a dino's textures are currently drawn with.
+#[derive(Component)]
+pub struct DinoHue(pub f32);
+
+/// Names of the players on one side, above their score.
+#[derive(Component)]
+pub struct NameText(pub sim::Side);
 
 #[derive(Component)]
 pub struct WinUI;
@@ -63,4 +70,280 @@ pub struct MenuUI;
 pub struct SpeedButton(pub config::SpeedLevel);
 
 #[derive(Component)]
-pub struct StartButton;
+pub struct TargetScoreButton(pub u32);
+
+#[derive(Component)]
+pub struct SetsButton(pub u32);
+
+#[derive(Component, Clone, Copy, PartialEq, Eq)]
+pub enum RuleToggle {
+    WinByTwo,
+    TouchRules,
+    KeepUpOverNet,
+}
+
+#[derive(Component)]
+pub struct DifficultyButton(pub cpu::Difficulty);
+
+#[derive(Component)]
+pub struct StartButton {
+    pub vs_cpu: bool,
+    /// Dinos per side; 2 starts a doubles match.
+    pub team_size: u32,
+    /// Solo keep-up practice instead of a match.
+    pub keep_up: bool,
+}
+
+#[derive(Component)]
+pub struct SettingsButton;
+
+#[derive(Component)]
+pub struct SettingsUI;
+
+#[derive(Component)]
+pub struct BindingButton {
+    pub player: usize,
+    pub action: bindings::Action,
+}
+
+#[derive(Component)]
+pub struct BindingText {
+    pub player: usize,
+    pub action: bindings::Action,
+}
+
+#[derive(Component)]
+pub struct ConflictText;
+
+#[derive(Component)]
+pub struct ResetBindingsButton;
+
+#[derive(Component, Clone, Copy, PartialEq, Eq)]
+pub enum SettingOption {
+    WindowMode,
+    MasterVolumeDown,
+    MasterVolumeUp,
+    SfxVolumeDown,
+    SfxVolumeUp,
+    Spectators,
+}
+
+#[derive(Component, Clone, Copy, PartialEq, Eq)]
+pub enum SettingValue {
+    WindowMode,
+    MasterVolume,
+    SfxVolume,
+    Spectators,
+}
+
+#[derive(Component)]
+pub struct SettingsBackButton;
+
+#[derive(Component)]
+pub struct PauseUI;
+
+#[derive(Component)]
+pub struct WatchReplayButton;
+
+#[derive(Component)]
+pub struct InstantReplayUI;
+
+#[derive(Component)]
+pub struct OnlineMenuButton;
+
+#[derive(Component)]
+pub struct OnlineUI;
+
+#[derive(Component, Clone, Copy, PartialEq, Eq)]
+pub enum OnlineButton {
+    Host,
+    Join,
+    Server,
+    Watch,
+    DelayDown,
+    DelayUp,
+    LatencyDown,
+    LatencyUp,
+    LossDown,
+    LossUp,
+    Back,
+    JoinLan(std::net::SocketAddr),
+    Focus(OnlineField),
+}
+
+/// Text field on the online screen that typing goes into.
+#[derive(Clone, Copy, PartialEq, Eq, Default, Debug)]
+pub enum OnlineField {
+    Name,
+    #[default]
+    Address,
+}
+
+/// Holds the discovered games, rebuilt whenever the listed ones change.
+#[derive(Component, Default)]
+pub struct LanList(pub Vec<(std::net::SocketAddr, String)>);
+
+#[derive(Component, Clone, Copy, PartialEq, Eq)]
+pub enum OnlineValue {
+    Name,
+    Address,
+    Delay,
+    Latency,
+    Loss,
+    Status,
+}
+
+#[derive(Component)]
+pub struct SpectatorText;
+
+#[derive(Component)]
+pub struct TrainingMenuButton;
+
+#[derive(Component)]
+pub struct TrainingUI;
+
+#[derive(Component, Clone, Copy, PartialEq, Eq)]
+pub enum TrainingButton {
+    Drill,
+    SpeedDown,
+    SpeedUp,
+    AngleDown,
+    AngleUp,
+    Spin,
+    Order,
+    IntervalDown,
+    IntervalUp,
+    Target,
+    Start,
+    Back,
+}
+
+#[derive(Component, Clone, Copy, PartialEq, Eq)]
+pub enum TrainingValue {
+    Drill,
+    Speed,
+    Angle,
+    Spin,
+    Order,
+    Interval,
+    Target,
+    Stats,
+}
+
+#[derive(Component)]
+pub struct RecordsMenuButton;
+
+#[derive(Component)]
+pub struct RecordsUI;
+
+#[derive(Component, Clone, Copy, PartialEq, Eq)]
+pub enum RecordsButton {
+    ExportCsv,
+    ExportJson,
+    Back,
+}
+
+/// Result of the last export on the records screen.
+#[derive(Component)]
+pub struct RecordsStatus;
+
+#[derive(Component)]
+pub struct ProfilesMenuButton;
+
+#[derive(Component)]
+pub struct ProfilesUI;
+
+#[derive(Component, Clone, Copy, PartialEq, Eq)]
+pub enum ProfilesButton {
+    /// Cycles who plays a player slot.
+    Slot(usize),
+    /// Cycles which profile is being edited.
+    Edit,
+    New,
+    Delete,
+    Color,
+    Controls,
+    Back,
+}
+
+#[derive(Component, Clone, Copy, PartialEq, Eq)]
+pub enum ProfilesValue {
+    Slot(usize),
+    Edit,
+    Name,
+    Color,
+    Controls,
+    /// Keys two playing slots share.
+    Conflicts,
+}
+
+/// Shows the colour of the profile being edited.
+#[derive(Component)]
+pub struct ProfileSwatch;
+
+#[derive(Component)]
+pub struct DinosMenuButton;
+
+#[derive(Component)]
+pub struct DinosUI;
+
+#[derive(Component, Clone, Copy, PartialEq, Eq)]
+pub enum DinosButton {
+    HueDown(usize),
+    HueUp(usize),
+    /// Cycles the preset colours for a slot.
+    Preset(usize),
+    Back,
+}
+
+#[derive(Component, Clone, Copy, PartialEq, Eq)]
+pub enum DinosValue {
+    Name(usize),
+    Color(usize),
+}
+
+/// Shows the colour a player slot's dino is drawn with.
+#[derive(Component)]
+pub struct DinoSwatch(pub usize);
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum PreviewFrame {
+    Stationary,
+    Run,
+    Jump,
+}
+
+/// One frame of a player slot's dino on the colour picker.
+#[derive(Component)]
+pub struct DinoPreview {
+    pub slot: usize,
+    pub frame: PreviewFrame,
+}
+
+/// Highlights where returned balls should land during training.
+#[derive(Component)]
+pub struct TargetZoneMarker;
+
+#[derive(Component)]
+pub struct ReplayUI;
+
+#[derive(Component)]
+pub struct ReplayText;
+
+#[derive(Component, Clone, Copy, PartialEq, Eq)]
+pub enum ReplayButton {
+    SeekBack,
+    TogglePause,
+    SeekForward,
+    Slower,
+    Faster,
+    Exit,
+}
+
+#[derive(Component, Clone, Copy, PartialEq, Eq)]
+pub enum PauseButton {
+    Resume,
+    Restart,
+    Settings,
+    Quit,
+}
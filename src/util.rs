@@ -5,98 +5,45 @@ pub fn close_on_esc(
     mut commands: Commands,
     primary_window: Query<Entity, With<bevy::window::PrimaryWindow>>,
 ) {
-    if keyboard.just_pressed(KeyCode::Escape) {
-        if let Some(entity) = primary_window.iter().next() {
-            commands.entity(entity).despawn();
-        }
+    if keyboard.just_pressed(KeyCode::Escape)
+        && let Some(entity) = primary_window.iter().next()
+    {
+        commands.entity(entity).despawn();
     }
 }
 
-fn rgb_to_hsv(r: f32, g: f32, b: f32) -> (f32, f32, f32) {
-    let max = r.max(g).max(b);
-    let min = r.min(g).min(b);
-    let d = max - min;
-    let mut h = 0.0;
-    if d != 0.0 {
-        if max == r {
-            h = (g - b) / d + (if g < b { 6.0 } else { 0.0 });
-        } else if max == g {
-            h = (b - r) / d + 2.0;
-        } else {
-            h = (r - g) / d + 4.0;
-        }
-        h /= 6.0;
-    }
-    let s = if max == 0.0 { 0.0 } else { d / max };
-    let v = max;
-    (h, s, v)
+/// Location of a file in the platform config directory, e.g. `$XDG_CONFIG_HOME/dino-ball/<file>`.
+pub fn config_path(file: &str) -> Option<std::path::PathBuf> {
+    dirs::config_dir().map(|dir| dir.join("dino-ball").join(file))
 }
 
-fn hsv_to_rgb(h: f32, s: f32, v: f32) -> (f32, f32, f32) {
-    let i = (h * 6.0).floor();
-    let f = h * 6.0 - i;
-    let p = v * (1.0 - s);
-    let q = v * (1.0 - f * s);
-    let t = v * (1.0 - (1.0 - f) * s);
-    match i as i32 % 6 {
-        0 => (v, t, p),
-        1 => (q, v, p),
-        2 => (p, v, t),
-        3 => (p, q, v),
-        4 => (t, p, v),
-        _ => (v, p, q),
-    }
+/// Location of a file in the platform data directory, e.g. `$XDG_DATA_HOME/dino-ball/<file>`.
+pub fn data_path(file: &str) -> Option<std::path::PathBuf> {
+    dirs::data_dir().map(|dir| dir.join("dino-ball").join(file))
 }
 
-pub fn load_and_shift(path: &str, shift: f32) -> Option<Image> {
-    #[cfg(target_os = "macos")]
-    let base_path = if let Ok(exe_path) = std::env::current_exe() {
-        if let Some(exe_dir) = exe_path.parent() {
-            let bundle_assets = exe_dir.join("../Resources/assets");
-            if bundle_assets.exists() {
-                bundle_assets
-            } else {
-                std::path::PathBuf::from("assets")
-            }
-        } else {
-            std::path::PathBuf::from("assets")
-        }
-    } else {
-        std::path::PathBuf::from("assets")
-    };
-
-    #[cfg(not(target_os = "macos"))]
-    let base_path = std::path::PathBuf::from("assets");
+/// Reads a TOML file from the data directory, falling back to the default if it's missing or broken.
+pub fn load_data<T: Default + serde::de::DeserializeOwned>(file: &str) -> T {
+    let Some(path) = data_path(file) else { return T::default() };
+    let Ok(text) = std::fs::read_to_string(&path) else { return T::default() };
+    toml::from_str(&text).unwrap_or_else(|e| {
+        eprintln!("Failed to read {:?}: {}", path, e);
+        T::default()
+    })
+}
 
-    let full_path = base_path.join(path);
-    if let Ok(img) = image::open(&full_path) {
-        let mut rgba = img.into_rgba8();
-        for pixel in rgba.pixels_mut() {
-            let [r, g, b, a] = pixel.0;
-            if a > 0 {
-                let (h, s, v) = rgb_to_hsv(r as f32 / 255.0, g as f32 / 255.0, b as f32 / 255.0);
-                let nh = if s > 0.15 { (h + shift).fract() } else { h };
-                let (nr, ng, nb) = hsv_to_rgb(nh, s, v);
-                pixel.0 = [(nr * 255.0).round() as u8, (ng * 255.0).round() as u8, (nb * 255.0).round() as u8, a];
+/// Writes `value` as TOML to the data directory, creating it if needed.
+pub fn save_data<T: serde::Serialize>(file: &str, value: &T) {
+    let Some(path) = data_path(file) else { return };
+    let result = toml::to_string_pretty(value)
+        .map_err(std::io::Error::other)
+        .and_then(|text| {
+            if let Some(dir) = path.parent() {
+                std::fs::create_dir_all(dir)?;
             }
-        }
-        
-        let width = rgba.width();
-        let height = rgba.height();
-        let data = rgba.into_raw();
-        
-        Some(Image::new(
-            bevy::render::render_resource::Extent3d {
-                width,
-                height,
-                depth_or_array_layers: 1,
-            },
-            bevy::render::render_resource::TextureDimension::D2,
-            data,
-            bevy::render::render_resource::TextureFormat::Rgba8UnormSrgb,
-            bevy::asset::RenderAssetUsages::default(),
-        ))
-    } else {
-        None
+            std::fs::write(&path, text)
+        });
+    if let Err(e) = result {
+        eprintln!("Failed to save {:?}: {}", path, e);
     }
 }